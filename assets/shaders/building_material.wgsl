@@ -1,4 +1,5 @@
 #import bevy_pbr::{
+    pbr_bindings,
     pbr_fragment::pbr_input_from_standard_material,
     pbr_functions::alpha_discard,
 }
@@ -14,6 +15,18 @@ struct BuildingMaterial {
 
 @group(1) @binding(100)
 var<uniform> material: BuildingMaterial;
+@group(1) @binding(101)
+var albedo_array: texture_2d_array<f32>;
+@group(1) @binding(102)
+var albedo_sampler: sampler;
+@group(1) @binding(103)
+var normal_array: texture_2d_array<f32>;
+@group(1) @binding(104)
+var normal_sampler: sampler;
+
+// Metallic and perceptual roughness of every texture layer
+@group(1) @binding(105)
+var<uniform> layer_properties: array<vec4<f32>, 256>;
 
 fn grid(uv: vec2<f32>, period: f32, thickness: f32) -> f32 {
     var grid = fract(uv * period);
@@ -34,6 +47,31 @@ fn fragment(
 ) -> FragmentOutput {
     var pbr_input = pbr_input_from_standard_material(input, is_front);
 
+    // Chunk meshes store the block tint in the vertex color and the texture layer in its alpha
+    var tint = vec3<f32>(1.0);
+    var layer = 0u;
+#ifdef VERTEX_COLORS
+    tint = input.color.rgb;
+    layer = u32(round(input.color.a));
+#endif
+
+    let albedo = textureSample(albedo_array, albedo_sampler, input.uv, layer);
+    pbr_input.material.base_color = vec4<f32>(
+        pbr_bindings::material.base_color.rgb * tint * albedo.rgb,
+        pbr_bindings::material.base_color.a,
+    );
+    pbr_input.material.metallic = layer_properties[layer].x;
+    pbr_input.material.perceptual_roughness = layer_properties[layer].y;
+
+#ifdef VERTEX_TANGENTS
+    // Tangent space normal mapping using the normal map of the block's layer
+    let normal_sample = textureSample(normal_array, normal_sampler, input.uv, layer).rgb * 2.0 - 1.0;
+    let N = normalize(pbr_input.world_normal);
+    let T = normalize(input.world_tangent.xyz - N * dot(input.world_tangent.xyz, N));
+    let B = cross(N, T) * input.world_tangent.w;
+    pbr_input.N = normalize(normal_sample.x * T + normal_sample.y * B + normal_sample.z * N);
+#endif
+
     pbr_input.material.base_color = alpha_discard(
         pbr_input.material,
         pbr_input.material.base_color
@@ -42,10 +80,13 @@ fn fragment(
     var out: FragmentOutput;
     out.color = apply_pbr_lighting(pbr_input);
 
-    var result = 1.0 - grid(input.uv, 1.0, 0.08);
-
     out.color = main_pass_post_lighting_processing(pbr_input, out.color);
-    out.color = mix(out.color, material.grid_color, result);
+
+    // No grid is drawn when the grid color is fully transparent
+    if material.grid_color.a > 0.0 {
+        var result = 1.0 - grid(input.uv, 1.0, 0.08);
+        out.color = mix(out.color, material.grid_color, result);
+    }
 
     return out;
 }
\ No newline at end of file
@@ -8,15 +8,22 @@ use big_space::FloatingOriginPlugin;
 use crate::building::BuildingPlugin;
 use crate::building_material::BuildingMaterialPlugin;
 use crate::camera::{CameraDebugPlugin, CameraPlugin};
+use crate::cockpit::CockpitPlugin;
+use crate::damage::DamagePlugin;
 use crate::free_camera::FreeCameraPlugin;
 use crate::grid::plugin::GridPlugin;
+use crate::gyroscope::GyroscopePlugin;
 use crate::pause::PausePlugin;
 use crate::player::PlayerPlugin;
 use crate::player_controller::PlayerControllerPlugin;
+use crate::power::PowerPlugin;
 use crate::raycast_selection::SelectionPlugin;
 use crate::reticle::ReticlePlugin;
+use crate::rooms::RoomPlugin;
+use crate::save::SavePlugin;
 use crate::settings::{DebugSettingsPlugin, Settings};
 use crate::skybox::SkyboxPlugin;
+use crate::thruster::ThrusterPlugin;
 use crate::UniverseGridPrecision;
 
 pub trait SetupBevyPlugins {
@@ -54,8 +61,12 @@ impl SetupGame for App {
                 GridPlugin,
                 SelectionPlugin,
                 BuildingPlugin,
+                DamagePlugin,
+                RoomPlugin,
+                (ThrusterPlugin, GyroscopePlugin, PowerPlugin, CockpitPlugin),
                 ReticlePlugin,
                 SkyboxPlugin,
+                SavePlugin,
             ))
     }
 }
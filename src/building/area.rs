@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use big_space::FloatingOrigin;
+
+use crate::building_material::Building;
+use crate::camera::ActiveCamera;
+use crate::fixed_update::FixedInput;
+use crate::grid::block::{Block, BLOCK_SIZE};
+use crate::grid::registry::BlockRegistry;
+use crate::grid::{ChunkPos, GridPos};
+use crate::raycast_selection::SelectionSource;
+use crate::UniverseGrid;
+
+use super::events::{CopyAreaRequest, FillAreaRequest};
+use super::hotbar::Hotbar;
+use super::symmetry::{send_mirrored_area, Symmetry};
+use super::targeted_block;
+
+// Larger areas are rejected so that a single drag can't stall the game
+pub const MAX_AREA_BLOCKS: usize = 64 * 64 * 64;
+
+/// Shows the area that will be filled, cleared or copied while dragging with the area tool
+#[derive(Component)]
+pub struct AreaMarker;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AreaAction {
+    Fill(Block),
+    Copy,
+}
+
+struct AreaDrag {
+    grid: Entity,
+    start: GridPos,
+    end: GridPos,
+    action: AreaAction,
+    button: MouseButton,
+}
+
+/// Fills, clears or copies the box between the block where a drag started and the block where it
+/// ended
+#[derive(Resource, Default)]
+pub struct AreaTool {
+    pub enabled: bool,
+    drag: Option<AreaDrag>,
+}
+
+/// Whether the box between two corners is small enough to be filled, cleared or copied
+pub fn within_area_limit(start: GridPos, end: GridPos) -> bool {
+    let size = (start.to_block_coords() - end.to_block_coords()).abs() + IVec3::ONE;
+    if size.x as usize * size.y as usize * size.z as usize > MAX_AREA_BLOCKS {
+        warn!("Areas can't contain more than {MAX_AREA_BLOCKS} blocks");
+        return false;
+    }
+
+    true
+}
+
+pub fn area_tool_enabled(area_tool: Res<AreaTool>) -> bool {
+    area_tool.enabled
+}
+
+pub fn toggle_area_tool(keys: Res<FixedInput<KeyCode>>, mut area_tool: ResMut<AreaTool>) {
+    if keys.just_pressed(KeyCode::G) {
+        area_tool.enabled = !area_tool.enabled;
+        area_tool.drag = None;
+    }
+}
+
+pub fn create_area_requests(
+    mouse_buttons: Res<FixedInput<MouseButton>>,
+    mut area_tool: ResMut<AreaTool>,
+    mut fill_area_requests: EventWriter<FillAreaRequest>,
+    mut copy_area_requests: EventWriter<CopyAreaRequest>,
+    selection_source_query: Query<&SelectionSource, With<ActiveCamera>>,
+    chunk_query: Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+    symmetry_query: Query<&Symmetry, With<Building>>,
+    hotbar: Res<Hotbar>,
+    registry: Res<BlockRegistry>,
+) {
+    let target = targeted_block(&selection_source_query, &chunk_query);
+
+    let Some(drag) = &mut area_tool.drag else {
+        let Some(target) = target else {
+            return;
+        };
+
+        // Filling starts in front of the clicked face, clearing and copying start at the clicked
+        // block
+        if mouse_buttons.just_pressed(MouseButton::Left) {
+            let Some(block) = hotbar.selected_block(&registry) else {
+                return;
+            };
+
+            area_tool.drag = Some(AreaDrag {
+                grid: target.grid,
+                start: target.adjacent,
+                end: target.adjacent,
+                action: AreaAction::Fill(block),
+                button: MouseButton::Left,
+            });
+        } else if mouse_buttons.just_pressed(MouseButton::Right) {
+            area_tool.drag = Some(AreaDrag {
+                grid: target.grid,
+                start: target.selected,
+                end: target.selected,
+                action: AreaAction::Fill(Block::EMPTY),
+                button: MouseButton::Right,
+            });
+        } else if mouse_buttons.just_pressed(MouseButton::Middle) {
+            area_tool.drag = Some(AreaDrag {
+                grid: target.grid,
+                start: target.selected,
+                end: target.selected,
+                action: AreaAction::Copy,
+                button: MouseButton::Middle,
+            });
+        }
+
+        return;
+    };
+
+    // The end of the area only follows the reticle while it is on the same grid
+    if let Some(target) = target.filter(|target| target.grid == drag.grid) {
+        drag.end = match drag.action {
+            AreaAction::Fill(block) if !block.is_empty() => target.adjacent,
+            _ => target.selected,
+        };
+    }
+
+    if mouse_buttons.just_released(drag.button) {
+        match drag.action {
+            AreaAction::Fill(block) => {
+                if within_area_limit(drag.start, drag.end) {
+                    send_mirrored_area(
+                        symmetry_query.get(drag.grid).ok(),
+                        FillAreaRequest {
+                            grid: drag.grid,
+                            start: drag.start,
+                            end: drag.end,
+                            block,
+                        },
+                        &registry,
+                        &mut fill_area_requests,
+                    );
+                }
+            }
+            AreaAction::Copy => copy_area_requests.send(CopyAreaRequest {
+                grid: drag.grid,
+                start: drag.start,
+                end: drag.end,
+            }),
+        }
+
+        area_tool.drag = None;
+    }
+}
+
+/// Positions on the outside of the box between two corners, given in block coordinates
+pub fn area_surface(min: IVec3, max: IVec3) -> impl Iterator<Item = GridPos> {
+    (min.z..=max.z).flat_map(move |z| {
+        (min.y..=max.y).flat_map(move |y| {
+            // Rows through the inside of the box only touch the surface at their ends
+            let inside = y != min.y && y != max.y && z != min.z && z != max.z;
+            let step = if inside { (max.x - min.x).max(1) } else { 1 };
+
+            (min.x..=max.x)
+                .step_by(step as usize)
+                .map(move |x| GridPos::from_block_coords(IVec3::new(x, y, z)))
+        })
+    })
+}
+
+pub fn move_area_marker(
+    area_tool: Res<AreaTool>,
+    mut area_marker_query: Query<
+        (&mut Visibility, &mut Transform, &mut UniverseGrid),
+        (With<AreaMarker>, Without<FloatingOrigin>),
+    >,
+    floating_origin_query: Query<&UniverseGrid, With<FloatingOrigin>>,
+    global_transform_query: Query<&GlobalTransform>,
+) {
+    let Ok((mut area_marker_visibility, mut area_marker_transform, mut area_marker_universe_grid)) =
+        area_marker_query.get_single_mut()
+    else {
+        return;
+    };
+
+    let Some(drag) = &area_tool.drag else {
+        *area_marker_visibility = Visibility::Hidden;
+        return;
+    };
+
+    let (Ok(floating_origin), Ok(grid_transform)) = (
+        floating_origin_query.get_single(),
+        global_transform_query.get(drag.grid),
+    ) else {
+        *area_marker_visibility = Visibility::Hidden;
+        return;
+    };
+
+    let start = drag.start.to_block_coords();
+    let end = drag.end.to_block_coords();
+    let min = start.min(end).as_vec3();
+    let max = start.max(end).as_vec3() + Vec3::ONE;
+
+    *area_marker_universe_grid = *floating_origin;
+    area_marker_transform.translation =
+        grid_transform.transform_point((min + max) / 2.0 * BLOCK_SIZE);
+    area_marker_transform.rotation = grid_transform.to_scale_rotation_translation().1;
+    // The marker's mesh is the size of one block
+    area_marker_transform.scale = max - min;
+    *area_marker_visibility = Visibility::Visible;
+}
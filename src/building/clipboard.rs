@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use big_space::FloatingOrigin;
+
+use crate::camera::ActiveCamera;
+use crate::fixed_update::FixedInput;
+use crate::grid::block::BLOCK_SIZE;
+use crate::grid::blueprint::Blueprint;
+use crate::grid::command::SpawnGrid;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::{ChunkPos, Grid};
+use crate::raycast_selection::SelectionSource;
+use crate::UniverseGrid;
+
+use super::area::within_area_limit;
+use super::events::{CopyAreaRequest, PlaceBlockRequest};
+use super::targeted_block;
+
+// Larger blueprints only show their bounds in the paste preview
+const MAX_PREVIEW_BLOCKS: usize = 1024;
+
+// How far in front of the camera blueprints are pasted as new grids
+const NEW_GRID_DISTANCE: f32 = 10.0;
+
+/// The last copied blueprint, which can be pasted onto grids or as a new grid
+#[derive(Resource, Default)]
+pub struct Clipboard {
+    pub blueprint: Option<Blueprint>,
+    pub pasting: bool,
+}
+
+pub fn pasting(clipboard: Res<Clipboard>) -> bool {
+    clipboard.pasting
+}
+
+pub fn copy_areas(
+    mut copy_area_requests: EventReader<CopyAreaRequest>,
+    grid_query: Query<&Grid>,
+    mut clipboard: ResMut<Clipboard>,
+) {
+    for request in copy_area_requests.read() {
+        if !within_area_limit(request.start, request.end) {
+            continue;
+        }
+
+        let Ok(grid) = grid_query.get(request.grid) else {
+            continue;
+        };
+
+        // Copying an empty area keeps the previous blueprint
+        let blueprint = Blueprint::copy(grid, request.start, request.end, request.start);
+        if blueprint.is_empty() {
+            continue;
+        }
+
+        clipboard.blueprint = Some(blueprint);
+    }
+}
+
+// Ctrl+V starts or stops pasting the clipboard onto grids
+pub fn toggle_pasting(keys: Res<FixedInput<KeyCode>>, mut clipboard: ResMut<Clipboard>) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+        || !keys.just_pressed(KeyCode::V)
+    {
+        return;
+    }
+
+    clipboard.pasting = !clipboard.pasting && clipboard.blueprint.is_some();
+}
+
+// While pasting, R turns the blueprint around the grid's Y axis and Shift+R around its X axis. Left
+// click pastes the blueprint against the targeted face and right click stops pasting.
+pub fn paste_blueprint(
+    keys: Res<FixedInput<KeyCode>>,
+    mouse_buttons: Res<FixedInput<MouseButton>>,
+    mut clipboard: ResMut<Clipboard>,
+    selection_source_query: Query<&SelectionSource, With<ActiveCamera>>,
+    chunk_query: Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+    registry: Res<BlockRegistry>,
+    mut place_block_requests: EventWriter<PlaceBlockRequest>,
+) {
+    if mouse_buttons.just_pressed(MouseButton::Right) {
+        clipboard.pasting = false;
+        return;
+    }
+
+    let Some(blueprint) = &mut clipboard.blueprint else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::R) {
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            blueprint.rotate(0, &registry);
+        } else {
+            blueprint.rotate(1, &registry);
+        }
+    }
+
+    if !mouse_buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(target) = targeted_block(&selection_source_query, &chunk_query) else {
+        return;
+    };
+
+    place_block_requests.send_batch(blueprint.placements(target.adjacent).map(|(pos, block)| {
+        PlaceBlockRequest {
+            grid: target.grid,
+            pos,
+            block,
+            record_history: true,
+        }
+    }));
+}
+
+// Ctrl+Shift+V pastes the clipboard as a new grid centered in front of the camera
+pub fn paste_as_new_grid(
+    keys: Res<FixedInput<KeyCode>>,
+    clipboard: Res<Clipboard>,
+    camera_query: Query<&GlobalTransform, With<ActiveCamera>>,
+    floating_origin_query: Query<&UniverseGrid, With<FloatingOrigin>>,
+    mut commands: Commands,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+        || !keys.just_pressed(KeyCode::V)
+    {
+        return;
+    }
+
+    let Some((min, max)) = clipboard
+        .blueprint
+        .as_ref()
+        .and_then(|blueprint| blueprint.bounds())
+    else {
+        return;
+    };
+
+    let (Ok(camera_transform), Ok(floating_origin)) = (
+        camera_query.get_single(),
+        floating_origin_query.get_single(),
+    ) else {
+        return;
+    };
+
+    let rotation = camera_transform.to_scale_rotation_translation().1;
+    let center = (min + max + IVec3::ONE).as_vec3() / 2.0 * BLOCK_SIZE;
+    let translation = camera_transform.translation()
+        + camera_transform.forward() * NEW_GRID_DISTANCE
+        - rotation * center;
+
+    let mut spawn_grid = SpawnGrid::new(
+        Transform::from_translation(translation).with_rotation(rotation),
+        clipboard.blueprint.as_ref().unwrap().to_grid(),
+    );
+    spawn_grid.grid_cell = *floating_origin;
+
+    commands.add(spawn_grid);
+}
+
+pub fn draw_paste_preview(
+    clipboard: Res<Clipboard>,
+    selection_source_query: Query<&SelectionSource, With<ActiveCamera>>,
+    chunk_query: Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+    grid_query: Query<&GlobalTransform, With<Grid>>,
+    mut gizmos: Gizmos,
+) {
+    if !clipboard.pasting {
+        return;
+    }
+
+    let Some(blueprint) = &clipboard.blueprint else {
+        return;
+    };
+    let Some((min, max)) = blueprint.bounds() else {
+        return;
+    };
+
+    let Some(target) = targeted_block(&selection_source_query, &chunk_query) else {
+        return;
+    };
+    let Ok(grid_transform) = grid_query.get(target.grid) else {
+        return;
+    };
+
+    let rotation = grid_transform.to_scale_rotation_translation().1;
+    let anchor = target.adjacent.to_block_coords();
+
+    let min = (anchor + min).as_vec3();
+    let max = (anchor + max + IVec3::ONE).as_vec3();
+    gizmos.cuboid(
+        Transform::from_translation(grid_transform.transform_point((min + max) / 2.0 * BLOCK_SIZE))
+            .with_rotation(rotation)
+            .with_scale((max - min) * BLOCK_SIZE),
+        Color::YELLOW,
+    );
+
+    if blueprint.blocks.len() > MAX_PREVIEW_BLOCKS {
+        return;
+    }
+
+    for (pos, _) in blueprint.placements(target.adjacent) {
+        let center = (pos.to_block_coords().as_vec3() + Vec3::splat(0.5)) * BLOCK_SIZE;
+
+        gizmos.cuboid(
+            Transform::from_translation(grid_transform.transform_point(center))
+                .with_rotation(rotation)
+                .with_scale(Vec3::splat(BLOCK_SIZE)),
+            Color::CYAN,
+        );
+    }
+}
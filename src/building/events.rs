@@ -7,4 +7,43 @@ pub struct PlaceBlockRequest {
     pub grid: Entity,
     pub pos: GridPos,
     pub block: Block,
+    // Requests made by undoing or redoing edits are not recorded again
+    pub record_history: bool,
+}
+
+/// Places the block in every position of the box between two corners
+#[derive(Event)]
+pub struct FillAreaRequest {
+    pub grid: Entity,
+    pub start: GridPos,
+    pub end: GridPos,
+    pub block: Block,
+}
+
+impl FillAreaRequest {
+    /// The lowest and highest block coordinates in the area
+    pub fn bounds(&self) -> (IVec3, IVec3) {
+        let start = self.start.to_block_coords();
+        let end = self.end.to_block_coords();
+
+        (start.min(end), start.max(end))
+    }
+}
+
+/// Copies the box between two corners into the clipboard, anchored at the start corner
+#[derive(Event)]
+pub struct CopyAreaRequest {
+    pub grid: Entity,
+    pub start: GridPos,
+    pub end: GridPos,
+}
+
+#[derive(Event)]
+pub struct UndoRequest {
+    pub grid: Entity,
+}
+
+#[derive(Event)]
+pub struct RedoRequest {
+    pub grid: Entity,
 }
@@ -0,0 +1,392 @@
+use std::collections::VecDeque;
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::camera::ActiveCamera;
+use crate::fixed_update::FixedInput;
+use crate::grid::block::Block;
+use crate::grid::chunk::Chunk;
+use crate::grid::command::SpawnGrid;
+use crate::grid::{ChunkPos, Grid, GridPos};
+use crate::raycast_selection::SelectionSource;
+use crate::UniverseGrid;
+
+use super::events::{FillAreaRequest, PlaceBlockRequest, RedoRequest, UndoRequest};
+
+// Older transactions are forgotten once a grid has this many
+pub const MAX_HISTORY_LENGTH: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockChange {
+    pub pos: GridPos,
+    pub previous: Block,
+    pub new: Block,
+}
+
+/// The block changes made by a single input action
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transaction {
+    pub changes: Vec<BlockChange>,
+}
+
+impl Transaction {
+    // Moves the changes that match the predicate into a transaction of their own
+    fn split_off(&mut self, predicate: impl Fn(&BlockChange) -> bool) -> Transaction {
+        let (moved, kept) = self.changes.drain(..).partition(predicate);
+        self.changes = kept;
+
+        Transaction { changes: moved }
+    }
+}
+
+/// Undo and redo stacks of the edits made to a grid
+#[derive(Clone, Debug, Default)]
+pub struct EditHistory {
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    // Where the grid was when it was last edited, so that it can be respawned there
+    location: Option<(Transform, UniverseGrid)>,
+}
+
+impl EditHistory {
+    /// Adds a transaction to the history. Making a new edit discards the edits that were undone.
+    pub fn record(&mut self, transaction: Transaction) {
+        if transaction.changes.is_empty() {
+            return;
+        }
+
+        self.redo.clear();
+        self.undo.push_back(transaction);
+
+        if self.undo.len() > MAX_HISTORY_LENGTH {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Returns the requests that revert the latest transaction
+    pub fn undo(&mut self, grid: Entity) -> Option<Vec<PlaceBlockRequest>> {
+        let transaction = self.undo.pop_back()?;
+
+        let requests = transaction
+            .changes
+            .iter()
+            .rev()
+            .map(|change| PlaceBlockRequest {
+                grid,
+                pos: change.pos,
+                block: change.previous,
+                record_history: false,
+            })
+            .collect();
+
+        self.redo.push(transaction);
+
+        Some(requests)
+    }
+
+    /// Returns the requests that make the latest undone transaction again
+    pub fn redo(&mut self, grid: Entity) -> Option<Vec<PlaceBlockRequest>> {
+        let transaction = self.redo.pop()?;
+
+        let requests = transaction
+            .changes
+            .iter()
+            .map(|change| PlaceBlockRequest {
+                grid,
+                pos: change.pos,
+                block: change.new,
+                record_history: false,
+            })
+            .collect();
+
+        self.undo.push_back(transaction);
+
+        Some(requests)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Moves the changes to blocks that are in `grid` into a history of their own. Used when the
+    /// blocks are split off into a new grid.
+    pub fn split_off(&mut self, grid: &Grid) -> EditHistory {
+        let in_grid = |change: &BlockChange| !grid.get_block(change.pos).is_empty();
+
+        let mut history = EditHistory {
+            undo: self
+                .undo
+                .iter_mut()
+                .map(|transaction| transaction.split_off(in_grid))
+                .collect(),
+            redo: self
+                .redo
+                .iter_mut()
+                .map(|transaction| transaction.split_off(in_grid))
+                .collect(),
+            location: self.location,
+        };
+
+        self.remove_empty_transactions();
+        history.remove_empty_transactions();
+
+        history
+    }
+
+    /// Calls `f` on every change in the history
+    pub fn map_changes(&mut self, mut f: impl FnMut(&mut BlockChange)) {
+        for transaction in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            transaction.changes.iter_mut().for_each(&mut f);
+        }
+    }
+
+    fn remove_empty_transactions(&mut self) {
+        self.undo
+            .retain(|transaction| !transaction.changes.is_empty());
+        self.redo
+            .retain(|transaction| !transaction.changes.is_empty());
+    }
+
+    /// Adds the edits of a grid that was welded onto this one, as if they were made before this
+    /// grid's own edits
+    pub fn merge(&mut self, mut older: EditHistory) {
+        older.undo.append(&mut self.undo);
+        self.undo = older.undo;
+        while self.undo.len() > MAX_HISTORY_LENGTH {
+            self.undo.pop_front();
+        }
+
+        older.redo.append(&mut self.redo);
+        self.redo = older.redo;
+    }
+}
+
+/// The edit history of every edited grid, by grid entity. Histories outlive their grids, so that
+/// removing the last block of a grid can still be undone.
+#[derive(Resource, Default)]
+pub struct EditHistories(pub HashMap<Entity, EditHistory>);
+
+// Spawns a grid in place of a grid that was despawned and moves the old grid's history over to it
+struct RespawnGrid {
+    grid: Entity,
+    requests: Vec<PlaceBlockRequest>,
+}
+
+impl Command for RespawnGrid {
+    fn apply(self, world: &mut World) {
+        let mut histories = world.resource_mut::<EditHistories>();
+        let Some((transform, grid_cell)) = histories
+            .0
+            .get(&self.grid)
+            .and_then(|history| history.location)
+        else {
+            return;
+        };
+
+        let mut grid = Grid::new();
+        for request in self.requests {
+            if request.block.is_empty() {
+                continue;
+            }
+
+            if grid.get_chunk(request.pos.chunk_pos).is_none() {
+                grid.set_chunk(
+                    request.pos.chunk_pos,
+                    Some(Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY)),
+                );
+            }
+
+            grid.get_chunk_mut(request.pos.chunk_pos)
+                .unwrap()
+                .set_by_block_pos(request.pos.block_pos, request.block);
+        }
+
+        if grid.chunks().next().is_none() {
+            return;
+        }
+
+        let history = histories.0.remove(&self.grid).unwrap();
+
+        let grid_entity = SpawnGrid {
+            grid_cell,
+            ..SpawnGrid::new(transform, grid)
+        }
+        .spawn(world);
+
+        world
+            .resource_mut::<EditHistories>()
+            .0
+            .insert(grid_entity, history);
+        world.resource_mut::<LastEditedGrid>().0 = Some(grid_entity);
+    }
+}
+
+// Undo and redo act on this grid when the player isn't looking at one
+#[derive(Resource, Default)]
+pub struct LastEditedGrid(pub Option<Entity>);
+
+#[derive(Default)]
+struct PendingTransaction {
+    transaction: Transaction,
+    // Index of the change made to each block
+    change_indices: HashMap<GridPos, usize>,
+}
+
+impl PendingTransaction {
+    fn add(&mut self, grid: &Grid, pos: GridPos, block: Block) {
+        // A block changed twice keeps the block it had before the first change
+        match self.change_indices.get(&pos) {
+            Some(&index) => self.transaction.changes[index].new = block,
+            None => {
+                let previous = grid.get_block(pos);
+                if previous == block {
+                    return;
+                }
+
+                self.change_indices
+                    .insert(pos, self.transaction.changes.len());
+                self.transaction.changes.push(BlockChange {
+                    pos,
+                    previous,
+                    new: block,
+                });
+            }
+        }
+    }
+}
+
+// Every request for a grid handled in the same update comes from one input action, so they are
+// grouped into one transaction. Areas are handled before single blocks, like when they are placed.
+pub fn record_edit_history(
+    mut fill_area_requests: EventReader<FillAreaRequest>,
+    mut place_block_requests: EventReader<PlaceBlockRequest>,
+    grid_query: Query<(&Grid, &Transform, Option<&UniverseGrid>)>,
+    mut histories: ResMut<EditHistories>,
+    mut last_edited_grid: ResMut<LastEditedGrid>,
+) {
+    let mut transactions: HashMap<Entity, PendingTransaction> = HashMap::new();
+
+    for request in fill_area_requests.read() {
+        let Ok((grid, _, _)) = grid_query.get(request.grid) else {
+            continue;
+        };
+
+        let pending = transactions.entry(request.grid).or_default();
+        let (min, max) = request.bounds();
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let pos = GridPos::from_block_coords(IVec3::new(x, y, z));
+                    pending.add(grid, pos, request.block);
+                }
+            }
+        }
+    }
+
+    for request in place_block_requests.read() {
+        if !request.record_history {
+            continue;
+        }
+
+        let Ok((grid, _, _)) = grid_query.get(request.grid) else {
+            continue;
+        };
+
+        transactions
+            .entry(request.grid)
+            .or_default()
+            .add(grid, request.pos, request.block);
+    }
+
+    for (grid_entity, pending) in transactions {
+        let Ok((_, transform, grid_cell)) = grid_query.get(grid_entity) else {
+            continue;
+        };
+
+        let history = histories.0.entry(grid_entity).or_default();
+        history.record(pending.transaction);
+        history.location = Some((*transform, grid_cell.copied().unwrap_or_default()));
+
+        last_edited_grid.0 = Some(grid_entity);
+    }
+}
+
+// Undoing or redoing edits of a grid that was despawned spawns it again
+pub fn apply_edit_history(
+    mut undo_requests: EventReader<UndoRequest>,
+    mut redo_requests: EventReader<RedoRequest>,
+    mut histories: ResMut<EditHistories>,
+    grid_query: Query<(&Transform, Option<&UniverseGrid>), With<Grid>>,
+    mut place_block_requests: EventWriter<PlaceBlockRequest>,
+    mut commands: Commands,
+) {
+    let undos = undo_requests.read().map(|request| (request.grid, true));
+    let redos = redo_requests.read().map(|request| (request.grid, false));
+
+    for (grid, undo) in undos.chain(redos) {
+        let Some(history) = histories.0.get_mut(&grid) else {
+            continue;
+        };
+
+        let requests = if undo {
+            history.undo(grid)
+        } else {
+            history.redo(grid)
+        };
+        let Some(requests) = requests else {
+            continue;
+        };
+
+        match grid_query.get(grid) {
+            Ok((transform, grid_cell)) => {
+                history.location = Some((*transform, grid_cell.copied().unwrap_or_default()));
+                place_block_requests.send_batch(requests);
+            }
+            Err(_) => commands.add(RespawnGrid { grid, requests }),
+        }
+    }
+}
+
+// Ctrl+Z undoes, Ctrl+Y and Ctrl+Shift+Z redo
+pub fn create_history_requests(
+    keys: Res<FixedInput<KeyCode>>,
+    selection_source_query: Query<&SelectionSource, With<ActiveCamera>>,
+    chunk_query: Query<&Parent, With<ChunkPos>>,
+    last_edited_grid: Res<LastEditedGrid>,
+    mut undo_requests: EventWriter<UndoRequest>,
+    mut redo_requests: EventWriter<RedoRequest>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let undo = keys.just_pressed(KeyCode::Z) && !shift;
+    let redo = keys.just_pressed(KeyCode::Y) || (keys.just_pressed(KeyCode::Z) && shift);
+    if !undo && !redo {
+        return;
+    }
+
+    let selected_grid = selection_source_query
+        .get_single()
+        .ok()
+        .and_then(|selection_source| selection_source.intersection())
+        .and_then(|(chunk_entity, _)| chunk_query.get(chunk_entity).ok())
+        .map(|parent| parent.get());
+
+    let Some(grid) = selected_grid.or(last_edited_grid.0) else {
+        return;
+    };
+
+    if undo {
+        undo_requests.send(UndoRequest { grid });
+    } else {
+        redo_requests.send(RedoRequest { grid });
+    }
+}
@@ -0,0 +1,233 @@
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::building_material::Building;
+use crate::grid::block::{Block, BlockId};
+use crate::grid::mesh::generate_block_mesh;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::shape::{BlockShape, Orientation};
+
+use super::BuildMarker;
+
+pub const HOTBAR_SLOTS: usize = 9;
+
+const SLOT_KEYS: [KeyCode; HOTBAR_SLOTS] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+// Keys that turn the block a quarter turn forwards and backwards around each of the grid's axes
+const ROTATION_KEYS: [(KeyCode, KeyCode); 3] = [
+    (KeyCode::Home, KeyCode::End),
+    (KeyCode::Insert, KeyCode::Delete),
+    (KeyCode::PageUp, KeyCode::PageDown),
+];
+
+/// The block types the player can quickly switch between while building, along with the shape and
+/// orientation blocks are placed with
+#[derive(Resource, Default)]
+pub struct Hotbar {
+    pub slots: [Option<BlockId>; HOTBAR_SLOTS],
+    pub selected: usize,
+    pub shape: BlockShape,
+    pub orientation: Orientation,
+    pub palette_open: bool,
+}
+
+impl Hotbar {
+    /// The block placed by building, None if the selected slot is empty
+    pub fn selected_block(&self, registry: &BlockRegistry) -> Option<Block> {
+        self.slots[self.selected]
+            .map(|id| registry.orient(Block::new(id), self.shape, self.orientation))
+    }
+
+    /// Moves the selection by a number of slots, wrapping around at either end
+    pub fn scroll(&mut self, slots: i32) {
+        self.selected = (self.selected as i32 + slots).rem_euclid(HOTBAR_SLOTS as i32) as usize;
+    }
+
+    /// Puts registered blocks that aren't on the hotbar yet into its empty slots
+    pub fn fill_from_registry(&mut self, registry: &BlockRegistry) {
+        let mut new_blocks = registry
+            .definitions()
+            .map(|(id, _)| id)
+            .filter(|&id| !Block::new(id).is_empty() && !self.slots.contains(&Some(id)))
+            .collect::<Vec<BlockId>>()
+            .into_iter();
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            *slot = new_blocks.next();
+        }
+    }
+}
+
+// Block definitions are loaded in the background, so they are added to the hotbar once they arrive
+pub fn fill_hotbar(registry: Res<BlockRegistry>, mut hotbar: ResMut<Hotbar>) {
+    if registry.is_changed() {
+        hotbar.fill_from_registry(&registry);
+    }
+}
+
+// While building, number keys pick a slot directly and the scroll wheel moves through the slots. P
+// opens the palette of every block type.
+pub fn select_hotbar_slot(
+    building_query: Query<(), With<Building>>,
+    keys: Res<Input<KeyCode>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut hotbar: ResMut<Hotbar>,
+) {
+    // The scroll wheel is read either way, so that scrolling outside of build mode is dropped
+    let scroll: f32 = mouse_wheel_events.read().map(|event| event.y).sum();
+    if building_query.is_empty() {
+        return;
+    }
+
+    if let Some(slot) = SLOT_KEYS.iter().position(|&key| keys.just_pressed(key)) {
+        hotbar.selected = slot;
+    }
+
+    if scroll != 0.0 {
+        // Scrolling down moves to the next slot
+        hotbar.scroll(-scroll.signum() as i32);
+    }
+
+    if keys.just_pressed(KeyCode::P) {
+        hotbar.palette_open = !hotbar.palette_open;
+    }
+}
+
+// T switches to the next shape and Shift+T to the previous one. Home and End, Insert and Delete, and
+// Page Up and Page Down turn the block around the grid's X, Y and Z axes.
+pub fn shape_build_block(keys: Res<Input<KeyCode>>, mut hotbar: ResMut<Hotbar>) {
+    if keys.just_pressed(KeyCode::T) {
+        let step = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            BlockShape::ALL.len() - 1
+        } else {
+            1
+        };
+
+        hotbar.shape =
+            BlockShape::ALL[(hotbar.shape.index() as usize + step) % BlockShape::ALL.len()];
+    }
+
+    for (axis, (forwards, backwards)) in ROTATION_KEYS.into_iter().enumerate() {
+        if keys.just_pressed(forwards) {
+            hotbar.orientation = hotbar.orientation.rotated(axis);
+        }
+
+        // Three quarter turns forwards are one backwards
+        if keys.just_pressed(backwards) {
+            for _ in 0..3 {
+                hotbar.orientation = hotbar.orientation.rotated(axis);
+            }
+        }
+    }
+}
+
+fn block_name(registry: &BlockRegistry, block: Option<BlockId>) -> &str {
+    block
+        .and_then(|id| registry.get(id))
+        .map_or("", |definition| definition.name.as_str())
+}
+
+pub fn draw_hotbar(
+    building_query: Query<(), With<Building>>,
+    mut contexts: EguiContexts,
+    mut hotbar: ResMut<Hotbar>,
+    registry: Res<BlockRegistry>,
+) {
+    if building_query.is_empty() {
+        return;
+    }
+
+    egui::Area::new("hotbar")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -16.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Shape: {}", hotbar.shape.name()));
+
+            ui.horizontal(|ui| {
+                for slot in 0..HOTBAR_SLOTS {
+                    let label = format!(
+                        "{}\n{}",
+                        slot + 1,
+                        block_name(&registry, hotbar.slots[slot])
+                    );
+
+                    if ui
+                        .selectable_label(hotbar.selected == slot, label)
+                        .clicked()
+                    {
+                        hotbar.selected = slot;
+                    }
+                }
+            });
+        });
+
+    if !hotbar.palette_open {
+        return;
+    }
+
+    // Choosing a block from the palette puts it into the selected slot
+    let mut palette_open = true;
+    egui::Window::new("Blocks")
+        .open(&mut palette_open)
+        .show(contexts.ctx_mut(), |ui| {
+            for (id, definition) in registry.definitions() {
+                if Block::new(id).is_empty() {
+                    continue;
+                }
+
+                let selected = hotbar.slots[hotbar.selected] == Some(id);
+                if ui.selectable_label(selected, &definition.name).clicked() {
+                    let slot = hotbar.selected;
+                    hotbar.slots[slot] = Some(id);
+                }
+            }
+        });
+    hotbar.palette_open = palette_open;
+}
+
+// The build marker takes on the color and shape of the selected block
+pub fn update_build_marker(
+    hotbar: Res<Hotbar>,
+    registry: Res<BlockRegistry>,
+    mut build_marker_query: Query<
+        (&Handle<StandardMaterial>, &mut Handle<Mesh>),
+        With<BuildMarker>,
+    >,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut marker_shape: Local<Option<BlockShape>>,
+) {
+    if !hotbar.is_changed() && !registry.is_changed() {
+        return;
+    }
+
+    let Ok((material_handle, mut mesh_handle)) = build_marker_query.get_single_mut() else {
+        return;
+    };
+
+    if *marker_shape != Some(hotbar.shape) {
+        *mesh_handle = meshes.add(generate_block_mesh(hotbar.shape));
+        *marker_shape = Some(hotbar.shape);
+    }
+
+    let Some(material) = materials.get_mut(material_handle) else {
+        return;
+    };
+
+    let tint = hotbar
+        .selected_block(&registry)
+        .and_then(|block| registry.get(block.id))
+        .map_or([1.0; 3], |definition| definition.tint);
+
+    material.base_color = Color::rgba(tint[0], tint[1], tint[2], 0.5);
+}
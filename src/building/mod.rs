@@ -1,36 +1,49 @@
 use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_egui::EguiPlugin;
 use big_space::FloatingOrigin;
 
-use crate::building_material::BuildingMaterialHandle;
+use crate::building_material::{Building, BuildingMaterialHandle};
 use crate::camera::ActiveCamera;
 use crate::fixed_update::{FixedInput, FixedUpdateSet};
-use crate::grid::block::{Block, BlockMaterial, BLOCK_SIZE};
-use crate::grid::chunk::{BlockPos, Chunk, ChunkBundle, ChunkChanged};
-use crate::grid::command::DespawnChunk;
-use crate::grid::{ChunkPos, Grid, GridPos};
+use crate::grid::block::{Block, BLOCK_SIZE};
+use crate::grid::chunk::{Chunk, ChunkBundle, ChunkChanged, CHUNK_SIZE};
+use crate::grid::command::{DespawnChunk, SplitGrid};
+use crate::grid::registry::BlockRegistry;
+use crate::grid::weld::{touches_grid, LatticeTransform, WeldGrids};
+use crate::grid::{block_at_surface, snap_to_grid, ChunkPos, Grid, GridPos, NEIGHBOR_OFFSETS};
+use crate::power::GridPower;
 use crate::raycast_selection::SelectionSource;
+use crate::rooms::Rooms;
 use crate::UniverseGrid;
 
-use self::events::PlaceBlockRequest;
-
+use self::area::{
+    area_surface, area_tool_enabled, create_area_requests, move_area_marker, toggle_area_tool,
+    AreaTool,
+};
+use self::clipboard::{
+    copy_areas, draw_paste_preview, paste_as_new_grid, paste_blueprint, pasting, toggle_pasting,
+    Clipboard,
+};
+use self::events::{CopyAreaRequest, FillAreaRequest, PlaceBlockRequest, RedoRequest, UndoRequest};
+use self::history::{
+    apply_edit_history, create_history_requests, record_edit_history, EditHistories, LastEditedGrid,
+};
+use self::hotbar::{
+    draw_hotbar, fill_hotbar, select_hotbar_slot, shape_build_block, update_build_marker, Hotbar,
+};
+use self::symmetry::{configure_symmetry, draw_mirror_planes, send_mirrored, Symmetry};
+
+pub mod area;
+pub mod clipboard;
 pub mod events;
+pub mod history;
+pub mod hotbar;
+pub mod symmetry;
 
 #[derive(Component)]
 pub struct BuildMarker;
 
-fn snap_to_grid(point: Vec3, snap_resolution: f32) -> Vec3 {
-    // This extra rounding smoothes out any jittering
-    let rounded_x = (point.x * 1000.0).round();
-    let rounded_y = (point.y * 1000.0).round();
-    let rounded_z = (point.z * 1000.0).round();
-
-    let x = (rounded_x * 1.0 / (snap_resolution * 1000.0)).floor() / (1.0 / snap_resolution);
-    let y = (rounded_y * 1.0 / (snap_resolution * 1000.0)).floor() / (1.0 / snap_resolution);
-    let z = (rounded_z * 1.0 / (snap_resolution * 1000.0)).floor() / (1.0 / snap_resolution);
-
-    Vec3::new(x, y, z)
-}
-
 fn move_build_marker(
     mut build_marker_query: Query<
         (&mut Visibility, &mut Transform, &mut UniverseGrid),
@@ -39,6 +52,7 @@ fn move_build_marker(
     selection_source_query: Query<&SelectionSource, With<ActiveCamera>>,
     floating_origin_query: Query<&UniverseGrid, With<FloatingOrigin>>,
     global_transform_query: Query<&GlobalTransform>,
+    hotbar: Res<Hotbar>,
 ) {
     let Ok((
         mut build_marker_visibility,
@@ -78,91 +92,123 @@ fn move_build_marker(
 
     *build_marker_universe_grid = *floating_origin;
     build_marker_transform.translation = chunk_transform_affine.transform_point(block_pos);
-    build_marker_transform.rotation = chunk_transform.to_scale_rotation_translation().1;
+    // The marker previews the orientation the block will be placed with
+    build_marker_transform.rotation =
+        chunk_transform.to_scale_rotation_translation().1 * hotbar.orientation.rotation();
     *build_marker_visibility = Visibility::Visible;
 }
 
+/// The block the reticle is pointing at and the position in front of the face that was hit
+pub struct TargetedBlock {
+    pub grid: Entity,
+    pub selected: GridPos,
+    pub adjacent: GridPos,
+}
+
+pub fn targeted_block(
+    selection_source_query: &Query<&SelectionSource, With<ActiveCamera>>,
+    chunk_query: &Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+) -> Option<TargetedBlock> {
+    let selection_source = selection_source_query.get_single().ok()?;
+    let (chunk_entity, intersection) = selection_source.intersection()?;
+    let (chunk_transform, chunk_pos, chunk_parent) = chunk_query.get(chunk_entity).ok()?;
+
+    let (selected, face) = block_at_surface(
+        chunk_transform,
+        *chunk_pos,
+        intersection.point,
+        intersection.normal,
+    );
+    let adjacent = selected + (face.x as i16, face.y as i16, face.z as i16);
+
+    Some(TargetedBlock {
+        grid: chunk_parent.get(),
+        selected,
+        adjacent,
+    })
+}
+
 fn create_build_request_events(
     mouse_buttons: Res<FixedInput<MouseButton>>,
     mut place_block_requests: EventWriter<PlaceBlockRequest>,
     selection_source_query: Query<&SelectionSource, With<ActiveCamera>>,
     chunk_query: Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+    hotbar: Res<Hotbar>,
+    registry: Res<BlockRegistry>,
+    symmetry_query: Query<&Symmetry, With<Building>>,
 ) {
-    let Ok(selection_source) = selection_source_query.get_single() else {
-        return;
-    };
-    let Some((chunk_entity, intersection)) = selection_source.intersection() else {
+    let Some(target) = targeted_block(&selection_source_query, &chunk_query) else {
         return;
     };
-
-    let Ok((chunk_transform, chunk_pos, chunk_parent)) = chunk_query.get(chunk_entity) else {
-        return;
-    };
-
-    let chunk_transform_affine = chunk_transform.affine();
-    let chunk_transform_inverse = chunk_transform_affine.inverse();
-
-    let inverse_normal = chunk_transform_inverse
-        .transform_vector3(intersection.normal)
-        .round();
-
-    let snapped_intersection = snap_to_grid(
-        chunk_transform_inverse.transform_point(intersection.point),
-        BLOCK_SIZE,
-    ) / BLOCK_SIZE
-        - inverse_normal * 0.5;
-
-    let block_pos = BlockPos {
-        x: snapped_intersection.x.floor() as u8,
-        y: snapped_intersection.y.floor() as u8,
-        z: snapped_intersection.z.floor() as u8,
-    };
+    let symmetry = symmetry_query.get(target.grid).ok();
 
     if mouse_buttons.just_pressed(MouseButton::Left) {
-        let selected_pos = GridPos {
-            chunk_pos: *chunk_pos,
-            block_pos,
+        let Some(block) = hotbar.selected_block(&registry) else {
+            return;
         };
 
-        let new_block_pos = selected_pos
-            + (
-                inverse_normal.x as i16,
-                inverse_normal.y as i16,
-                inverse_normal.z as i16,
-            );
-
-        place_block_requests.send(PlaceBlockRequest {
-            grid: chunk_parent.get(),
-            pos: new_block_pos,
-            block: Block {
-                material: BlockMaterial::Aluminum,
+        send_mirrored(
+            symmetry,
+            PlaceBlockRequest {
+                grid: target.grid,
+                pos: target.adjacent,
+                block,
+                record_history: true,
             },
-        })
+            &registry,
+            &mut place_block_requests,
+        );
     } else if mouse_buttons.just_pressed(MouseButton::Right) {
-        let selected_pos = GridPos {
-            chunk_pos: *chunk_pos,
-            block_pos,
-        };
-
-        place_block_requests.send(PlaceBlockRequest {
-            grid: chunk_parent.get(),
-            pos: selected_pos,
-            block: Block {
-                material: BlockMaterial::Empty,
+        send_mirrored(
+            symmetry,
+            PlaceBlockRequest {
+                grid: target.grid,
+                pos: target.selected,
+                block: Block::EMPTY,
+                record_history: true,
             },
-        })
+            &registry,
+            &mut place_block_requests,
+        );
+    }
+}
+
+// Faces on chunk borders depend on the neighboring chunk, so it needs to be remeshed as well
+fn mark_neighboring_chunks_dirty(grid: &Grid, pos: GridPos, dirty_chunks: &mut HashSet<Entity>) {
+    for offset in NEIGHBOR_OFFSETS {
+        let neighbor_pos = pos + offset;
+        if neighbor_pos.chunk_pos == pos.chunk_pos {
+            continue;
+        }
+
+        if let Some(chunk) = grid.get_chunk(neighbor_pos.chunk_pos) {
+            dirty_chunks.insert(chunk.entity);
+        }
     }
 }
 
-struct DeleteChunkData {
-    pub chunk_entity: Entity,
+// Spawns an empty chunk as a child of the grid
+fn spawn_chunk(
+    commands: &mut Commands,
+    material_handle: &BuildingMaterialHandle,
+    grid: Entity,
+    chunk_pos: ChunkPos,
+) -> Chunk {
+    let chunk_entity = commands
+        .spawn((ChunkBundle::new(chunk_pos), material_handle.0.clone()))
+        .id();
+    commands.entity(grid).add_child(chunk_entity);
+
+    Chunk::filled(chunk_entity, Block::EMPTY)
 }
 
+// Areas are placed before single blocks
 fn place_blocks(
-    mut dirty_chunks: Local<Vec<Entity>>,
-    mut chunks_to_delete: Local<Vec<DeleteChunkData>>,
+    mut dirty_chunks: Local<HashSet<Entity>>,
+    mut chunks_to_delete: Local<HashSet<Entity>>,
+    mut fill_area_requests: EventReader<FillAreaRequest>,
     mut place_block_requests: EventReader<PlaceBlockRequest>,
-    mut grid_query: Query<&mut Grid>,
+    mut grid_query: Query<(&mut Grid, Option<&mut GridPower>, Option<&mut Rooms>)>,
     mut commands: Commands,
     material_handle: Res<BuildingMaterialHandle>,
     mut chunk_changed_writer: EventWriter<ChunkChanged>,
@@ -170,32 +216,121 @@ fn place_blocks(
     dirty_chunks.clear();
     chunks_to_delete.clear();
 
+    // Blocks removed from each grid
+    let mut grids_to_split: HashMap<Entity, Vec<GridPos>> = HashMap::new();
+
+    for request in fill_area_requests.read() {
+        let Ok((mut grid, mut power, mut rooms)) = grid_query.get_mut(request.grid) else {
+            continue;
+        };
+
+        let (min, max) = request.bounds();
+        grid.repair_area(min, max);
+
+        // Each chunk is written once, with the part of the area inside it
+        let min_chunk = GridPos::from_block_coords(min).chunk_pos;
+        let max_chunk = GridPos::from_block_coords(max).chunk_pos;
+        for chunk_z in min_chunk.z..=max_chunk.z {
+            for chunk_y in min_chunk.y..=max_chunk.y {
+                for chunk_x in min_chunk.x..=max_chunk.x {
+                    let chunk_pos = ChunkPos::new(chunk_x, chunk_y, chunk_z);
+                    let chunk_origin = IVec3::new(chunk_x as i32, chunk_y as i32, chunk_z as i32)
+                        * CHUNK_SIZE as i32;
+                    let chunk_min = min.max(chunk_origin) - chunk_origin;
+                    let chunk_max =
+                        max.min(chunk_origin + IVec3::splat(CHUNK_SIZE as i32 - 1)) - chunk_origin;
+
+                    if grid.get_chunk(chunk_pos).is_none() {
+                        if request.block.is_empty() {
+                            continue;
+                        }
+
+                        let chunk =
+                            spawn_chunk(&mut commands, &material_handle, request.grid, chunk_pos);
+                        grid.set_chunk(chunk_pos, Some(chunk));
+                    }
+
+                    let chunk = grid.get_chunk_mut(chunk_pos).unwrap();
+                    for z in chunk_min.z..=chunk_max.z {
+                        for y in chunk_min.y..=chunk_max.y {
+                            for x in chunk_min.x..=chunk_max.x {
+                                chunk.set(x as u8, y as u8, z as u8, request.block);
+                            }
+                        }
+                    }
+
+                    if chunk.is_empty() {
+                        chunks_to_delete.insert(chunk.entity);
+                    } else {
+                        chunks_to_delete.remove(&chunk.entity);
+                        dirty_chunks.insert(chunk.entity);
+                    }
+                }
+            }
+        }
+
+        // Chunks next to the area are remeshed for the faces on their borders
+        let border_min = GridPos::from_block_coords(min - IVec3::ONE).chunk_pos;
+        let border_max = GridPos::from_block_coords(max + IVec3::ONE).chunk_pos;
+        for chunk_z in border_min.z..=border_max.z {
+            for chunk_y in border_min.y..=border_max.y {
+                for chunk_x in border_min.x..=border_max.x {
+                    if let Some(chunk) = grid.get_chunk(ChunkPos::new(chunk_x, chunk_y, chunk_z)) {
+                        if !chunks_to_delete.contains(&chunk.entity) {
+                            dirty_chunks.insert(chunk.entity);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Only the blocks on the surface of a cleared area have neighbors that can be cut off
+        if request.block.is_empty() {
+            grids_to_split
+                .entry(request.grid)
+                .or_default()
+                .extend(area_surface(min, max));
+        }
+
+        // Power networks and rooms are found again from scratch once enough blocks changed
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let pos = GridPos::from_block_coords(IVec3::new(x, y, z));
+                    if let Some(power) = &mut power {
+                        power.block_changed(pos);
+                    }
+                    if let Some(rooms) = &mut rooms {
+                        rooms.block_changed(pos);
+                    }
+                }
+            }
+        }
+    }
+
     for request in place_block_requests.read() {
-        let Ok(mut grid) = grid_query.get_mut(request.grid) else {
+        let Ok((mut grid, power, rooms)) = grid_query.get_mut(request.grid) else {
             continue;
         };
 
-        if request.block.material == BlockMaterial::Empty {
+        if grid.damage(request.pos) > 0.0 {
+            grid.repair(request.pos);
+        }
+
+        if request.block.is_empty() {
             if let Some(chunk) = grid.get_chunk_mut(request.pos.chunk_pos) {
-                chunk.set_by_block_pos(
-                    request.pos.block_pos,
-                    Block {
-                        material: BlockMaterial::Empty,
-                    },
-                );
+                chunk.set_by_block_pos(request.pos.block_pos, Block::EMPTY);
 
-                if chunk
-                    .blocks()
-                    .iter()
-                    .all(|block| block.material == BlockMaterial::Empty)
-                {
-                    chunks_to_delete.push(DeleteChunkData {
-                        chunk_entity: chunk.entity,
-                    });
+                // Removing a block can cut the grid in two
+                grids_to_split
+                    .entry(request.grid)
+                    .or_default()
+                    .push(request.pos);
+
+                if chunk.is_empty() {
+                    chunks_to_delete.insert(chunk.entity);
                 } else {
-                    if !dirty_chunks.contains(&chunk.entity) {
-                        dirty_chunks.push(chunk.entity);
-                    }
+                    dirty_chunks.insert(chunk.entity);
                 }
             } else {
                 continue;
@@ -204,53 +339,160 @@ fn place_blocks(
             if let Some(chunk) = grid.get_chunk_mut(request.pos.chunk_pos) {
                 chunk.set_by_block_pos(request.pos.block_pos, request.block);
 
-                if !dirty_chunks.contains(&chunk.entity) {
-                    dirty_chunks.push(chunk.entity);
-                }
+                // The chunk may have been emptied by an earlier request this update
+                chunks_to_delete.remove(&chunk.entity);
+                dirty_chunks.insert(chunk.entity);
             } else {
-                let chunk_entity = commands
-                    .spawn((
-                        ChunkBundle::new(request.pos.chunk_pos),
-                        material_handle.0.clone(),
-                    ))
-                    .id();
-                commands.entity(request.grid).add_child(chunk_entity);
-
-                let mut chunk = Chunk::new(
-                    chunk_entity,
-                    [Block {
-                        material: BlockMaterial::Empty,
-                    }; 4096],
+                let mut chunk = spawn_chunk(
+                    &mut commands,
+                    &material_handle,
+                    request.grid,
+                    request.pos.chunk_pos,
                 );
                 chunk.set_by_block_pos(request.pos.block_pos, request.block);
 
-                if !dirty_chunks.contains(&chunk_entity) {
-                    dirty_chunks.push(chunk_entity);
-                }
+                dirty_chunks.insert(chunk.entity);
 
                 grid.set_chunk(request.pos.chunk_pos, Some(chunk));
             }
         }
+
+        mark_neighboring_chunks_dirty(&grid, request.pos, &mut dirty_chunks);
+
+        if let Some(mut power) = power {
+            power.block_changed(request.pos);
+        }
+        if let Some(mut rooms) = rooms {
+            rooms.block_changed(request.pos);
+        }
+    }
+
+    for &entity in chunks_to_delete.iter() {
+        commands.add(DespawnChunk { entity });
     }
 
-    for chunk_data in chunks_to_delete.iter() {
-        commands.add(DespawnChunk {
-            entity: chunk_data.chunk_entity,
-        });
+    for (grid, removed) in grids_to_split.drain() {
+        commands.add(SplitGrid { grid, removed });
     }
 
     chunk_changed_writer.send_batch(dirty_chunks.iter().copied().map(ChunkChanged));
 }
 
+// Placing a block against another grid joins the grids together. Only the surface of a filled area
+// can touch another grid.
+fn weld_placed_blocks(
+    mut welds: Local<Vec<(Entity, Entity)>>,
+    mut fill_area_requests: EventReader<FillAreaRequest>,
+    mut place_block_requests: EventReader<PlaceBlockRequest>,
+    grid_query: Query<(Entity, &Grid, &GlobalTransform)>,
+    mut commands: Commands,
+) {
+    welds.clear();
+
+    // Blocks placed in each grid
+    let mut placed: HashMap<Entity, Vec<GridPos>> = HashMap::new();
+
+    for request in fill_area_requests.read() {
+        if !request.block.is_empty() {
+            let (min, max) = request.bounds();
+            placed
+                .entry(request.grid)
+                .or_default()
+                .extend(area_surface(min, max));
+        }
+    }
+
+    for request in place_block_requests.read() {
+        if !request.block.is_empty() {
+            placed.entry(request.grid).or_default().push(request.pos);
+        }
+    }
+
+    for (grid_entity, positions) in placed {
+        let Ok((_, grid, grid_transform)) = grid_query.get(grid_entity) else {
+            continue;
+        };
+
+        for (other_entity, other_grid, other_transform) in grid_query.iter() {
+            // Grids that both had blocks placed against each other are welded once
+            if other_entity == grid_entity || welds.contains(&(other_entity, grid_entity)) {
+                continue;
+            }
+
+            let Some(lattice) = LatticeTransform::between(other_transform, grid_transform) else {
+                continue;
+            };
+
+            if positions
+                .iter()
+                .any(|&pos| touches_grid(grid, pos, other_grid, &lattice))
+            {
+                welds.push((grid_entity, other_entity));
+            }
+        }
+    }
+
+    for &(target, source) in welds.iter() {
+        commands.add(WeldGrids { target, source });
+    }
+}
+
 pub struct BuildingPlugin;
 
 impl Plugin for BuildingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<PlaceBlockRequest>().add_systems(
-            FixedUpdate,
-            (move_build_marker, place_blocks, create_build_request_events)
-                .chain()
-                .in_set(FixedUpdateSet::Update),
-        );
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.add_event::<PlaceBlockRequest>()
+            .add_event::<FillAreaRequest>()
+            .add_event::<CopyAreaRequest>()
+            .add_event::<UndoRequest>()
+            .add_event::<RedoRequest>()
+            .init_resource::<EditHistories>()
+            .init_resource::<LastEditedGrid>()
+            .init_resource::<AreaTool>()
+            .init_resource::<Clipboard>()
+            .init_resource::<Hotbar>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    move_build_marker,
+                    move_area_marker,
+                    apply_edit_history,
+                    copy_areas,
+                    record_edit_history,
+                    place_blocks,
+                    weld_placed_blocks,
+                    create_build_request_events
+                        .run_if(not(area_tool_enabled))
+                        .run_if(not(pasting)),
+                    create_area_requests
+                        .run_if(area_tool_enabled)
+                        .run_if(not(pasting)),
+                    create_history_requests,
+                    toggle_area_tool,
+                    configure_symmetry,
+                    paste_blueprint.run_if(pasting),
+                    paste_as_new_grid,
+                    toggle_pasting,
+                )
+                    .chain()
+                    .in_set(FixedUpdateSet::Update),
+            )
+            .add_systems(
+                Update,
+                (
+                    draw_mirror_planes,
+                    draw_paste_preview,
+                    fill_hotbar,
+                    select_hotbar_slot,
+                    shape_build_block,
+                    draw_hotbar,
+                    update_build_marker,
+                )
+                    .chain(),
+            );
     }
 }
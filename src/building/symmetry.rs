@@ -0,0 +1,338 @@
+use bevy::prelude::*;
+
+use crate::building_material::Building;
+use crate::camera::ActiveCamera;
+use crate::fixed_update::FixedInput;
+use crate::grid::block::{Block, BLOCK_SIZE};
+use crate::grid::chunk::CHUNK_SIZE;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::{ChunkPos, Grid, GridPos};
+use crate::raycast_selection::SelectionSource;
+
+use super::events::{FillAreaRequest, PlaceBlockRequest};
+use super::targeted_block;
+
+/// A plane perpendicular to one of a grid's axes that building is mirrored across
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MirrorPlane {
+    // Position of the plane along its axis in half blocks. Odd values put the plane through the
+    // center of a block, even values put it between two blocks.
+    pub half_blocks: i32,
+}
+
+impl MirrorPlane {
+    /// A plane through the center of the block at `coordinate`
+    pub fn through_block(coordinate: i32) -> Self {
+        Self {
+            half_blocks: coordinate * 2 + 1,
+        }
+    }
+
+    /// A plane between the block at `coordinate` and the next block along the axis
+    pub fn after_block(coordinate: i32) -> Self {
+        Self {
+            half_blocks: coordinate * 2 + 2,
+        }
+    }
+
+    pub fn mirror(&self, coordinate: i32) -> i32 {
+        self.half_blocks - coordinate - 1
+    }
+
+    // Position of the plane in the grid's local space
+    fn offset(&self) -> f32 {
+        self.half_blocks as f32 / 2.0 * BLOCK_SIZE
+    }
+}
+
+/// The mirror planes of a grid, one for each axis
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Symmetry {
+    pub x: Option<MirrorPlane>,
+    pub y: Option<MirrorPlane>,
+    pub z: Option<MirrorPlane>,
+}
+
+impl Symmetry {
+    pub fn plane_mut(&mut self, axis: usize) -> &mut Option<MirrorPlane> {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            _ => &mut self.z,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.x.is_some() || self.y.is_some() || self.z.is_some()
+    }
+
+    /// Returns the position followed by each of its mirror images, without duplicates
+    pub fn mirrored_positions(&self, pos: GridPos) -> Vec<GridPos> {
+        self.mirror(pos, (), |_, _| ())
+            .into_iter()
+            .map(|(pos, _)| pos)
+            .collect()
+    }
+
+    /// Returns the block at the position followed by each of its mirror images. The mirrored
+    /// blocks are turned so that their shapes are mirrored as well.
+    pub fn mirrored_blocks(
+        &self,
+        pos: GridPos,
+        block: Block,
+        registry: &BlockRegistry,
+    ) -> Vec<(GridPos, Block)> {
+        self.mirror(pos, block, |block, axis| {
+            registry.orient(
+                block,
+                block.shape,
+                block.orientation.mirrored(axis, block.shape),
+            )
+        })
+    }
+
+    /// Returns the area between two corners followed by each of its mirror images, without
+    /// duplicates. The block filling each mirrored area is turned so that its shape is mirrored.
+    pub fn mirrored_areas(
+        &self,
+        start: GridPos,
+        end: GridPos,
+        block: Block,
+        registry: &BlockRegistry,
+    ) -> Vec<(GridPos, GridPos, Block)> {
+        let mut mirrored = vec![(start.to_block_coords(), end.to_block_coords(), block)];
+
+        for (axis, plane) in [self.x, self.y, self.z].into_iter().enumerate() {
+            let Some(plane) = plane else {
+                continue;
+            };
+
+            for index in 0..mirrored.len() {
+                let (mut start, mut end, block) = mirrored[index];
+                start[axis] = plane.mirror(start[axis]);
+                end[axis] = plane.mirror(end[axis]);
+
+                // An area across the plane is its own mirror image
+                let is_duplicate = mirrored.iter().any(|&(other_start, other_end, _)| {
+                    other_start.min(other_end) == start.min(end)
+                        && other_start.max(other_end) == start.max(end)
+                });
+                if !is_duplicate {
+                    let block = registry.orient(
+                        block,
+                        block.shape,
+                        block.orientation.mirrored(axis, block.shape),
+                    );
+                    mirrored.push((start, end, block));
+                }
+            }
+        }
+
+        mirrored
+            .into_iter()
+            .map(|(start, end, block)| {
+                (
+                    GridPos::from_block_coords(start),
+                    GridPos::from_block_coords(end),
+                    block,
+                )
+            })
+            .collect()
+    }
+
+    // Mirrors the position along with a value that is reflected across each plane with it
+    fn mirror<T: Copy>(
+        &self,
+        pos: GridPos,
+        value: T,
+        reflect: impl Fn(T, usize) -> T,
+    ) -> Vec<(GridPos, T)> {
+        let mut mirrored = vec![(pos.to_block_coords(), value)];
+
+        for (axis, plane) in [self.x, self.y, self.z].into_iter().enumerate() {
+            let Some(plane) = plane else {
+                continue;
+            };
+
+            for index in 0..mirrored.len() {
+                let (mut coords, value) = mirrored[index];
+                coords[axis] = plane.mirror(coords[axis]);
+
+                if !mirrored.iter().any(|&(other, _)| other == coords) {
+                    mirrored.push((coords, reflect(value, axis)));
+                }
+            }
+        }
+
+        mirrored
+            .into_iter()
+            .map(|(coords, value)| (GridPos::from_block_coords(coords), value))
+            .collect()
+    }
+}
+
+/// Sends the request along with a copy for every mirrored position
+pub fn send_mirrored(
+    symmetry: Option<&Symmetry>,
+    request: PlaceBlockRequest,
+    registry: &BlockRegistry,
+    place_block_requests: &mut EventWriter<PlaceBlockRequest>,
+) {
+    let Some(symmetry) = symmetry.filter(|symmetry| symmetry.is_enabled()) else {
+        place_block_requests.send(request);
+        return;
+    };
+
+    place_block_requests.send_batch(
+        symmetry
+            .mirrored_blocks(request.pos, request.block, registry)
+            .into_iter()
+            .map(|(pos, block)| PlaceBlockRequest {
+                grid: request.grid,
+                pos,
+                block,
+                record_history: request.record_history,
+            }),
+    );
+}
+
+/// Sends the request along with a copy for every mirrored area
+pub fn send_mirrored_area(
+    symmetry: Option<&Symmetry>,
+    request: FillAreaRequest,
+    registry: &BlockRegistry,
+    fill_area_requests: &mut EventWriter<FillAreaRequest>,
+) {
+    let Some(symmetry) = symmetry.filter(|symmetry| symmetry.is_enabled()) else {
+        fill_area_requests.send(request);
+        return;
+    };
+
+    fill_area_requests.send_batch(
+        symmetry
+            .mirrored_areas(request.start, request.end, request.block, registry)
+            .into_iter()
+            .map(|(start, end, block)| FillAreaRequest {
+                grid: request.grid,
+                start,
+                end,
+                block,
+            }),
+    );
+}
+
+// While building, Alt+X, Alt+Y and Alt+Z toggle a mirror plane through the targeted block. Holding
+// Shift as well puts the plane on the far side of the block instead.
+pub fn configure_symmetry(
+    keys: Res<FixedInput<KeyCode>>,
+    selection_source_query: Query<&SelectionSource, With<ActiveCamera>>,
+    chunk_query: Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+    grid_query: Query<Option<&Symmetry>, With<Building>>,
+    mut commands: Commands,
+) {
+    if !keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        return;
+    }
+
+    let axis = if keys.just_pressed(KeyCode::X) {
+        0
+    } else if keys.just_pressed(KeyCode::Y) {
+        1
+    } else if keys.just_pressed(KeyCode::Z) {
+        2
+    } else {
+        return;
+    };
+
+    let Some(target) = targeted_block(&selection_source_query, &chunk_query) else {
+        return;
+    };
+
+    let Ok(symmetry) = grid_query.get(target.grid) else {
+        return;
+    };
+
+    let coordinate = target.selected.to_block_coords()[axis];
+    let plane = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        MirrorPlane::after_block(coordinate)
+    } else {
+        MirrorPlane::through_block(coordinate)
+    };
+
+    // Choosing the current plane again turns it off
+    let mut symmetry = symmetry.copied().unwrap_or_default();
+    let current_plane = symmetry.plane_mut(axis);
+    *current_plane = if *current_plane == Some(plane) {
+        None
+    } else {
+        Some(plane)
+    };
+
+    commands.entity(target.grid).insert(symmetry);
+}
+
+pub fn draw_mirror_planes(
+    grid_query: Query<(&Grid, &Symmetry, &GlobalTransform), With<Building>>,
+    mut gizmos: Gizmos,
+) {
+    for (grid, symmetry, grid_transform) in grid_query.iter() {
+        // The planes cover every chunk of the grid
+        let Some((min, max)) = grid
+            .chunks()
+            .map(|(pos, _)| IVec3::new(pos.x as i32, pos.y as i32, pos.z as i32))
+            .fold(None, |bounds: Option<(IVec3, IVec3)>, pos| match bounds {
+                Some((min, max)) => Some((min.min(pos), max.max(pos))),
+                None => Some((pos, pos)),
+            })
+        else {
+            continue;
+        };
+
+        let chunk_length = CHUNK_SIZE as f32 * BLOCK_SIZE;
+        let min = min.as_vec3() * chunk_length;
+        let max = (max + IVec3::ONE).as_vec3() * chunk_length;
+        let center = (min + max) / 2.0;
+        let size = max - min;
+
+        let (_, grid_rotation, _) = grid_transform.to_scale_rotation_translation();
+
+        // Rectangles are drawn in the XY plane, so they are rotated to face along each axis
+        let planes = [
+            (
+                symmetry.x,
+                Vec3::X,
+                Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+                Vec2::new(size.z, size.y),
+                Color::RED,
+            ),
+            (
+                symmetry.y,
+                Vec3::Y,
+                Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+                Vec2::new(size.x, size.z),
+                Color::GREEN,
+            ),
+            (
+                symmetry.z,
+                Vec3::Z,
+                Quat::IDENTITY,
+                Vec2::new(size.x, size.y),
+                Color::BLUE,
+            ),
+        ];
+
+        for (plane, axis, rotation, plane_size, color) in planes {
+            let Some(plane) = plane else {
+                continue;
+            };
+
+            let position = center * (Vec3::ONE - axis) + axis * plane.offset();
+            gizmos.rect(
+                grid_transform.transform_point(position),
+                grid_rotation * rotation,
+                plane_size,
+                color,
+            );
+        }
+    }
+}
@@ -5,18 +5,52 @@ use bevy::render::render_resource::{AsBindGroup, ShaderRef};
 
 use crate::app_setup::AssetInitialization;
 use crate::camera::ActiveCamera;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::textures::{texture_layer, BlockTextureArrays, MAX_TEXTURE_LAYERS};
 use crate::grid::{ChunkPos, GridMaterialHandle};
 use crate::raycast_selection::SelectionSource;
 
+/// Material used to draw chunks. Block textures are sampled from texture arrays using the layer
+/// stored in the alpha channel of the vertex color.
 #[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
 pub struct BuildingMaterial {
+    // The grid overlay is hidden when the alpha is zero
     #[uniform(100)]
     grid_color: Color,
+    #[texture(101, dimension = "2d_array")]
+    #[sampler(102)]
+    albedo_array: Option<Handle<Image>>,
+    #[texture(103, dimension = "2d_array")]
+    #[sampler(104)]
+    normal_array: Option<Handle<Image>>,
+    // Metallic and perceptual roughness of every texture layer
+    #[uniform(105)]
+    layer_properties: [Vec4; MAX_TEXTURE_LAYERS],
 }
 
 impl BuildingMaterial {
     pub fn new(grid_color: Color) -> Self {
-        Self { grid_color }
+        Self {
+            grid_color,
+            albedo_array: None,
+            normal_array: None,
+            layer_properties: [Vec4::new(0.0, 0.5, 0.0, 0.0); MAX_TEXTURE_LAYERS],
+        }
+    }
+
+    pub fn set_block_textures(
+        &mut self,
+        texture_arrays: &BlockTextureArrays,
+        registry: &BlockRegistry,
+    ) {
+        self.albedo_array = Some(texture_arrays.albedo.clone());
+        self.normal_array = Some(texture_arrays.normal.clone());
+
+        for (id, definition) in registry.definitions() {
+            let layer = texture_layer(id) as usize;
+            self.layer_properties[layer] =
+                Vec4::new(definition.metallic, definition.roughness, 0.0, 0.0);
+        }
     }
 }
 
@@ -32,32 +66,47 @@ impl MaterialExtension for BuildingMaterial {
     }
 }
 
-type BuildingMaterialType = ExtendedMaterial<StandardMaterial, BuildingMaterial>;
+pub type BuildingMaterialType = ExtendedMaterial<StandardMaterial, BuildingMaterial>;
 
 #[derive(Resource)]
 pub struct BuildingMaterialHandle(pub Handle<BuildingMaterialType>);
 
-fn init_building_material(
-    asset_server: Res<AssetServer>,
+fn init_block_materials(
     mut materials: ResMut<Assets<BuildingMaterialType>>,
+    mut grid_material_handle: ResMut<GridMaterialHandle>,
     mut building_material_handle: ResMut<BuildingMaterialHandle>,
 ) {
-    let material_handle = materials.add(ExtendedMaterial {
-        base: StandardMaterial {
-            base_color_texture: Some(asset_server.load("aluminum/albedo.png")),
-            metallic: 1.0,
-            perceptual_roughness: 0.79,
-            normal_map_texture: Some(asset_server.load("aluminum/normal.png")),
-            ..Default::default()
-        },
+    grid_material_handle.0 = materials.add(ExtendedMaterial {
+        base: StandardMaterial::default(),
+        extension: BuildingMaterial::new(Color::NONE),
+    });
+
+    building_material_handle.0 = materials.add(ExtendedMaterial {
+        base: StandardMaterial::default(),
         extension: BuildingMaterial::default(),
     });
+}
 
-    building_material_handle.0 = material_handle;
+fn update_block_materials(
+    texture_arrays: Res<BlockTextureArrays>,
+    registry: Res<BlockRegistry>,
+    mut materials: ResMut<Assets<BuildingMaterialType>>,
+    grid_material_handle: Res<GridMaterialHandle>,
+    building_material_handle: Res<BuildingMaterialHandle>,
+) {
+    // Modifying the materials also makes them pick up the rebuilt texture arrays
+    for handle in [&grid_material_handle.0, &building_material_handle.0] {
+        if let Some(material) = materials.get_mut(handle) {
+            material
+                .extension
+                .set_block_textures(&texture_arrays, &registry);
+        }
+    }
 }
 
+/// Marks grids that are being built, which shows the block grid on their chunks
 #[derive(Component)]
-struct Building;
+pub struct Building;
 
 fn toggle_build_mode(
     selection_query: Query<&SelectionSource, With<ActiveCamera>>,
@@ -101,7 +150,6 @@ fn update_grid_materials(
             if let Ok(_) = chunk_query.get(child) {
                 commands
                     .entity(child)
-                    .remove::<Handle<StandardMaterial>>()
                     .insert(building_material_handle.0.clone());
             }
         }
@@ -112,7 +160,6 @@ fn update_grid_materials(
             if let Ok(_) = chunk_query.get(child) {
                 commands
                     .entity(child)
-                    .remove::<Handle<BuildingMaterialType>>()
                     .insert(grid_material_handle.0.clone());
             }
         }
@@ -124,8 +171,9 @@ pub struct BuildingMaterialPlugin;
 impl Plugin for BuildingMaterialPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(MaterialPlugin::<BuildingMaterialType>::default())
+            .insert_resource(GridMaterialHandle(Handle::default()))
             .insert_resource(BuildingMaterialHandle(Handle::default()))
-            .add_systems(Startup, init_building_material.in_set(AssetInitialization))
+            .add_systems(Startup, init_block_materials.in_set(AssetInitialization))
             .add_systems(
                 Update,
                 (
@@ -133,6 +181,10 @@ impl Plugin for BuildingMaterialPlugin {
                     update_grid_materials,
                 )
                     .chain(),
+            )
+            .add_systems(
+                Update,
+                update_block_materials.run_if(resource_changed::<BlockTextureArrays>()),
             );
     }
 }
@@ -1,3 +1,4 @@
+use bevy::ecs::system::Command;
 use bevy::prelude::*;
 use big_space::FloatingOrigin;
 
@@ -7,6 +8,57 @@ use crate::settings::Settings;
 #[derive(Component)]
 pub struct ActiveCamera;
 
+/// Gives control to an entity and makes a camera the active camera. The floating origin follows the
+/// controlled entity, or the camera itself if nothing is being controlled.
+pub struct TransferControl {
+    pub controlled: Option<Entity>,
+    pub camera: Entity,
+}
+
+impl Command for TransferControl {
+    fn apply(self, world: &mut World) {
+        // Remove ownership from the old entities
+        let old_owners: Vec<Entity> = world
+            .query_filtered::<Entity, Or<(With<ActivelyControlled>, With<FloatingOrigin>)>>()
+            .iter(world)
+            .collect();
+        for entity in old_owners {
+            world
+                .entity_mut(entity)
+                .remove::<(ActivelyControlled, FloatingOrigin)>();
+        }
+
+        let old_cameras: Vec<Entity> = world
+            .query_filtered::<Entity, With<ActiveCamera>>()
+            .iter(world)
+            .collect();
+        for entity in old_cameras {
+            let mut old_camera = world.entity_mut(entity);
+            old_camera.remove::<ActiveCamera>();
+            if let Some(mut camera) = old_camera.get_mut::<Camera>() {
+                camera.is_active = false;
+            }
+        }
+
+        match self.controlled {
+            Some(controlled) => {
+                world
+                    .entity_mut(controlled)
+                    .insert((ActivelyControlled, FloatingOrigin));
+            }
+            None => {
+                world.entity_mut(self.camera).insert(FloatingOrigin);
+            }
+        }
+
+        let mut new_camera = world.entity_mut(self.camera);
+        new_camera.insert(ActiveCamera);
+        if let Some(mut camera) = new_camera.get_mut::<Camera>() {
+            camera.is_active = true;
+        }
+    }
+}
+
 fn cycle_cameras(
     input: Res<Input<KeyCode>>,
     mut active_camera_query: Query<(Entity, Option<&Parent>), With<ActiveCamera>>,
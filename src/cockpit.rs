@@ -0,0 +1,414 @@
+use bevy::core_pipeline::Skybox;
+use bevy::ecs::system::Command;
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use bevy_rapier3d::prelude::*;
+
+use crate::building::targeted_block;
+use crate::camera::{ActiveCamera, TransferControl};
+use crate::fixed_update::{FixedInput, FixedUpdateSet};
+use crate::grid::block::{Block, BLOCK_SIZE};
+use crate::grid::registry::BlockRegistry;
+use crate::grid::{ChunkPos, Grid, GridPos};
+use crate::player::Player;
+use crate::player_camera::PlayerCamera;
+use crate::player_controller::ActivelyControlled;
+use crate::raycast_selection::SelectionSource;
+use crate::settings::Settings;
+use crate::skybox::SkyboxHandle;
+use crate::thruster::{fire_thrusters, ThrusterControl};
+use crate::UniverseGrid;
+
+// How far away in meters the player can get into a cockpit from
+const COCKPIT_REACH: f32 = 5.0;
+
+// How far above the cockpit the player is put when getting out, clear of the seat
+const EXIT_DISTANCE: f32 = 2.0;
+
+// Scales mouse movement to how fast the grid turns
+const MOUSE_TURN_SCALE: f32 = 0.25;
+
+/// Marks a grid that is being piloted from one of its cockpits
+#[derive(Component)]
+pub struct Piloted {
+    pub pilot: Entity,
+    pub cockpit: GridPos,
+    // Camera in the cockpit's seat
+    pub camera: Entity,
+}
+
+/// Marks a player sitting in the cockpit of a grid. Seated players are hidden and don't take part
+/// in physics, but follow the cockpit around.
+#[derive(Component)]
+pub struct Seated {
+    pub grid: Entity,
+}
+
+#[derive(Bundle)]
+pub struct SeatCameraBundle {
+    pub camera: Camera3dBundle,
+    pub player_camera: PlayerCamera,
+    pub skybox: Skybox,
+}
+
+impl SeatCameraBundle {
+    pub fn new(transform: Transform, skybox: Skybox) -> Self {
+        Self {
+            camera: Camera3dBundle {
+                transform,
+                camera: Camera {
+                    is_active: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            player_camera: PlayerCamera,
+            skybox,
+        }
+    }
+}
+
+/// Where a cockpit's seat is in its grid. The seat faces the cockpit's front and is upright
+/// towards its top.
+pub fn seat_transform(pos: GridPos, block: Block) -> Transform {
+    Transform::from_translation((pos.to_block_coords().as_vec3() + 0.5) * BLOCK_SIZE)
+        .with_rotation(block.orientation.rotation())
+}
+
+/// Puts a player in a cockpit, giving control to its grid and switching to a camera in the seat
+pub struct EnterCockpit {
+    pub player: Entity,
+    pub grid: Entity,
+    pub cockpit: GridPos,
+}
+
+impl Command for EnterCockpit {
+    fn apply(self, world: &mut World) {
+        if world.get::<Piloted>(self.grid).is_some() || world.get::<Seated>(self.player).is_some() {
+            return;
+        }
+
+        let Some(grid) = world.get::<Grid>(self.grid) else {
+            return;
+        };
+
+        let block = grid.get_block(self.cockpit);
+        if !world
+            .resource::<BlockRegistry>()
+            .get(block.id)
+            .is_some_and(|definition| definition.cockpit)
+        {
+            return;
+        }
+
+        let skybox = Skybox(world.resource::<SkyboxHandle>().0.clone());
+        let camera = world
+            .spawn(SeatCameraBundle::new(
+                seat_transform(self.cockpit, block),
+                skybox,
+            ))
+            .set_parent(self.grid)
+            .id();
+
+        world.entity_mut(self.grid).insert(Piloted {
+            pilot: self.player,
+            cockpit: self.cockpit,
+            camera,
+        });
+        world.entity_mut(self.player).insert((
+            Seated { grid: self.grid },
+            RigidBodyDisabled,
+            ColliderDisabled,
+            Visibility::Hidden,
+        ));
+
+        TransferControl {
+            controlled: Some(self.grid),
+            camera,
+        }
+        .apply(world);
+    }
+}
+
+/// Takes a player out of the cockpit they are sitting in and gives control back to them. If the
+/// grid is gone, the player is left where the cockpit last was.
+pub struct ExitCockpit {
+    pub player: Entity,
+}
+
+impl Command for ExitCockpit {
+    fn apply(self, world: &mut World) {
+        let Some(seated) = world
+            .get_entity_mut(self.player)
+            .and_then(|mut player| player.take::<Seated>())
+        else {
+            return;
+        };
+
+        let piloted = world
+            .get_entity_mut(seated.grid)
+            .and_then(|mut grid| grid.take::<Piloted>());
+
+        if let Some(piloted) = piloted {
+            if let Some(camera) = world.get_entity_mut(piloted.camera) {
+                camera.despawn_recursive();
+            }
+
+            let mut grid_entity = world.entity_mut(seated.grid);
+            if let Some(mut control) = grid_entity.get_mut::<ThrusterControl>() {
+                control.clear_input();
+            }
+
+            let grid_transform = *grid_entity.get::<Transform>().unwrap();
+            let grid_cell = grid_entity
+                .get::<UniverseGrid>()
+                .copied()
+                .unwrap_or_default();
+            let velocity = grid_entity.get::<Velocity>().copied().unwrap_or_default();
+            let block = grid_entity
+                .get::<Grid>()
+                .unwrap()
+                .get_block(piloted.cockpit);
+
+            let seat = grid_transform * seat_transform(piloted.cockpit, block);
+            world.entity_mut(self.player).insert((
+                Transform::from_translation(seat.translation + seat.up() * EXIT_DISTANCE)
+                    .with_rotation(seat.rotation),
+                grid_cell,
+                Velocity::linear(velocity.linvel),
+            ));
+        }
+
+        world
+            .entity_mut(self.player)
+            .remove::<(RigidBodyDisabled, ColliderDisabled)>()
+            .insert(Visibility::Inherited);
+
+        let camera = world.get::<Children>(self.player).and_then(|children| {
+            children
+                .iter()
+                .copied()
+                .find(|&child| world.get::<PlayerCamera>(child).is_some())
+        });
+
+        match camera {
+            Some(camera) => TransferControl {
+                controlled: Some(self.player),
+                camera,
+            }
+            .apply(world),
+            None => warn!(
+                "Player {:?} has no camera to take control with",
+                self.player
+            ),
+        }
+    }
+}
+
+// F gets the pilot of the controlled grid out of their cockpit
+fn leave_cockpits(
+    keys: Res<FixedInput<KeyCode>>,
+    piloted_query: Query<&Piloted, With<ActivelyControlled>>,
+    mut commands: Commands,
+) {
+    if !keys.just_pressed(KeyCode::F) {
+        return;
+    }
+
+    if let Ok(piloted) = piloted_query.get_single() {
+        commands.add(ExitCockpit {
+            player: piloted.pilot,
+        });
+    }
+}
+
+// F gets the controlled player into the cockpit they are looking at
+fn enter_cockpits(
+    keys: Res<FixedInput<KeyCode>>,
+    player_query: Query<(Entity, &GlobalTransform, Has<ActivelyControlled>), With<Player>>,
+    selection_source_query: Query<&SelectionSource, With<ActiveCamera>>,
+    chunk_query: Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+    grid_query: Query<(&Grid, &GlobalTransform)>,
+    registry: Res<BlockRegistry>,
+    mut commands: Commands,
+) {
+    if !keys.just_pressed(KeyCode::F) {
+        return;
+    }
+
+    let Some((player, player_transform, _)) = player_query
+        .iter()
+        .find(|&(_, _, actively_controlled)| actively_controlled)
+    else {
+        return;
+    };
+
+    let Some(target) = targeted_block(&selection_source_query, &chunk_query) else {
+        return;
+    };
+
+    let Ok((grid, grid_transform)) = grid_query.get(target.grid) else {
+        return;
+    };
+
+    let block = grid.get_block(target.selected);
+    if !registry
+        .get(block.id)
+        .is_some_and(|definition| definition.cockpit)
+    {
+        return;
+    }
+
+    let seat = grid_transform.transform_point(seat_transform(target.selected, block).translation);
+    if seat.distance(player_transform.translation()) > COCKPIT_REACH {
+        return;
+    }
+
+    commands.add(EnterCockpit {
+        player,
+        grid: target.grid,
+        cockpit: target.selected,
+    });
+}
+
+// Flies the controlled grid relative to the cockpit it is piloted from. WASD, space and C move the
+// grid like they move the player, Q and E roll it and the mouse turns it while the cursor is
+// locked. I toggles the dampeners. Grids that are piloted but not controlled, like after switching
+// cameras, are left without input.
+fn pilot_grids(
+    keys: Res<FixedInput<KeyCode>>,
+    mut grid_query: Query<(
+        &Grid,
+        &Piloted,
+        &mut ThrusterControl,
+        Has<ActivelyControlled>,
+    )>,
+    mut motion_reader: EventReader<MouseMotion>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+    settings: Res<Settings>,
+) {
+    let mut linear = Vec3::ZERO;
+    let mut angular = Vec3::ZERO;
+
+    for (key, direction) in [
+        (KeyCode::W, Vec3::NEG_Z),
+        (KeyCode::S, Vec3::Z),
+        (KeyCode::A, Vec3::NEG_X),
+        (KeyCode::D, Vec3::X),
+        (KeyCode::Space, Vec3::Y),
+        (KeyCode::C, Vec3::NEG_Y),
+    ] {
+        if keys.pressed(key) {
+            linear += direction;
+        }
+    }
+
+    if keys.pressed(KeyCode::Q) {
+        angular += Vec3::Z;
+    }
+
+    if keys.pressed(KeyCode::E) {
+        angular += Vec3::NEG_Z;
+    }
+
+    let cursor_locked = primary_window_query
+        .get_single()
+        .ok()
+        .filter(|window| window.cursor.grab_mode != CursorGrabMode::None);
+    match cursor_locked {
+        Some(window) => {
+            let scale_factor = window.height().min(window.width())
+                * settings.first_person_sensitivity
+                * MOUSE_TURN_SCALE;
+
+            for motion in motion_reader.read() {
+                angular.x -= motion.delta.y * scale_factor;
+                angular.y -= motion.delta.x * scale_factor;
+            }
+        }
+        None => motion_reader.clear(),
+    }
+
+    let linear = linear.normalize_or_zero();
+    let angular = angular.clamp(Vec3::NEG_ONE, Vec3::ONE);
+
+    for (grid, piloted, mut control, actively_controlled) in grid_query.iter_mut() {
+        if !actively_controlled {
+            control.clear_input();
+            continue;
+        }
+
+        if keys.just_pressed(KeyCode::I) {
+            control.dampeners = !control.dampeners;
+        }
+
+        // The controls are relative to the seat, the grid is controlled in its own space. Axes are
+        // turned exactly so that the ones without input stay at zero for the dampeners.
+        let orientation = grid.get_block(piloted.cockpit).orientation;
+        let to_grid = |input: Vec3| -> Vec3 {
+            [IVec3::X, IVec3::Y, IVec3::Z]
+                .into_iter()
+                .enumerate()
+                .map(|(axis, direction)| orientation.rotate(direction).as_vec3() * input[axis])
+                .sum()
+        };
+        control.linear = to_grid(linear);
+        control.angular = to_grid(angular);
+    }
+}
+
+// Players get out when their cockpit is destroyed or their grid is gone
+fn eject_pilots(
+    seated_query: Query<(Entity, &Seated)>,
+    grid_query: Query<(&Grid, &Piloted)>,
+    registry: Res<BlockRegistry>,
+    mut commands: Commands,
+) {
+    for (player, seated) in seated_query.iter() {
+        let in_cockpit = grid_query.get(seated.grid).is_ok_and(|(grid, piloted)| {
+            piloted.pilot == player
+                && registry
+                    .get(grid.get_block(piloted.cockpit).id)
+                    .is_some_and(|definition| definition.cockpit)
+        });
+
+        if !in_cockpit {
+            commands.add(ExitCockpit { player });
+        }
+    }
+}
+
+fn follow_cockpits(
+    mut seated_query: Query<(&Seated, &mut Transform, &mut UniverseGrid)>,
+    grid_query: Query<(&Grid, &Piloted, &Transform, &UniverseGrid), Without<Seated>>,
+) {
+    for (seated, mut transform, mut cell) in seated_query.iter_mut() {
+        let Ok((grid, piloted, grid_transform, grid_cell)) = grid_query.get(seated.grid) else {
+            continue;
+        };
+
+        *transform =
+            *grid_transform * seat_transform(piloted.cockpit, grid.get_block(piloted.cockpit));
+        *cell = *grid_cell;
+    }
+}
+
+pub struct CockpitPlugin;
+
+impl Plugin for CockpitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                (
+                    leave_cockpits,
+                    enter_cockpits,
+                    pilot_grids.before(fire_thrusters),
+                )
+                    .in_set(FixedUpdateSet::Update),
+                (eject_pilots, follow_cockpits).in_set(FixedUpdateSet::PostUpdate),
+            ),
+        );
+    }
+}
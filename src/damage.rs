@@ -0,0 +1,267 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_rapier3d::prelude::*;
+
+use crate::building::events::PlaceBlockRequest;
+use crate::fixed_update::FixedUpdateSet;
+use crate::grid::block::Block;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::structure::{find_weak_joints, WeakJoint};
+use crate::grid::{block_at_surface, ChunkPos, Grid, GridPos};
+use crate::PHYSICS_TIMESTEP;
+
+// Load in newtons that a block can carry for each of its hit points
+const NEWTONS_PER_HIT_POINT: f32 = 1000.0;
+
+// Impulse in newton seconds that a block can absorb for each of its hit points
+const IMPULSE_PER_HIT_POINT: f32 = 100.0;
+
+/// Impacts between grids with a smaller impulse than this, in newton seconds, don't damage blocks
+pub const MIN_DAMAGING_IMPULSE: f32 = 500.0;
+
+#[derive(Event)]
+pub struct DamageBlockRequest {
+    pub grid: Entity,
+    pub pos: GridPos,
+    pub amount: f32,
+}
+
+/// Whether grids are checked for sections that tear off under acceleration. Off by default since
+/// it walks every block of a grid whenever the grid changes.
+#[derive(Resource, Default)]
+pub struct StructuralIntegrity {
+    pub enabled: bool,
+}
+
+pub fn structural_integrity_enabled(structural_integrity: Res<StructuralIntegrity>) -> bool {
+    structural_integrity.enabled
+}
+
+/// The weak joints of a grid, kept until the grid changes
+#[derive(Component)]
+pub struct StructuralJoints(pub Vec<WeakJoint>);
+
+// Blocks that run out of hit points are removed with the same requests as building, so that chunks
+// are despawned and grids are split in one place
+pub fn damage_blocks(
+    mut damage_block_requests: EventReader<DamageBlockRequest>,
+    mut grid_query: Query<&mut Grid>,
+    registry: Res<BlockRegistry>,
+    mut place_block_writer: EventWriter<PlaceBlockRequest>,
+) {
+    let mut destroyed = HashSet::new();
+
+    for request in damage_block_requests.read() {
+        if request.amount <= 0.0 {
+            continue;
+        }
+
+        let Ok(mut grid) = grid_query.get_mut(request.grid) else {
+            continue;
+        };
+
+        let Some(definition) = registry.get(grid.get_block(request.pos).id) else {
+            continue;
+        };
+        let hit_points = definition.hit_points as f32;
+
+        // Damage alone doesn't change the grid's blocks, so it shouldn't make thrusters, gyroscopes
+        // and joints be found again
+        let damage = grid
+            .bypass_change_detection()
+            .apply_damage(request.pos, request.amount);
+        if damage >= hit_points && destroyed.insert((request.grid, request.pos)) {
+            place_block_writer.send(PlaceBlockRequest {
+                grid: request.grid,
+                pos: request.pos,
+                block: Block::EMPTY,
+                record_history: false,
+            });
+        }
+    }
+}
+
+// Joints have to carry the sections hanging off them as their grid accelerates. Overloaded joints
+// take damage in proportion to how far over their limit they are, so a joint carrying twice what it
+// can hold breaks in about a second. Only linear acceleration is taken into account.
+pub fn check_structural_integrity(
+    mut previous_velocities: Local<HashMap<Entity, Vec3>>,
+    grid_query: Query<(Entity, Ref<Grid>, &Velocity, Option<&StructuralJoints>)>,
+    registry: Res<BlockRegistry>,
+    mut damage_block_writer: EventWriter<DamageBlockRequest>,
+    mut commands: Commands,
+) {
+    let mut velocities = HashMap::new();
+
+    for (entity, grid, velocity, joints) in grid_query.iter() {
+        velocities.insert(entity, velocity.linvel);
+
+        let new_joints;
+        let joints = match joints {
+            Some(joints) if !grid.is_changed() => &joints.0,
+            _ => {
+                new_joints = find_weak_joints(&grid, &registry);
+                commands
+                    .entity(entity)
+                    .insert(StructuralJoints(new_joints.clone()));
+                &new_joints
+            }
+        };
+
+        let Some(&previous_velocity) = previous_velocities.get(&entity) else {
+            continue;
+        };
+        let acceleration = (velocity.linvel - previous_velocity).length() / PHYSICS_TIMESTEP;
+
+        for joint in joints {
+            let Some(definition) = registry.get(grid.get_block(joint.pos).id) else {
+                continue;
+            };
+
+            let capacity = definition.hit_points as f32 * NEWTONS_PER_HIT_POINT;
+            let load = joint.section_mass * acceleration;
+            if load > capacity {
+                damage_block_writer.send(DamageBlockRequest {
+                    grid: entity,
+                    pos: joint.pos,
+                    amount: (load - capacity) / NEWTONS_PER_HIT_POINT * PHYSICS_TIMESTEP,
+                });
+            }
+        }
+    }
+
+    *previous_velocities = velocities;
+}
+
+// The block of a chunk at a contact point and its hit points
+fn contact_block(
+    chunk_query: &Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+    grid_query: &Query<&Grid>,
+    registry: &BlockRegistry,
+    collider: Entity,
+    point: Vec3,
+    normal: Vec3,
+) -> Option<(Entity, GridPos, f32)> {
+    let (chunk_transform, chunk_pos, parent) = chunk_query.get(collider).ok()?;
+    let (pos, _) = block_at_surface(chunk_transform, *chunk_pos, point, normal);
+
+    let block = grid_query.get(parent.get()).ok()?.get_block(pos);
+    if block.is_empty() {
+        return None;
+    }
+
+    let definition = registry.get(block.id)?;
+
+    Some((parent.get(), pos, definition.hit_points as f32))
+}
+
+// New chunks report impacts between grids that are strong enough to damage blocks
+pub fn report_chunk_impacts(mut commands: Commands, chunk_query: Query<Entity, Added<ChunkPos>>) {
+    for entity in chunk_query.iter() {
+        commands.entity(entity).insert((
+            ActiveEvents::CONTACT_FORCE_EVENTS,
+            ContactForceEventThreshold(MIN_DAMAGING_IMPULSE / PHYSICS_TIMESTEP),
+        ));
+    }
+}
+
+// Impacts between grids damage the blocks at their contact points. The impulse is shared out
+// between the contact points, and the weaker of the two blocks at each point takes more of the
+// damage.
+pub fn damage_colliding_grids(
+    mut contact_force_events: EventReader<ContactForceEvent>,
+    rapier_context: Res<RapierContext>,
+    chunk_query: Query<(&GlobalTransform, &ChunkPos, &Parent)>,
+    grid_query: Query<&Grid>,
+    registry: Res<BlockRegistry>,
+    mut damage_block_writer: EventWriter<DamageBlockRequest>,
+) {
+    for event in contact_force_events.read() {
+        let impulse = event.total_force_magnitude * PHYSICS_TIMESTEP;
+        if impulse <= MIN_DAMAGING_IMPULSE {
+            continue;
+        }
+
+        let Some(contact_pair) = rapier_context.contact_pair(event.collider1, event.collider2)
+        else {
+            continue;
+        };
+
+        // The normal points out of the first collider
+        let mut contacts = Vec::new();
+        for manifold in contact_pair.manifolds() {
+            let normal = manifold.normal();
+            for solver_contact in manifold.solver_contacts() {
+                contacts.push((solver_contact.point(), normal));
+            }
+        }
+
+        if contacts.is_empty() {
+            continue;
+        }
+
+        let damage =
+            (impulse - MIN_DAMAGING_IMPULSE) / IMPULSE_PER_HIT_POINT / contacts.len() as f32;
+
+        for (point, normal) in contacts {
+            let first = contact_block(
+                &chunk_query,
+                &grid_query,
+                &registry,
+                contact_pair.collider1(),
+                point,
+                normal,
+            );
+            let second = contact_block(
+                &chunk_query,
+                &grid_query,
+                &registry,
+                contact_pair.collider2(),
+                point,
+                -normal,
+            );
+            let (Some(first), Some(second)) = (first, second) else {
+                continue;
+            };
+
+            // Blocks of the same strength both take the full damage
+            let total_hit_points = first.2 + second.2;
+            let first_share = if total_hit_points > 0.0 {
+                second.2 / total_hit_points
+            } else {
+                0.5
+            };
+
+            for ((grid, pos, _), share) in [(first, first_share), (second, 1.0 - first_share)] {
+                damage_block_writer.send(DamageBlockRequest {
+                    grid,
+                    pos,
+                    amount: damage * share * 2.0,
+                });
+            }
+        }
+    }
+}
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageBlockRequest>()
+            .init_resource::<StructuralIntegrity>()
+            .add_systems(
+                FixedUpdate,
+                report_chunk_impacts.in_set(FixedUpdateSet::PreUpdate),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    check_structural_integrity.run_if(structural_integrity_enabled),
+                    damage_colliding_grids,
+                    damage_blocks,
+                )
+                    .chain()
+                    .in_set(FixedUpdateSet::PostUpdate),
+            );
+    }
+}
@@ -1,12 +1,57 @@
+use bevy::prelude::*;
+
+use super::shape::{BlockShape, Orientation};
+
 pub const BLOCK_SIZE: f32 = 0.25;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum BlockMaterial {
-    Empty,
-    Aluminum,
+/// Index of a block definition in the block registry
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockId(pub u16);
+
+impl BlockId {
+    pub const EMPTY: Self = Self(0);
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Block {
-    pub material: BlockMaterial,
+    pub id: BlockId,
+    pub shape: BlockShape,
+    pub orientation: Orientation,
+}
+
+impl Block {
+    pub const EMPTY: Self = Self::new(BlockId::EMPTY);
+
+    pub const fn new(id: BlockId) -> Self {
+        Self {
+            id,
+            shape: BlockShape::Cube,
+            orientation: Orientation::DEFAULT,
+        }
+    }
+
+    // Cubes look the same in every orientation, so they always use the default one to keep chunk
+    // palettes small
+    pub fn with_shape(self, shape: BlockShape, orientation: Orientation) -> Self {
+        Self {
+            shape,
+            orientation: if shape == BlockShape::Cube {
+                Orientation::DEFAULT
+            } else {
+                orientation
+            },
+            ..self
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id == BlockId::EMPTY
+    }
+
+    /// Returns true if the block completely covers the side facing `direction` in the grid
+    pub fn covers_side(&self, direction: IVec3) -> bool {
+        !self.is_empty()
+            && (self.shape == BlockShape::Cube
+                || self.shape.covers_side(self.orientation.unrotate(direction)))
+    }
 }
@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+
+use super::block::Block;
+use super::chunk::Chunk;
+use super::registry::BlockRegistry;
+use super::{Grid, GridPos};
+
+/// Blocks copied out of a grid, positioned relative to an anchor block so that they can be placed
+/// anywhere on any grid
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Blueprint {
+    pub blocks: Vec<(IVec3, Block)>,
+}
+
+impl Blueprint {
+    /// Copies the blocks in the box between two corners, leaving out empty blocks
+    pub fn copy(grid: &Grid, start: GridPos, end: GridPos, anchor: GridPos) -> Self {
+        let start = start.to_block_coords();
+        let end = end.to_block_coords();
+        let min = start.min(end);
+        let max = start.max(end);
+        let anchor = anchor.to_block_coords();
+
+        let mut blocks = Vec::new();
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let coords = IVec3::new(x, y, z);
+                    let block = grid.get_block(GridPos::from_block_coords(coords));
+                    if !block.is_empty() {
+                        blocks.push((coords - anchor, block));
+                    }
+                }
+            }
+        }
+
+        Self { blocks }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Turns the blueprint a quarter turn counterclockwise around one of its axes through the anchor
+    pub fn rotate(&mut self, axis: usize, registry: &BlockRegistry) {
+        let first = (axis + 1) % 3;
+        let second = (axis + 2) % 3;
+
+        for (offset, block) in self.blocks.iter_mut() {
+            let rotated_first = -offset[second];
+            offset[second] = offset[first];
+            offset[first] = rotated_first;
+
+            *block = registry.orient(*block, block.shape, block.orientation.rotated(axis));
+        }
+    }
+
+    /// The smallest and largest offsets of the blueprint's blocks
+    pub fn bounds(&self) -> Option<(IVec3, IVec3)> {
+        self.blocks
+            .iter()
+            .fold(None, |bounds, &(offset, _)| match bounds {
+                Some((min, max)) => Some((offset.min(min), offset.max(max))),
+                None => Some((offset, offset)),
+            })
+    }
+
+    /// Positions of the blueprint's blocks with its anchor at `anchor`
+    pub fn placements(&self, anchor: GridPos) -> impl Iterator<Item = (GridPos, Block)> + '_ {
+        let anchor = anchor.to_block_coords();
+
+        self.blocks
+            .iter()
+            .map(move |&(offset, block)| (GridPos::from_block_coords(anchor + offset), block))
+    }
+
+    /// Builds a grid out of the blueprint with the anchor at the grid's origin
+    pub fn to_grid(&self) -> Grid {
+        let mut grid = Grid::new();
+
+        for (pos, block) in self.placements(GridPos::from_block_coords(IVec3::ZERO)) {
+            match grid.get_chunk_mut(pos.chunk_pos) {
+                Some(chunk) => chunk.set_by_block_pos(pos.block_pos, block),
+                None => {
+                    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+                    chunk.set_by_block_pos(pos.block_pos, block);
+                    grid.set_chunk(pos.chunk_pos, Some(chunk));
+                }
+            }
+        }
+
+        grid
+    }
+}
@@ -2,12 +2,14 @@ use bevy::prelude::*;
 
 use crate::raycast_selection::Selectable;
 
-use super::{block::Block, ChunkPos};
+use super::block::Block;
+use super::palette::PalettedBlocks;
+use super::ChunkPos;
 
 pub const CHUNK_SIZE: u8 = 16;
 pub const CHUNK_SIZE_CUBED: usize = CHUNK_SIZE as usize * CHUNK_SIZE as usize * CHUNK_SIZE as usize;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct BlockPos {
     pub x: u8,
     pub y: u8,
@@ -17,12 +19,22 @@ pub struct BlockPos {
 #[derive(Clone)]
 pub struct Chunk {
     pub entity: Entity,
-    blocks: [Block; CHUNK_SIZE as usize * CHUNK_SIZE as usize * CHUNK_SIZE as usize],
+    blocks: PalettedBlocks,
 }
 
 impl Chunk {
     pub fn new(entity: Entity, blocks: [Block; CHUNK_SIZE_CUBED]) -> Self {
-        Self { entity, blocks }
+        Self {
+            entity,
+            blocks: PalettedBlocks::from_blocks(&blocks),
+        }
+    }
+
+    pub fn filled(entity: Entity, block: Block) -> Self {
+        Self {
+            entity,
+            blocks: PalettedBlocks::filled(block),
+        }
     }
 
     pub fn pos_to_index(&self, x: u8, y: u8, z: u8) -> usize {
@@ -39,11 +51,12 @@ impl Chunk {
     }
 
     pub fn get(&self, x: u8, y: u8, z: u8) -> Block {
-        self.blocks[self.pos_to_index(x, y, z)]
+        self.blocks.get(self.pos_to_index(x, y, z))
     }
 
     pub fn set(&mut self, x: u8, y: u8, z: u8, block: Block) {
-        self.blocks[self.pos_to_index(x, y, z)] = block;
+        let index = self.pos_to_index(x, y, z);
+        self.blocks.set(index, block);
     }
 
     pub fn get_by_block_pos(&self, pos: BlockPos) -> Block {
@@ -54,14 +67,33 @@ impl Chunk {
         self.set(pos.x, pos.y, pos.z, block);
     }
 
-    pub fn blocks(&self) -> &[Block] {
-        &self.blocks
+    /// Iterates over every block in index order
+    pub fn blocks(&self) -> impl Iterator<Item = Block> + '_ {
+        (0..CHUNK_SIZE_CUBED).map(|index| self.blocks.get(index))
+    }
+
+    /// Iterates over the distinct blocks in the chunk
+    pub fn palette(&self) -> impl Iterator<Item = Block> + '_ {
+        self.blocks.palette()
+    }
+
+    pub fn is_uniform(&self) -> bool {
+        self.blocks.is_uniform()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.palette().all(|block| block.is_empty())
     }
 }
 
 #[derive(Event)]
 pub struct ChunkChanged(pub Entity);
 
+/// Marks a chunk whose mesh and collider are out of date. However many times a chunk changes during
+/// a tick, it is only rebuilt once.
+#[derive(Component)]
+pub struct ChunkDirty;
+
 #[derive(Bundle)]
 pub struct ChunkBundle {
     pub chunk_pos: ChunkPos,
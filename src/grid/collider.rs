@@ -1,12 +1,18 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
+use bevy_rapier3d::rapier::dynamics::MassProperties as RapierMassProperties;
+use bevy_rapier3d::rapier::math::Point;
+use bevy_rapier3d::rapier::na::Matrix3;
 
-use super::block::{BlockMaterial, BLOCK_SIZE};
+use super::block::BLOCK_SIZE;
 
-use super::chunk::{Chunk, ChunkChanged, CHUNK_SIZE, CHUNK_SIZE_CUBED};
-use super::{ChunkPos, Grid};
+use super::chunk::{Chunk, CHUNK_SIZE, CHUNK_SIZE_CUBED};
+use super::registry::BlockRegistry;
+use super::shape::BlockShape;
+use super::Grid;
 
-pub fn generate_collider_for_chunk(chunk: &Chunk) -> Collider {
+// Returns None if none of the blocks in the chunk are collidable
+pub fn generate_collider_for_chunk(chunk: &Chunk, registry: &BlockRegistry) -> Option<Collider> {
     let mut collider_data: Vec<(Vec3, Quat, Collider)> = Vec::new();
     let mut tested = vec![false; CHUNK_SIZE_CUBED];
 
@@ -20,22 +26,43 @@ pub fn generate_collider_for_chunk(chunk: &Chunk) -> Collider {
 
                 let block = chunk.get(start_x, start_y, start_z);
 
-                if block.material == BlockMaterial::Empty {
+                if !registry.is_collidable(block) {
                     tested[start_index] = true;
                     continue;
                 }
 
                 tested[start_index] = true;
 
+                // Other shapes can't be merged, so each block gets its own convex hull
+                if block.shape != BlockShape::Cube {
+                    let points: Vec<Vec3> = block
+                        .shape
+                        .vertices()
+                        .into_iter()
+                        .map(|vertex| (block.orientation.rotate_point(vertex) - 0.5) * BLOCK_SIZE)
+                        .collect();
+
+                    if let Some(collider) = Collider::convex_hull(&points) {
+                        collider_data.push((
+                            (Vec3::new(start_x as f32, start_y as f32, start_z as f32) + 0.5)
+                                * BLOCK_SIZE,
+                            Quat::IDENTITY,
+                            collider,
+                        ));
+                    }
+
+                    continue;
+                }
+
                 let mut end_x = start_x;
                 let mut end_y = start_y;
                 let mut end_z = start_z;
 
                 for x in start_x + 1..CHUNK_SIZE {
                     let current_index = chunk.pos_to_index(x, start_y, start_z);
-                    let test_block = chunk.blocks()[current_index];
+                    let test_block = chunk.get(x, start_y, start_z);
 
-                    if test_block.material != block.material || tested[current_index] {
+                    if test_block != block || tested[current_index] {
                         end_x = x - 1;
                         break;
                     }
@@ -50,9 +77,9 @@ pub fn generate_collider_for_chunk(chunk: &Chunk) -> Collider {
                 'height: for y in start_y + 1..CHUNK_SIZE {
                     for x in start_x..end_x + 1 {
                         let current_index = chunk.pos_to_index(x, y, start_z);
-                        let test_block = chunk.blocks()[current_index];
+                        let test_block = chunk.get(x, y, start_z);
 
-                        if test_block.material != block.material || tested[current_index] {
+                        if test_block != block || tested[current_index] {
                             end_y = y - 1;
                             break 'height;
                         }
@@ -71,8 +98,8 @@ pub fn generate_collider_for_chunk(chunk: &Chunk) -> Collider {
                     for y in start_y..end_y + 1 {
                         for x in start_x..end_x + 1 {
                             let current_index = chunk.pos_to_index(x, y, z);
-                            let test_block = chunk.blocks()[current_index];
-                            if test_block.material != block.material || tested[current_index] {
+                            let test_block = chunk.get(x, y, z);
+                            if test_block != block || tested[current_index] {
                                 end_z = z - 1;
                                 break 'depth;
                             }
@@ -106,32 +133,166 @@ pub fn generate_collider_for_chunk(chunk: &Chunk) -> Collider {
         }
     }
 
-    Collider::compound(collider_data)
+    if collider_data.is_empty() {
+        return None;
+    }
+
+    Some(Collider::compound(collider_data))
+}
+
+/// A box around the chunk's collidable blocks. It is cheap enough to build right away, so it stands
+/// in for a new chunk's collider while the real one is generated in the background.
+pub fn generate_fallback_collider_for_chunk(
+    chunk: &Chunk,
+    registry: &BlockRegistry,
+) -> Option<Collider> {
+    let mut bounds: Option<(Vec3, Vec3)> = None;
+
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                if !registry.is_collidable(chunk.get(x, y, z)) {
+                    continue;
+                }
+
+                let min = Vec3::new(x as f32, y as f32, z as f32);
+                let max = min + 1.0;
+                bounds = Some(match bounds {
+                    Some((bounds_min, bounds_max)) => (bounds_min.min(min), bounds_max.max(max)),
+                    None => (min, max),
+                });
+            }
+        }
+    }
+
+    let (min, max) = bounds?;
+    let half_extents = (max - min) * BLOCK_SIZE / 2.0;
+
+    Some(Collider::compound(vec![(
+        (min + max) * BLOCK_SIZE / 2.0,
+        Quat::IDENTITY,
+        Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+    )]))
+}
+
+/// Inertia tensor of a point mass about the origin
+pub fn point_inertia(mass: f32, position: Vec3) -> Mat3 {
+    let outer_product = Mat3::from_cols(
+        position * position.x,
+        position * position.y,
+        position * position.z,
+    );
+
+    (Mat3::from_diagonal(Vec3::splat(position.length_squared())) - outer_product) * mass
+}
+
+// Mass of a chunk's blocks along with their first moment of mass and inertia about the chunk's
+// origin
+fn chunk_mass_distribution(chunk: &Chunk, registry: &BlockRegistry) -> (f32, Vec3, Mat3) {
+    let block_volume = BLOCK_SIZE * BLOCK_SIZE * BLOCK_SIZE;
+    // A cube's inertia about its center is m * s² / 6 around every axis. Other shapes are
+    // approximated with the inertia of a cube of the same mass.
+    let block_inertia = BLOCK_SIZE * BLOCK_SIZE / 6.0;
+
+    let mut mass = 0.0;
+    let mut moment = Vec3::ZERO;
+    let mut inertia = Mat3::ZERO;
+
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let block = chunk.get(x, y, z);
+                let Some(definition) = registry.get(block.id) else {
+                    continue;
+                };
+
+                let block_mass = definition.density * block_volume * block.shape.volume();
+                if block_mass <= 0.0 {
+                    continue;
+                }
+
+                let center = (Vec3::new(x as f32, y as f32, z as f32)
+                    + block.orientation.rotate_point(block.shape.centroid()))
+                    * BLOCK_SIZE;
+
+                mass += block_mass;
+                moment += center * block_mass;
+                inertia += Mat3::from_diagonal(Vec3::splat(block_mass * block_inertia))
+                    + point_inertia(block_mass, center);
+            }
+        }
+    }
+
+    (mass, moment, inertia)
 }
 
-pub fn regenerate_chunk_colliders(
-    mut chunk_changed_events: EventReader<ChunkChanged>,
-    chunk_query: Query<(&ChunkPos, &Parent)>,
-    grid_query: Query<&Grid>,
-    mut commands: Commands,
-) {
-    for chunk_changed in chunk_changed_events.read() {
-        let Ok((chunk_pos, parent)) = chunk_query.get(chunk_changed.0) else {
+/// Computes the mass, center of mass and inertia of a chunk from the density of its blocks.
+/// The center of mass is relative to the chunk's origin.
+pub fn generate_mass_properties_for_chunk(
+    chunk: &Chunk,
+    registry: &BlockRegistry,
+) -> ColliderMassProperties {
+    let (mass, moment, inertia) = chunk_mass_distribution(chunk, registry);
+    if mass <= 0.0 {
+        return ColliderMassProperties::Mass(0.0);
+    }
+
+    // Move the inertia from the chunk's origin to the center of mass
+    let center_of_mass = moment / mass;
+    let inertia = inertia - point_inertia(mass, center_of_mass);
+
+    let mass_properties = RapierMassProperties::with_inertia_matrix(
+        Point::new(center_of_mass.x, center_of_mass.y, center_of_mass.z),
+        mass,
+        Matrix3::from_column_slice(&inertia.to_cols_array()),
+    );
+
+    ColliderMassProperties::MassProperties(MassProperties::from_rapier(mass_properties, 1.0))
+}
+
+/// Mass properties of a whole grid in the grid's space
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GridMassProperties {
+    pub mass: f32,
+    pub center_of_mass: Vec3,
+    /// Inertia about the center of mass
+    pub inertia: Mat3,
+}
+
+/// Computes the mass properties of a grid the same way as those of its chunks
+pub fn grid_mass_properties(grid: &Grid, registry: &BlockRegistry) -> GridMassProperties {
+    let chunk_width = CHUNK_SIZE as f32 * BLOCK_SIZE;
+
+    let mut mass = 0.0;
+    let mut moment = Vec3::ZERO;
+    // Inertia about the grid's origin
+    let mut inertia = Mat3::ZERO;
+
+    for (chunk_pos, chunk) in grid.chunks() {
+        let (chunk_mass, chunk_moment, chunk_inertia) = chunk_mass_distribution(chunk, registry);
+        if chunk_mass <= 0.0 {
             continue;
-        };
+        }
 
-        let grid_entity = parent.get();
+        let chunk_origin =
+            Vec3::new(chunk_pos.x as f32, chunk_pos.y as f32, chunk_pos.z as f32) * chunk_width;
+        let chunk_center = chunk_moment / chunk_mass;
 
-        let Ok(grid) = grid_query.get(grid_entity) else {
-            return;
-        };
+        mass += chunk_mass;
+        moment += (chunk_origin + chunk_center) * chunk_mass;
+        inertia += chunk_inertia - point_inertia(chunk_mass, chunk_center)
+            + point_inertia(chunk_mass, chunk_origin + chunk_center);
+    }
+
+    if mass <= 0.0 {
+        return GridMassProperties::default();
+    }
 
-        let Some(chunk) = grid.get_chunk(*chunk_pos) else {
-            return;
-        };
+    let center_of_mass = moment / mass;
 
-        commands
-            .entity(chunk_changed.0)
-            .insert(generate_collider_for_chunk(chunk));
+    GridMassProperties {
+        mass,
+        center_of_mass,
+        inertia: inertia - point_inertia(mass, center_of_mass),
     }
 }
@@ -2,50 +2,83 @@ use bevy::ecs::system::{Command, SystemState};
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
+use crate::building::history::EditHistories;
+use crate::gyroscope::Gyroscopes;
+use crate::power::GridPower;
+use crate::rooms::Rooms;
+use crate::thruster::{ThrusterControl, Thrusters};
 use crate::UniverseGrid;
 
-use super::chunk::ChunkBundle;
-use super::collider::generate_collider_for_chunk;
-use super::mesh::generate_chunk_mesh;
-use super::{ChunkPos, Grid, GridMaterialHandle};
+use super::block::Block;
+use super::chunk::{Chunk, ChunkBundle, ChunkChanged, ChunkDirty};
+use super::collider::{
+    generate_fallback_collider_for_chunk, generate_mass_properties_for_chunk, grid_mass_properties,
+};
+use super::connectivity::find_cut_off_islands;
+use super::registry::BlockRegistry;
+use super::{ChunkPos, Grid, GridMaterialHandle, GridPos};
 
 pub struct SpawnGrid {
     pub transform: Transform,
+    pub grid_cell: UniverseGrid,
+    pub velocity: Velocity,
     pub grid: Grid,
 }
 
 impl Command for SpawnGrid {
-    fn apply(mut self, world: &mut World) {
-        let mut system_state: SystemState<(
-            ResMut<Assets<Mesh>>,
-            Res<GridMaterialHandle>,
-            Commands,
-        )> = SystemState::new(world);
+    fn apply(self, world: &mut World) {
+        self.spawn(world);
+    }
+}
 
-        let (mut meshes, material_handle, mut commands) = system_state.get_mut(world);
+impl SpawnGrid {
+    pub fn new(transform: Transform, grid: Grid) -> Self {
+        Self {
+            transform,
+            grid_cell: UniverseGrid::default(),
+            velocity: Velocity::default(),
+            grid,
+        }
+    }
 
-        let mut chunk_entities = Vec::with_capacity(self.grid.chunks.len());
+    /// Spawns the grid right away and returns its entity
+    pub fn spawn(mut self, world: &mut World) -> Entity {
+        let mut system_state: SystemState<(Res<GridMaterialHandle>, Res<BlockRegistry>, Commands)> =
+            SystemState::new(world);
 
-        for (pos, chunk) in self.grid.chunks.iter_mut() {
-            let mesh = generate_chunk_mesh(chunk);
-            let mesh_handle = meshes.add(mesh);
-            let collider = generate_collider_for_chunk(chunk);
+        let (material_handle, registry, mut commands) = system_state.get_mut(world);
 
-            let entity = commands
-                .spawn((
-                    ChunkBundle::new(*pos),
-                    mesh_handle,
-                    material_handle.0.clone(),
+        let mut chunk_entities = Vec::with_capacity(self.grid.chunks.len());
+
+        // Meshes and colliders are generated in the background once the grid exists. Until then,
+        // chunks collide as boxes around their blocks.
+        for (pos, chunk) in self.grid.chunks.iter() {
+            let mut chunk_commands = commands.spawn((
+                ChunkBundle::new(*pos),
+                material_handle.0.clone(),
+                ChunkDirty,
+            ));
+            if let Some(collider) = generate_fallback_collider_for_chunk(chunk, &registry) {
+                chunk_commands.insert((
                     collider,
-                ))
-                .id();
+                    generate_mass_properties_for_chunk(chunk, &registry),
+                ));
+            }
+            let entity = chunk_commands.id();
 
-            chunk_entities.push(entity);
+            chunk_entities.push((*pos, entity));
+        }
 
-            chunk.entity = entity;
+        for (pos, entity) in chunk_entities.iter() {
+            self.grid.chunks.get_mut(pos).unwrap().entity = *entity;
         }
 
-        commands
+        let chunk_entities: Vec<Entity> = chunk_entities
+            .into_iter()
+            .map(|(_, entity)| entity)
+            .collect();
+
+        let grid_entity = commands
             .spawn((
                 SpatialBundle {
                     transform: self.transform,
@@ -54,18 +87,24 @@ impl Command for SpawnGrid {
                 self.grid,
                 RigidBody::Dynamic,
                 Ccd::enabled(),
-                UniverseGrid::default(),
+                self.velocity,
+                // Read to fire thrusters and gyroscopes around the grid's center of mass
+                ReadMassProperties::default(),
+                ExternalForce::default(),
+                Thrusters::default(),
+                Gyroscopes::default(),
+                ThrusterControl::default(),
+                GridPower::default(),
+                Rooms::default(),
+                self.grid_cell,
                 TransformInterpolation::default(),
             ))
-            .push_children(&chunk_entities);
+            .push_children(&chunk_entities)
+            .id();
 
         system_state.apply(world);
-    }
-}
 
-impl SpawnGrid {
-    pub fn new(transform: Transform, grid: Grid) -> Self {
-        Self { transform, grid }
+        grid_entity
     }
 }
 
@@ -84,6 +123,15 @@ impl Command for DespawnChunk {
 
         let grid_entity = world.entity(self.entity).get::<Parent>().unwrap().get();
 
+        // Blocks placed after the chunk was emptied keep it alive
+        if world
+            .get::<Grid>(grid_entity)
+            .and_then(|grid| grid.get_chunk(chunk_pos))
+            .is_some_and(|chunk| chunk.entity == self.entity && !chunk.is_empty())
+        {
+            return;
+        }
+
         world
             .entity_mut(grid_entity)
             .remove_children(&[self.entity]);
@@ -98,3 +146,166 @@ impl Command for DespawnChunk {
         }
     }
 }
+
+// Velocity of the part of a grid centered on `center` while the grid spins about `grid_center`, both
+// in the grid's space
+fn velocity_of_part(
+    velocity: Velocity,
+    transform: &Transform,
+    grid_center: Vec3,
+    center: Vec3,
+) -> Velocity {
+    Velocity {
+        linvel: velocity.linvel
+            + velocity
+                .angvel
+                .cross(transform.rotation * (center - grid_center)),
+        angvel: velocity.angvel,
+    }
+}
+
+/// Moves every island of blocks that removing blocks cut off from the rest of a grid into a grid of
+/// its own. Which blocks stay in the original grid is decided by [`find_cut_off_islands`]. Each
+/// part keeps moving the way it did as part of the original grid.
+pub struct SplitGrid {
+    pub grid: Entity,
+    /// Blocks removed from the grid since it was last split
+    pub removed: Vec<GridPos>,
+}
+
+impl Command for SplitGrid {
+    fn apply(self, world: &mut World) {
+        let Some(grid) = world.get::<Grid>(self.grid) else {
+            return;
+        };
+
+        let islands = find_cut_off_islands(grid, &self.removed);
+        if islands.is_empty() {
+            return;
+        }
+
+        let grid_entity = world.entity(self.grid);
+        let transform = *grid_entity.get::<Transform>().unwrap();
+        let grid_cell = grid_entity
+            .get::<UniverseGrid>()
+            .copied()
+            .unwrap_or_default();
+        let velocity = grid_entity.get::<Velocity>().copied().unwrap_or_default();
+        let grid_center =
+            grid_mass_properties(grid, world.resource::<BlockRegistry>()).center_of_mass;
+
+        let mut grid = world.get_mut::<Grid>(self.grid).unwrap();
+        let mut changed_chunks = Vec::new();
+        let mut new_grids = Vec::with_capacity(islands.len());
+
+        for island in islands {
+            let mut new_grid = Grid::new();
+
+            for pos in island {
+                let chunk = grid.get_chunk_mut(pos.chunk_pos).unwrap();
+                let block = chunk.get_by_block_pos(pos.block_pos);
+                chunk.set_by_block_pos(pos.block_pos, Block::EMPTY);
+
+                if !changed_chunks.contains(&pos.chunk_pos) {
+                    changed_chunks.push(pos.chunk_pos);
+                }
+
+                match new_grid.get_chunk_mut(pos.chunk_pos) {
+                    Some(new_chunk) => new_chunk.set_by_block_pos(pos.block_pos, block),
+                    None => {
+                        let mut new_chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+                        new_chunk.set_by_block_pos(pos.block_pos, block);
+                        new_grid.set_chunk(pos.chunk_pos, Some(new_chunk));
+                    }
+                }
+
+                let damage = grid.damage(pos);
+                if damage > 0.0 {
+                    grid.repair(pos);
+                    new_grid.apply_damage(pos, damage);
+                }
+            }
+
+            new_grids.push(new_grid);
+        }
+
+        let mut empty_chunks = Vec::new();
+        let mut dirty_chunks = Vec::new();
+        for chunk in changed_chunks
+            .into_iter()
+            .filter_map(|chunk_pos| grid.get_chunk(chunk_pos))
+        {
+            if chunk.is_empty() {
+                empty_chunks.push(chunk.entity);
+            } else {
+                dirty_chunks.push(chunk.entity);
+            }
+        }
+
+        for entity in empty_chunks {
+            DespawnChunk { entity }.apply(world);
+        }
+
+        for entity in dirty_chunks {
+            world.send_event(ChunkChanged(entity));
+        }
+
+        if let Some(mut rooms) = world.get_mut::<Rooms>(self.grid) {
+            rooms.blocks_moved();
+        }
+
+        for grid in new_grids {
+            // Batteries keep their charge, rooms their air and blocks their edit history in the grid
+            // they end up in
+            let charges: Vec<(GridPos, f32)> = match world.get_mut::<GridPower>(self.grid) {
+                Some(mut power) => power.take_charges(|pos| !grid.get_block(pos).is_empty()),
+                None => Vec::new(),
+            };
+            let rooms = world
+                .get_mut::<Rooms>(self.grid)
+                .map(|mut rooms| rooms.split_off(&grid));
+            let center =
+                grid_mass_properties(&grid, world.resource::<BlockRegistry>()).center_of_mass;
+            let history = world
+                .get_resource_mut::<EditHistories>()
+                .and_then(|mut histories| Some(histories.0.get_mut(&self.grid)?.split_off(&grid)));
+
+            let new_grid_entity = SpawnGrid {
+                transform,
+                grid_cell,
+                velocity: velocity_of_part(velocity, &transform, grid_center, center),
+                grid,
+            }
+            .spawn(world);
+
+            let mut power = world.get_mut::<GridPower>(new_grid_entity).unwrap();
+            for (pos, charge) in charges {
+                power.set_charge(pos, charge);
+            }
+            if let Some(rooms) = rooms {
+                world.entity_mut(new_grid_entity).insert(rooms);
+            }
+            if let Some(history) = history {
+                world
+                    .resource_mut::<EditHistories>()
+                    .0
+                    .insert(new_grid_entity, history);
+            }
+        }
+
+        if let Some(mut power) = world.get_mut::<GridPower>(self.grid) {
+            power.rebuild();
+        }
+
+        let Some(grid) = world.get::<Grid>(self.grid) else {
+            return;
+        };
+        let center = grid_mass_properties(grid, world.resource::<BlockRegistry>()).center_of_mass;
+        world.entity_mut(self.grid).insert(velocity_of_part(
+            velocity,
+            &transform,
+            grid_center,
+            center,
+        ));
+    }
+}
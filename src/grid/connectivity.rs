@@ -0,0 +1,154 @@
+use std::cmp::Reverse;
+
+use bevy::utils::{HashMap, HashSet};
+
+use super::chunk::{BlockPos, CHUNK_SIZE};
+use super::{Grid, GridPos, NEIGHBOR_OFFSETS};
+
+/// Groups the blocks of a grid into islands of blocks that are connected through their faces.
+/// The islands are sorted from largest to smallest.
+pub fn find_islands(grid: &Grid) -> Vec<Vec<GridPos>> {
+    let mut visited: HashSet<GridPos> = HashSet::new();
+    let mut islands = Vec::new();
+
+    for (&chunk_pos, chunk) in grid.chunks() {
+        if chunk.is_empty() {
+            continue;
+        }
+
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let start = GridPos {
+                        chunk_pos,
+                        block_pos: BlockPos { x, y, z },
+                    };
+
+                    if chunk.get(x, y, z).is_empty() || !visited.insert(start) {
+                        continue;
+                    }
+
+                    // The island doubles as the queue of blocks whose neighbors still need checking
+                    let mut island = vec![start];
+                    let mut next = 0;
+
+                    while next < island.len() {
+                        let pos = island[next];
+                        next += 1;
+
+                        for offset in NEIGHBOR_OFFSETS {
+                            let neighbor_pos = pos + offset;
+                            if !grid.get_block(neighbor_pos).is_empty()
+                                && visited.insert(neighbor_pos)
+                            {
+                                island.push(neighbor_pos);
+                            }
+                        }
+                    }
+
+                    islands.push(island);
+                }
+            }
+        }
+    }
+
+    islands.sort_by_key(|island| Reverse(island.len()));
+
+    islands
+}
+
+// The search that a search has joined, following the searches it joined in turn
+fn root(joined: &mut [usize], mut search: usize) -> usize {
+    while joined[search] != search {
+        joined[search] = joined[joined[search]];
+        search = joined[search];
+    }
+
+    search
+}
+
+/// Finds the islands of blocks that removing the blocks at `removed` cut off from the rest of a
+/// grid. Searches start from the neighbors of the removed blocks and take turns, so a search that
+/// runs into another joins it, and the rest of the grid is never walked once only one search is
+/// left. The blocks of that search stay with the grid and aren't returned. If every search runs
+/// out, the largest island stays with the grid. The islands are sorted from largest to smallest.
+pub fn find_cut_off_islands(grid: &Grid, removed: &[GridPos]) -> Vec<Vec<GridPos>> {
+    // The search that reached each block first
+    let mut owners: HashMap<GridPos, usize> = HashMap::new();
+    // Each search's blocks double as its queue of blocks whose neighbors still need checking
+    let mut searches: Vec<(Vec<GridPos>, usize)> = Vec::new();
+    // The search that each search has joined, if any
+    let mut joined: Vec<usize> = Vec::new();
+
+    for &pos in removed {
+        for start in [pos]
+            .into_iter()
+            .chain(NEIGHBOR_OFFSETS.map(|offset| pos + offset))
+        {
+            if !grid.get_block(start).is_empty() && !owners.contains_key(&start) {
+                owners.insert(start, searches.len());
+                joined.push(searches.len());
+                searches.push((vec![start], 0));
+            }
+        }
+    }
+
+    loop {
+        let mut running = HashSet::new();
+
+        for search in 0..searches.len() {
+            let (blocks, next) = &mut searches[search];
+            let Some(&pos) = blocks.get(*next) else {
+                continue;
+            };
+            *next += 1;
+
+            for offset in NEIGHBOR_OFFSETS {
+                let neighbor_pos = pos + offset;
+                if grid.get_block(neighbor_pos).is_empty() {
+                    continue;
+                }
+
+                match owners.get(&neighbor_pos) {
+                    Some(&owner) => {
+                        let (a, b) = (root(&mut joined, search), root(&mut joined, owner));
+                        joined[a.max(b)] = a.min(b);
+                    }
+                    None => {
+                        owners.insert(neighbor_pos, search);
+                        searches[search].0.push(neighbor_pos);
+                    }
+                }
+            }
+
+            running.insert(search);
+        }
+
+        let running_roots: HashSet<usize> = running
+            .into_iter()
+            .map(|search| root(&mut joined, search))
+            .collect();
+        if running_roots.len() > 1 {
+            continue;
+        }
+
+        let mut islands: HashMap<usize, Vec<GridPos>> = HashMap::new();
+        for search in 0..searches.len() {
+            let search_root = root(&mut joined, search);
+            if !running_roots.contains(&search_root) {
+                islands
+                    .entry(search_root)
+                    .or_default()
+                    .extend(std::mem::take(&mut searches[search].0));
+            }
+        }
+
+        let mut islands: Vec<Vec<GridPos>> = islands.into_values().collect();
+        islands.sort_by_key(|island| Reverse(island.len()));
+        if running_roots.is_empty() && !islands.is_empty() {
+            islands.remove(0);
+        }
+
+        return islands;
+    }
+}
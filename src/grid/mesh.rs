@@ -1,44 +1,173 @@
 use bevy::prelude::*;
 use bevy::render::mesh::{Indices, Mesh, PrimitiveTopology};
-use bevy::render::primitives::Aabb;
 
-use super::block::BlockMaterial;
-use super::chunk::{Chunk, ChunkChanged, CHUNK_SIZE};
-use super::{ChunkPos, Grid};
+use super::block::{Block, BlockId};
+use super::chunk::{BlockPos, Chunk, CHUNK_SIZE};
+use super::registry::BlockRegistry;
+use super::shape::BlockShape;
+use super::textures::texture_layer;
+use super::{ChunkPos, Grid, GridPos};
 use crate::grid::block::BLOCK_SIZE;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FaceDirection {
+    Right,
+    Left,
+    Top,
+    Bottom,
+    Front,
+    Back,
+}
+
+impl FaceDirection {
+    const ALL: [Self; 6] = [
+        Self::Right,
+        Self::Left,
+        Self::Top,
+        Self::Bottom,
+        Self::Front,
+        Self::Back,
+    ];
+
+    // Maps a position in a layer perpendicular to the face normal back to chunk coordinates
+    fn chunk_coords(self, layer: u8, u: u8, v: u8) -> (u8, u8, u8) {
+        match self {
+            Self::Right | Self::Left => (layer, u, v),
+            Self::Top | Self::Bottom => (u, layer, v),
+            Self::Front | Self::Back => (u, v, layer),
+        }
+    }
+
+    fn normal(self) -> IVec3 {
+        match self {
+            Self::Right => IVec3::X,
+            Self::Left => IVec3::NEG_X,
+            Self::Top => IVec3::Y,
+            Self::Bottom => IVec3::NEG_Y,
+            Self::Front => IVec3::Z,
+            Self::Back => IVec3::NEG_Z,
+        }
+    }
+}
+
+struct MeshBuffers {
+    vertices: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    // Block tint in rgb, texture array layer in alpha
+    colors: Vec<[f32; 4]>,
+    triangles: Vec<u32>,
+    index_offset: u32,
+}
+
+impl MeshBuffers {
+    fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            triangles: Vec::new(),
+            index_offset: 0,
+        }
+    }
+
+    fn add_vertices(&mut self, verts: &[([f32; 3], [f32; 3], [f32; 2]); 4]) {
+        let index_offset = self.index_offset;
+
+        self.vertices.extend(verts.iter().map(|(p, _, _)| *p));
+        self.normals.extend(verts.iter().map(|(_, n, _)| *n));
+        self.uvs.extend(verts.iter().map(|(_, _, uv)| *uv));
+
+        self.triangles.extend([
+            index_offset,
+            index_offset + 1,
+            index_offset + 2,
+            index_offset + 2,
+            index_offset + 3,
+            index_offset,
+        ]);
+        self.index_offset += 4;
+    }
+
+    // Adds a convex polygon as a triangle fan. Textures are projected onto it along the axis it
+    // faces the most.
+    fn add_polygon(&mut self, positions: &[Vec3], normal: Vec3, color: [f32; 4]) {
+        let abs_normal = normal.abs();
+        let axis = if abs_normal.x >= abs_normal.y && abs_normal.x >= abs_normal.z {
+            0
+        } else if abs_normal.y >= abs_normal.z {
+            1
+        } else {
+            2
+        };
+
+        let index_offset = self.index_offset;
+
+        for position in positions {
+            self.vertices.push(position.to_array());
+            self.normals.push(normal.to_array());
+            self.uvs.push([
+                position[(axis + 1) % 3] / BLOCK_SIZE,
+                position[(axis + 2) % 3] / BLOCK_SIZE,
+            ]);
+            self.colors.push(color);
+        }
+
+        for index in 1..positions.len() as u32 - 1 {
+            self.triangles
+                .extend([index_offset, index_offset + index, index_offset + index + 1]);
+        }
+        self.index_offset += positions.len() as u32;
+    }
+
+    // Adds a quad covering `width` by `height` blocks starting at (`u`, `v`) in `layer`
+    fn add_quad(
+        &mut self,
+        direction: FaceDirection,
+        layer: u8,
+        u: u8,
+        v: u8,
+        width: u8,
+        height: u8,
+    ) {
+        let layer_min = layer as f32 * BLOCK_SIZE;
+        let layer_max = layer_min + BLOCK_SIZE;
+        let min_u = u as f32 * BLOCK_SIZE;
+        let max_u = (u + width) as f32 * BLOCK_SIZE;
+        let min_v = v as f32 * BLOCK_SIZE;
+        let max_v = (v + height) as f32 * BLOCK_SIZE;
+
+        match direction {
+            FaceDirection::Right => add_right_face(layer_max, min_u, max_u, min_v, max_v, self),
+            FaceDirection::Left => add_left_face(layer_min, min_u, max_u, min_v, max_v, self),
+            FaceDirection::Top => add_top_face(min_u, max_u, layer_max, min_v, max_v, self),
+            FaceDirection::Bottom => add_bottom_face(min_u, max_u, layer_min, min_v, max_v, self),
+            FaceDirection::Front => add_front_face(min_u, max_u, min_v, max_v, layer_max, self),
+            FaceDirection::Back => add_back_face(min_u, max_u, min_v, max_v, layer_min, self),
+        }
+    }
+}
+
 fn add_right_face(
     x: f32,
     min_y: f32,
     max_y: f32,
     min_z: f32,
     max_z: f32,
-    vertices: &mut Vec<[f32; 3]>,
-    triangles: &mut Vec<u32>,
-    normals: &mut Vec<[f32; 3]>,
-    uvs: &mut Vec<[f32; 2]>,
-    index_offset: &mut u32,
+    buffers: &mut MeshBuffers,
 ) {
+    let height = (max_y - min_y) / BLOCK_SIZE;
+    let depth = (max_z - min_z) / BLOCK_SIZE;
+
     let verts = &[
         ([x, min_y, min_z], [1.0, 0., 0.], [0., 0.]),
-        ([x, max_y, min_z], [1.0, 0., 0.], [1.0, 0.]),
-        ([x, max_y, max_z], [1.0, 0., 0.], [1.0, 1.0]),
-        ([x, min_y, max_z], [1.0, 0., 0.], [0., 1.0]),
+        ([x, max_y, min_z], [1.0, 0., 0.], [height, 0.]),
+        ([x, max_y, max_z], [1.0, 0., 0.], [height, depth]),
+        ([x, min_y, max_z], [1.0, 0., 0.], [0., depth]),
     ];
 
-    vertices.extend(verts.iter().map(|(p, _, _)| *p));
-    normals.extend(verts.iter().map(|(_, n, _)| *n));
-    uvs.extend(verts.iter().map(|(_, _, uv)| *uv));
-
-    triangles.extend([
-        *index_offset,
-        *index_offset + 1,
-        *index_offset + 2,
-        *index_offset + 2,
-        *index_offset + 3,
-        *index_offset,
-    ]);
-    *index_offset += 4;
+    buffers.add_vertices(verts);
 }
 
 fn add_left_face(
@@ -47,66 +176,33 @@ fn add_left_face(
     max_y: f32,
     min_z: f32,
     max_z: f32,
-    vertices: &mut Vec<[f32; 3]>,
-    triangles: &mut Vec<u32>,
-    normals: &mut Vec<[f32; 3]>,
-    uvs: &mut Vec<[f32; 2]>,
-    index_offset: &mut u32,
+    buffers: &mut MeshBuffers,
 ) {
+    let height = (max_y - min_y) / BLOCK_SIZE;
+    let depth = (max_z - min_z) / BLOCK_SIZE;
+
     let verts = &[
-        ([x, min_y, max_z], [-1.0, 0., 0.], [1.0, 0.]),
+        ([x, min_y, max_z], [-1.0, 0., 0.], [height, 0.]),
         ([x, max_y, max_z], [-1.0, 0., 0.], [0., 0.]),
-        ([x, max_y, min_z], [-1.0, 0., 0.], [0., 1.0]),
-        ([x, min_y, min_z], [-1.0, 0., 0.], [1.0, 1.0]),
+        ([x, max_y, min_z], [-1.0, 0., 0.], [0., depth]),
+        ([x, min_y, min_z], [-1.0, 0., 0.], [height, depth]),
     ];
 
-    vertices.extend(verts.iter().map(|(p, _, _)| *p));
-    normals.extend(verts.iter().map(|(_, n, _)| *n));
-    uvs.extend(verts.iter().map(|(_, _, uv)| *uv));
-
-    triangles.extend([
-        *index_offset,
-        *index_offset + 1,
-        *index_offset + 2,
-        *index_offset + 2,
-        *index_offset + 3,
-        *index_offset,
-    ]);
-    *index_offset += 4;
+    buffers.add_vertices(verts);
 }
 
-fn add_top_face(
-    min_x: f32,
-    max_x: f32,
-    y: f32,
-    min_z: f32,
-    max_z: f32,
-    vertices: &mut Vec<[f32; 3]>,
-    triangles: &mut Vec<u32>,
-    normals: &mut Vec<[f32; 3]>,
-    uvs: &mut Vec<[f32; 2]>,
-    index_offset: &mut u32,
-) {
+fn add_top_face(min_x: f32, max_x: f32, y: f32, min_z: f32, max_z: f32, buffers: &mut MeshBuffers) {
+    let width = (max_x - min_x) / BLOCK_SIZE;
+    let depth = (max_z - min_z) / BLOCK_SIZE;
+
     let verts = &[
-        ([max_x, y, min_z], [0., 1.0, 0.], [1.0, 0.]),
+        ([max_x, y, min_z], [0., 1.0, 0.], [width, 0.]),
         ([min_x, y, min_z], [0., 1.0, 0.], [0., 0.]),
-        ([min_x, y, max_z], [0., 1.0, 0.], [0., 1.0]),
-        ([max_x, y, max_z], [0., 1.0, 0.], [1.0, 1.0]),
+        ([min_x, y, max_z], [0., 1.0, 0.], [0., depth]),
+        ([max_x, y, max_z], [0., 1.0, 0.], [width, depth]),
     ];
 
-    vertices.extend(verts.iter().map(|(p, _, _)| *p));
-    normals.extend(verts.iter().map(|(_, n, _)| *n));
-    uvs.extend(verts.iter().map(|(_, _, uv)| *uv));
-
-    triangles.extend([
-        *index_offset,
-        *index_offset + 1,
-        *index_offset + 2,
-        *index_offset + 2,
-        *index_offset + 3,
-        *index_offset,
-    ]);
-    *index_offset += 4;
+    buffers.add_vertices(verts);
 }
 
 fn add_bottom_face(
@@ -115,32 +211,19 @@ fn add_bottom_face(
     y: f32,
     min_z: f32,
     max_z: f32,
-    vertices: &mut Vec<[f32; 3]>,
-    triangles: &mut Vec<u32>,
-    normals: &mut Vec<[f32; 3]>,
-    uvs: &mut Vec<[f32; 2]>,
-    index_offset: &mut u32,
+    buffers: &mut MeshBuffers,
 ) {
+    let width = (max_x - min_x) / BLOCK_SIZE;
+    let depth = (max_z - min_z) / BLOCK_SIZE;
+
     let verts = &[
         ([max_x, y, max_z], [0., -1.0, 0.], [0., 0.]),
-        ([min_x, y, max_z], [0., -1.0, 0.], [1.0, 0.]),
-        ([min_x, y, min_z], [0., -1.0, 0.], [1.0, 1.0]),
-        ([max_x, y, min_z], [0., -1.0, 0.], [0., 1.0]),
+        ([min_x, y, max_z], [0., -1.0, 0.], [width, 0.]),
+        ([min_x, y, min_z], [0., -1.0, 0.], [width, depth]),
+        ([max_x, y, min_z], [0., -1.0, 0.], [0., depth]),
     ];
 
-    vertices.extend(verts.iter().map(|(p, _, _)| *p));
-    normals.extend(verts.iter().map(|(_, n, _)| *n));
-    uvs.extend(verts.iter().map(|(_, _, uv)| *uv));
-
-    triangles.extend([
-        *index_offset,
-        *index_offset + 1,
-        *index_offset + 2,
-        *index_offset + 2,
-        *index_offset + 3,
-        *index_offset,
-    ]);
-    *index_offset += 4;
+    buffers.add_vertices(verts);
 }
 
 fn add_front_face(
@@ -149,32 +232,19 @@ fn add_front_face(
     min_y: f32,
     max_y: f32,
     z: f32,
-    vertices: &mut Vec<[f32; 3]>,
-    triangles: &mut Vec<u32>,
-    normals: &mut Vec<[f32; 3]>,
-    uvs: &mut Vec<[f32; 2]>,
-    index_offset: &mut u32,
+    buffers: &mut MeshBuffers,
 ) {
+    let width = (max_x - min_x) / BLOCK_SIZE;
+    let height = (max_y - min_y) / BLOCK_SIZE;
+
     let verts = &[
         ([min_x, min_y, z], [0., 0., 1.0], [0., 0.]),
-        ([max_x, min_y, z], [0., 0., 1.0], [1.0, 0.]),
-        ([max_x, max_y, z], [0., 0., 1.0], [1.0, 1.0]),
-        ([min_x, max_y, z], [0., 0., 1.0], [0., 1.0]),
+        ([max_x, min_y, z], [0., 0., 1.0], [width, 0.]),
+        ([max_x, max_y, z], [0., 0., 1.0], [width, height]),
+        ([min_x, max_y, z], [0., 0., 1.0], [0., height]),
     ];
 
-    vertices.extend(verts.iter().map(|(p, _, _)| *p));
-    normals.extend(verts.iter().map(|(_, n, _)| *n));
-    uvs.extend(verts.iter().map(|(_, _, uv)| *uv));
-
-    triangles.extend([
-        *index_offset,
-        *index_offset + 1,
-        *index_offset + 2,
-        *index_offset + 2,
-        *index_offset + 3,
-        *index_offset,
-    ]);
-    *index_offset += 4;
+    buffers.add_vertices(verts);
 }
 
 fn add_back_face(
@@ -183,189 +253,270 @@ fn add_back_face(
     min_y: f32,
     max_y: f32,
     z: f32,
-    vertices: &mut Vec<[f32; 3]>,
-    triangles: &mut Vec<u32>,
-    normals: &mut Vec<[f32; 3]>,
-    uvs: &mut Vec<[f32; 2]>,
-    index_offset: &mut u32,
+    buffers: &mut MeshBuffers,
 ) {
+    let width = (max_x - min_x) / BLOCK_SIZE;
+    let height = (max_y - min_y) / BLOCK_SIZE;
+
     let verts = &[
-        ([min_x, max_y, z], [0., 0., -1.0], [1.0, 0.]),
+        ([min_x, max_y, z], [0., 0., -1.0], [width, 0.]),
         ([max_x, max_y, z], [0., 0., -1.0], [0., 0.]),
-        ([max_x, min_y, z], [0., 0., -1.0], [0., 1.0]),
-        ([min_x, min_y, z], [0., 0., -1.0], [1.0, 1.0]),
+        ([max_x, min_y, z], [0., 0., -1.0], [0., height]),
+        ([min_x, min_y, z], [0., 0., -1.0], [width, height]),
     ];
 
-    vertices.extend(verts.iter().map(|(p, _, _)| *p));
-    normals.extend(verts.iter().map(|(_, n, _)| *n));
-    uvs.extend(verts.iter().map(|(_, _, uv)| *uv));
-
-    triangles.extend([
-        *index_offset,
-        *index_offset + 1,
-        *index_offset + 2,
-        *index_offset + 2,
-        *index_offset + 3,
-        *index_offset,
-    ]);
-    *index_offset += 4;
+    buffers.add_vertices(verts);
 }
 
-pub fn generate_chunk_mesh(chunk: &Chunk) -> Mesh {
-    let mut vertices: Vec<[f32; 3]> = Vec::new();
-    let mut normals: Vec<[f32; 3]> = Vec::new();
-    let mut uvs: Vec<[f32; 2]> = Vec::new();
-    let mut triangles: Vec<u32> = Vec::new();
-    let mut index_offset = 0;
-
-    for c_z in 0..CHUNK_SIZE {
-        for c_y in 0..CHUNK_SIZE {
-            for c_x in 0..CHUNK_SIZE {
-                if chunk.get(c_x, c_y, c_z).material == BlockMaterial::Empty {
-                    continue;
-                }
+fn neighbor_block(
+    grid: &Grid,
+    chunk_pos: ChunkPos,
+    chunk: &Chunk,
+    block_pos: BlockPos,
+    direction: IVec3,
+) -> Block {
+    let neighbor =
+        IVec3::new(block_pos.x as i32, block_pos.y as i32, block_pos.z as i32) + direction;
+
+    let range = 0..CHUNK_SIZE as i32;
+    if range.contains(&neighbor.x) && range.contains(&neighbor.y) && range.contains(&neighbor.z) {
+        chunk.get(neighbor.x as u8, neighbor.y as u8, neighbor.z as u8)
+    } else {
+        // The neighbor is in an adjacent chunk of the same grid
+        let pos = GridPos {
+            chunk_pos,
+            block_pos,
+        };
+        grid.get_block(pos + (direction.x as i16, direction.y as i16, direction.z as i16))
+    }
+}
 
-                let x = c_x as f32 * BLOCK_SIZE;
-                let y = c_y as f32 * BLOCK_SIZE;
-                let z = c_z as f32 * BLOCK_SIZE;
-
-                if c_x == CHUNK_SIZE - 1
-                    || chunk.get(c_x + 1, c_y, c_z).material == BlockMaterial::Empty
-                {
-                    add_right_face(
-                        x + BLOCK_SIZE,
-                        y,
-                        y + BLOCK_SIZE,
-                        z,
-                        z + BLOCK_SIZE,
-                        &mut vertices,
-                        &mut triangles,
-                        &mut normals,
-                        &mut uvs,
-                        &mut index_offset,
-                    );
-                }
+// Faces are hidden by neighbors that completely cover the side of the block they are on. Faces
+// between blocks of the same transparent type are hidden as well so that they look like one volume.
+fn is_side_visible(
+    registry: &BlockRegistry,
+    block: Block,
+    neighbor: Block,
+    direction: IVec3,
+) -> bool {
+    !neighbor.covers_side(-direction)
+        || (neighbor.id != block.id && registry.is_transparent(neighbor))
+}
 
-                if c_x == 0 || chunk.get(c_x - 1, c_y, c_z).material == BlockMaterial::Empty {
-                    add_left_face(
-                        x,
-                        y,
-                        y + BLOCK_SIZE,
-                        z,
-                        z + BLOCK_SIZE,
-                        &mut vertices,
-                        &mut triangles,
-                        &mut normals,
-                        &mut uvs,
-                        &mut index_offset,
-                    );
-                }
+fn is_face_visible(
+    grid: &Grid,
+    registry: &BlockRegistry,
+    chunk_pos: ChunkPos,
+    chunk: &Chunk,
+    block_pos: BlockPos,
+    direction: FaceDirection,
+) -> bool {
+    let neighbor = neighbor_block(grid, chunk_pos, chunk, block_pos, direction.normal());
+
+    is_side_visible(
+        registry,
+        chunk.get_by_block_pos(block_pos),
+        neighbor,
+        direction.normal(),
+    )
+}
+
+// Blocks that aren't cubes can't be merged with their neighbors, so their faces are added one by one
+fn add_shaped_block(
+    grid: &Grid,
+    registry: &BlockRegistry,
+    chunk_pos: ChunkPos,
+    chunk: &Chunk,
+    block_pos: BlockPos,
+    buffers: &mut MeshBuffers,
+) {
+    let block = chunk.get_by_block_pos(block_pos);
+    let origin = Vec3::new(block_pos.x as f32, block_pos.y as f32, block_pos.z as f32);
+    let color = vertex_color(registry, block.id);
+
+    for face in block.shape.faces() {
+        if let Some(side) = face.side {
+            let direction = block.orientation.rotate(side);
+            let neighbor = neighbor_block(grid, chunk_pos, chunk, block_pos, direction);
+
+            if !is_side_visible(registry, block, neighbor, direction) {
+                continue;
+            }
+        }
 
-                if c_y == CHUNK_SIZE - 1
-                    || chunk.get(c_x, c_y + 1, c_z).material == BlockMaterial::Empty
-                {
-                    add_top_face(
-                        x,
-                        x + BLOCK_SIZE,
-                        y + BLOCK_SIZE,
-                        z,
-                        z + BLOCK_SIZE,
-                        &mut vertices,
-                        &mut triangles,
-                        &mut normals,
-                        &mut uvs,
-                        &mut index_offset,
-                    );
+        let positions: Vec<Vec3> = face
+            .vertices
+            .iter()
+            .map(|&vertex| (origin + block.orientation.rotate_point(vertex)) * BLOCK_SIZE)
+            .collect();
+
+        buffers.add_polygon(
+            &positions,
+            block.orientation.rotation() * face.normal(),
+            color,
+        );
+    }
+}
+
+const LAYER_SIZE: usize = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
+
+fn layer_index(u: u8, v: u8) -> usize {
+    v as usize * CHUNK_SIZE as usize + u as usize
+}
+
+// Merges the visible faces of a single layer into as few quads as possible.
+// Faces are only merged with other faces of the same block type.
+fn greedy_mesh_layer(
+    registry: &BlockRegistry,
+    mask: &mut [Option<BlockId>; LAYER_SIZE],
+    direction: FaceDirection,
+    layer: u8,
+    buffers: &mut MeshBuffers,
+) {
+    for v in 0..CHUNK_SIZE {
+        let mut u = 0;
+        while u < CHUNK_SIZE {
+            let Some(id) = mask[layer_index(u, v)] else {
+                u += 1;
+                continue;
+            };
+
+            let mut width = 1;
+            while u + width < CHUNK_SIZE && mask[layer_index(u + width, v)] == Some(id) {
+                width += 1;
+            }
+
+            let mut height = 1;
+            'height: while v + height < CHUNK_SIZE {
+                for du in 0..width {
+                    if mask[layer_index(u + du, v + height)] != Some(id) {
+                        break 'height;
+                    }
                 }
 
-                if c_y == 0 || chunk.get(c_x, c_y - 1, c_z).material == BlockMaterial::Empty {
-                    add_bottom_face(
-                        x,
-                        x + BLOCK_SIZE,
-                        y,
-                        z,
-                        z + BLOCK_SIZE,
-                        &mut vertices,
-                        &mut triangles,
-                        &mut normals,
-                        &mut uvs,
-                        &mut index_offset,
-                    );
+                height += 1;
+            }
+
+            for dv in 0..height {
+                for du in 0..width {
+                    mask[layer_index(u + du, v + dv)] = None;
                 }
+            }
 
-                if c_z == CHUNK_SIZE - 1
-                    || chunk.get(c_x, c_y, c_z + 1).material == BlockMaterial::Empty
-                {
-                    add_front_face(
-                        x,
-                        x + BLOCK_SIZE,
-                        y,
-                        y + BLOCK_SIZE,
-                        z + BLOCK_SIZE,
-                        &mut vertices,
-                        &mut triangles,
-                        &mut normals,
-                        &mut uvs,
-                        &mut index_offset,
-                    );
+            buffers.add_quad(direction, layer, u, v, width, height);
+            buffers.colors.extend([vertex_color(registry, id); 4]);
+
+            u += width;
+        }
+    }
+}
+
+fn vertex_color(registry: &BlockRegistry, id: BlockId) -> [f32; 4] {
+    let [r, g, b] = registry
+        .get(id)
+        .map_or([1.0; 3], |definition| definition.tint);
+
+    [r, g, b, texture_layer(id) as f32]
+}
+
+pub fn generate_chunk_mesh(
+    grid: &Grid,
+    registry: &BlockRegistry,
+    chunk_pos: ChunkPos,
+) -> Option<Mesh> {
+    let chunk = grid.get_chunk(chunk_pos)?;
+
+    let mut buffers = MeshBuffers::new();
+    let mut mask = [None; LAYER_SIZE];
+
+    for direction in FaceDirection::ALL {
+        for layer in 0..CHUNK_SIZE {
+            for v in 0..CHUNK_SIZE {
+                for u in 0..CHUNK_SIZE {
+                    let (x, y, z) = direction.chunk_coords(layer, u, v);
+                    let block = chunk.get(x, y, z);
+
+                    mask[layer_index(u, v)] = if !block.is_empty()
+                        && block.shape == BlockShape::Cube
+                        && is_face_visible(
+                            grid,
+                            registry,
+                            chunk_pos,
+                            chunk,
+                            BlockPos { x, y, z },
+                            direction,
+                        ) {
+                        Some(block.id)
+                    } else {
+                        None
+                    };
                 }
+            }
+
+            greedy_mesh_layer(registry, &mut mask, direction, layer, &mut buffers);
+        }
+    }
 
-                if c_z == 0 || chunk.get(c_x, c_y, c_z - 1).material == BlockMaterial::Empty {
-                    add_back_face(
-                        x,
-                        x + BLOCK_SIZE,
-                        y,
-                        y + BLOCK_SIZE,
-                        z,
-                        &mut vertices,
-                        &mut triangles,
-                        &mut normals,
-                        &mut uvs,
-                        &mut index_offset,
-                    );
+    if chunk
+        .palette()
+        .any(|block| !block.is_empty() && block.shape != BlockShape::Cube)
+    {
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let block = chunk.get(x, y, z);
+                    if !block.is_empty() && block.shape != BlockShape::Cube {
+                        add_shaped_block(
+                            grid,
+                            registry,
+                            chunk_pos,
+                            chunk,
+                            BlockPos { x, y, z },
+                            &mut buffers,
+                        );
+                    }
                 }
             }
         }
     }
 
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-    mesh.set_indices(Some(Indices::U32(triangles)));
-    mesh.generate_tangents().unwrap();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, buffers.vertices);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, buffers.normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, buffers.uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, buffers.colors);
+    let is_empty = buffers.triangles.is_empty();
+    mesh.set_indices(Some(Indices::U32(buffers.triangles)));
+
+    // Tangent generation fails on meshes without any triangles
+    if is_empty {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, Vec::<[f32; 4]>::new());
+    } else {
+        mesh.generate_tangents().unwrap();
+    }
 
-    mesh
+    Some(mesh)
 }
 
-pub fn regenerate_chunk_meshes(
-    mut chunk_changed_events: EventReader<ChunkChanged>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    chunk_query: Query<(&ChunkPos, &Parent)>,
-    grid_query: Query<&Grid>,
-    mut commands: Commands,
-) {
-    for chunk_changed in chunk_changed_events.read() {
-        let Ok((chunk_pos, parent)) = chunk_query.get(chunk_changed.0) else {
-            continue;
-        };
+/// Mesh of a single block of the given shape centered on the origin, used to preview placing it
+pub fn generate_block_mesh(shape: BlockShape) -> Mesh {
+    let mut buffers = MeshBuffers::new();
 
-        let grid_entity = parent.get();
+    for face in shape.faces() {
+        let positions: Vec<Vec3> = face
+            .vertices
+            .iter()
+            .map(|&vertex| (vertex - 0.5) * BLOCK_SIZE)
+            .collect();
 
-        let Ok(grid) = grid_query.get(grid_entity) else {
-            return;
-        };
+        buffers.add_polygon(&positions, face.normal(), [1.0; 4]);
+    }
 
-        let Some(chunk) = grid.get_chunk(*chunk_pos) else {
-            return;
-        };
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, buffers.vertices);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, buffers.normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, buffers.uvs);
+    mesh.set_indices(Some(Indices::U32(buffers.triangles)));
 
-        let mesh = generate_chunk_mesh(chunk);
-        let mesh_handle = meshes.add(mesh);
-        commands
-            .entity(chunk_changed.0)
-            .insert(mesh_handle)
-            // Work around mesh AABBs not being updated automatically: https://github.com/bevyengine/bevy/issues/4294
-            .remove::<Aabb>();
-    }
+    mesh
 }
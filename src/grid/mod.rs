@@ -1,15 +1,27 @@
 pub mod block;
+pub mod blueprint;
 pub mod chunk;
 pub mod collider;
 pub mod command;
+pub mod connectivity;
 pub mod mesh;
+pub mod palette;
 pub mod plugin;
+pub mod rebuild;
+pub mod registry;
+pub mod shape;
+pub mod structure;
+pub mod textures;
+pub mod weld;
 
 use std::ops::Add;
 
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 
+use crate::building_material::BuildingMaterialType;
+
+use self::block::{Block, BLOCK_SIZE};
 use self::chunk::{BlockPos, Chunk, CHUNK_SIZE};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Component)]
@@ -25,12 +37,47 @@ impl ChunkPos {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+// Offsets to the six blocks sharing a face with a block
+pub const NEIGHBOR_OFFSETS: [(i16, i16, i16); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct GridPos {
     pub chunk_pos: ChunkPos,
     pub block_pos: BlockPos,
 }
 
+impl GridPos {
+    /// Position of the block counted in blocks from the grid's origin
+    pub fn to_block_coords(self) -> IVec3 {
+        IVec3::new(
+            self.chunk_pos.x as i32 * CHUNK_SIZE as i32 + self.block_pos.x as i32,
+            self.chunk_pos.y as i32 * CHUNK_SIZE as i32 + self.block_pos.y as i32,
+            self.chunk_pos.z as i32 * CHUNK_SIZE as i32 + self.block_pos.z as i32,
+        )
+    }
+
+    pub fn from_block_coords(coords: IVec3) -> Self {
+        let chunk_pos = coords.div_euclid(IVec3::splat(CHUNK_SIZE as i32));
+        let block_pos = coords.rem_euclid(IVec3::splat(CHUNK_SIZE as i32));
+
+        Self {
+            chunk_pos: ChunkPos::new(chunk_pos.x as i16, chunk_pos.y as i16, chunk_pos.z as i16),
+            block_pos: BlockPos {
+                x: block_pos.x as u8,
+                y: block_pos.y as u8,
+                z: block_pos.z as u8,
+            },
+        }
+    }
+}
+
 impl Add<(i16, i16, i16)> for GridPos {
     type Output = Self;
 
@@ -60,15 +107,22 @@ impl Add<(i16, i16, i16)> for GridPos {
 #[derive(Component)]
 pub struct Grid {
     chunks: HashMap<ChunkPos, Chunk>,
+    // Damage taken by each damaged block
+    damage: HashMap<GridPos, f32>,
 }
 
 impl Grid {
     pub fn new() -> Self {
         Self {
             chunks: HashMap::new(),
+            damage: HashMap::new(),
         }
     }
 
+    pub fn chunks(&self) -> impl Iterator<Item = (&ChunkPos, &Chunk)> {
+        self.chunks.iter()
+    }
+
     pub fn get_chunk(&self, pos: ChunkPos) -> Option<&Chunk> {
         self.chunks.get(&pos)
     }
@@ -77,6 +131,47 @@ impl Grid {
         self.chunks.get_mut(&pos)
     }
 
+    pub fn get_block(&self, pos: GridPos) -> Block {
+        match self.get_chunk(pos.chunk_pos) {
+            Some(chunk) => chunk.get_by_block_pos(pos.block_pos),
+            None => Block::EMPTY,
+        }
+    }
+
+    /// Adds to the damage taken by a block and returns its total damage. Empty blocks can't be
+    /// damaged.
+    pub fn apply_damage(&mut self, pos: GridPos, amount: f32) -> f32 {
+        if self.get_block(pos).is_empty() {
+            return 0.0;
+        }
+
+        let damage = self.damage.entry(pos).or_insert(0.0);
+        *damage += amount;
+        *damage
+    }
+
+    pub fn damage(&self, pos: GridPos) -> f32 {
+        self.damage.get(&pos).copied().unwrap_or(0.0)
+    }
+
+    pub fn damaged_blocks(&self) -> impl Iterator<Item = (GridPos, f32)> + '_ {
+        self.damage.iter().map(|(&pos, &damage)| (pos, damage))
+    }
+
+    /// Clears the damage taken by a block, called whenever a block is placed or removed
+    pub fn repair(&mut self, pos: GridPos) {
+        self.damage.remove(&pos);
+    }
+
+    /// Clears the damage taken by every block in the box between two corners, given in block
+    /// coordinates
+    pub fn repair_area(&mut self, min: IVec3, max: IVec3) {
+        self.damage.retain(|pos, _| {
+            let coords = pos.to_block_coords();
+            coords.cmplt(min).any() || coords.cmpgt(max).any()
+        });
+    }
+
     pub fn set_chunk(&mut self, pos: ChunkPos, chunk: Option<Chunk>) {
         match chunk {
             Some(chunk) => {
@@ -89,5 +184,47 @@ impl Grid {
     }
 }
 
+pub fn snap_to_grid(point: Vec3, snap_resolution: f32) -> Vec3 {
+    // This extra rounding smoothes out any jittering
+    let rounded_x = (point.x * 1000.0).round();
+    let rounded_y = (point.y * 1000.0).round();
+    let rounded_z = (point.z * 1000.0).round();
+
+    let x = (rounded_x * 1.0 / (snap_resolution * 1000.0)).floor() / (1.0 / snap_resolution);
+    let y = (rounded_y * 1.0 / (snap_resolution * 1000.0)).floor() / (1.0 / snap_resolution);
+    let z = (rounded_z * 1.0 / (snap_resolution * 1000.0)).floor() / (1.0 / snap_resolution);
+
+    Vec3::new(x, y, z)
+}
+
+/// Finds the block whose face was hit at a point on the surface of a chunk, along with the direction
+/// the face points in on the grid. The point and normal are in world space.
+pub fn block_at_surface(
+    chunk_transform: &GlobalTransform,
+    chunk_pos: ChunkPos,
+    point: Vec3,
+    normal: Vec3,
+) -> (GridPos, IVec3) {
+    let chunk_transform_inverse = chunk_transform.affine().inverse();
+
+    let inverse_normal = chunk_transform_inverse.transform_vector3(normal).round();
+
+    // Half a block back from the face is inside the block
+    let snapped_point = snap_to_grid(chunk_transform_inverse.transform_point(point), BLOCK_SIZE)
+        / BLOCK_SIZE
+        - inverse_normal * 0.5;
+
+    let chunk_origin = GridPos {
+        chunk_pos,
+        block_pos: BlockPos { x: 0, y: 0, z: 0 },
+    }
+    .to_block_coords();
+
+    (
+        GridPos::from_block_coords(chunk_origin + snapped_point.floor().as_ivec3()),
+        inverse_normal.as_ivec3(),
+    )
+}
+
 #[derive(Resource)]
-pub struct GridMaterialHandle(pub Handle<StandardMaterial>);
+pub struct GridMaterialHandle(pub Handle<BuildingMaterialType>);
@@ -0,0 +1,148 @@
+use super::block::Block;
+use super::chunk::CHUNK_SIZE_CUBED;
+
+#[derive(Clone, Copy, Debug)]
+struct PaletteEntry {
+    block: Block,
+    // Number of blocks using this entry. Entries with a count of zero are free to be reused.
+    count: u16,
+}
+
+/// Block storage for a chunk. Every distinct block is stored once in a palette and blocks are
+/// stored as bit-packed indices into it. A chunk made of a single block only stores that block.
+#[derive(Clone, Debug)]
+pub struct PalettedBlocks {
+    palette: Vec<PaletteEntry>,
+    // Always a power of two so that indices never straddle two words
+    bits_per_index: u32,
+    data: Vec<u64>,
+}
+
+impl PalettedBlocks {
+    pub fn filled(block: Block) -> Self {
+        Self {
+            palette: vec![PaletteEntry {
+                block,
+                count: CHUNK_SIZE_CUBED as u16,
+            }],
+            bits_per_index: 0,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn from_blocks(blocks: &[Block]) -> Self {
+        assert_eq!(blocks.len(), CHUNK_SIZE_CUBED);
+
+        let mut paletted_blocks = Self::filled(blocks[0]);
+        for (index, block) in blocks.iter().enumerate().skip(1) {
+            paletted_blocks.set(index, *block);
+        }
+
+        paletted_blocks
+    }
+
+    pub fn get(&self, index: usize) -> Block {
+        self.palette[self.read_index(index)].block
+    }
+
+    pub fn set(&mut self, index: usize, block: Block) {
+        let old_palette_index = self.read_index(index);
+        if self.palette[old_palette_index].block == block {
+            return;
+        }
+
+        let new_palette_index = match self
+            .palette
+            .iter()
+            .position(|entry| entry.count > 0 && entry.block == block)
+        {
+            Some(palette_index) => palette_index,
+            None => self.add_palette_entry(block),
+        };
+
+        self.write_index(index, new_palette_index);
+        self.palette[old_palette_index].count -= 1;
+        self.palette[new_palette_index].count += 1;
+
+        if self.palette[old_palette_index].count == 0 {
+            self.collapse_if_uniform();
+        }
+    }
+
+    /// Iterates over the distinct blocks that are currently stored
+    pub fn palette(&self) -> impl Iterator<Item = Block> + '_ {
+        self.palette
+            .iter()
+            .filter(|entry| entry.count > 0)
+            .map(|entry| entry.block)
+    }
+
+    pub fn is_uniform(&self) -> bool {
+        self.bits_per_index == 0
+    }
+
+    fn indices_per_word(&self) -> usize {
+        64 / self.bits_per_index as usize
+    }
+
+    fn read_index(&self, index: usize) -> usize {
+        assert!(index < CHUNK_SIZE_CUBED);
+
+        if self.bits_per_index == 0 {
+            return 0;
+        }
+
+        let indices_per_word = self.indices_per_word();
+        let word = self.data[index / indices_per_word];
+        let shift = (index % indices_per_word) as u32 * self.bits_per_index;
+        let mask = (1u64 << self.bits_per_index) - 1;
+
+        ((word >> shift) & mask) as usize
+    }
+
+    fn write_index(&mut self, index: usize, palette_index: usize) {
+        let indices_per_word = self.indices_per_word();
+        let word = &mut self.data[index / indices_per_word];
+        let shift = (index % indices_per_word) as u32 * self.bits_per_index;
+        let mask = (1u64 << self.bits_per_index) - 1;
+
+        *word = (*word & !(mask << shift)) | ((palette_index as u64 & mask) << shift);
+    }
+
+    fn add_palette_entry(&mut self, block: Block) -> usize {
+        if let Some(free_index) = self.palette.iter().position(|entry| entry.count == 0) {
+            self.palette[free_index].block = block;
+            return free_index;
+        }
+
+        self.palette.push(PaletteEntry { block, count: 0 });
+
+        let required_bits = usize::BITS - (self.palette.len() - 1).leading_zeros();
+        if required_bits > self.bits_per_index {
+            self.repack(required_bits.next_power_of_two());
+        }
+
+        self.palette.len() - 1
+    }
+
+    fn repack(&mut self, bits_per_index: u32) {
+        let indices: Vec<usize> = (0..CHUNK_SIZE_CUBED)
+            .map(|index| self.read_index(index))
+            .collect();
+
+        self.bits_per_index = bits_per_index;
+        self.data = vec![0; CHUNK_SIZE_CUBED.div_ceil(self.indices_per_word())];
+
+        for (index, palette_index) in indices.into_iter().enumerate() {
+            self.write_index(index, palette_index);
+        }
+    }
+
+    fn collapse_if_uniform(&mut self) {
+        let mut used_entries = self.palette.iter().filter(|entry| entry.count > 0);
+
+        if let (Some(&entry), None) = (used_entries.next(), used_entries.next()) {
+            *self = Self::filled(entry.block);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy_rapier3d::prelude::*;
+
+use super::chunk::{ChunkChanged, ChunkDirty};
+use super::collider::{
+    generate_collider_for_chunk, generate_fallback_collider_for_chunk,
+    generate_mass_properties_for_chunk,
+};
+use super::mesh::generate_chunk_mesh;
+use super::registry::BlockRegistry;
+use super::{ChunkPos, Grid, NEIGHBOR_OFFSETS};
+
+/// Everything that is generated from a chunk's blocks
+pub struct ChunkRebuild {
+    /// None if the chunk no longer exists in the grid it was rebuilt from
+    pub mesh: Option<Mesh>,
+    /// None if none of the chunk's blocks are collidable
+    pub collider: Option<(Collider, ColliderMassProperties)>,
+}
+
+/// Generates a chunk's mesh, collider and mass properties in one go. The grid only needs to contain
+/// the chunk and the chunks next to it.
+pub fn rebuild_chunk(grid: &Grid, registry: &BlockRegistry, chunk_pos: ChunkPos) -> ChunkRebuild {
+    let collider = grid.get_chunk(chunk_pos).and_then(|chunk| {
+        generate_collider_for_chunk(chunk, registry).map(|collider| {
+            (
+                collider,
+                generate_mass_properties_for_chunk(chunk, registry),
+            )
+        })
+    });
+
+    ChunkRebuild {
+        mesh: generate_chunk_mesh(grid, registry, chunk_pos),
+        collider,
+    }
+}
+
+// Copies a chunk and the chunks next to it, which is everything rebuilding the chunk needs to cull
+// its faces
+fn chunk_neighborhood(grid: &Grid, chunk_pos: ChunkPos) -> Grid {
+    let mut neighborhood = Grid::new();
+
+    for (x, y, z) in std::iter::once((0, 0, 0)).chain(NEIGHBOR_OFFSETS) {
+        let pos = ChunkPos::new(chunk_pos.x + x, chunk_pos.y + y, chunk_pos.z + z);
+        neighborhood.set_chunk(pos, grid.get_chunk(pos).cloned());
+    }
+
+    neighborhood
+}
+
+/// Rebuild running in the background for a chunk
+#[derive(Component)]
+pub struct ChunkRebuildTask(Task<ChunkRebuild>);
+
+// Chunks can be changed several times in a tick, and by chunks that are despawned later on in it, so
+// events only mark chunks that still exist
+pub fn mark_dirty_chunks(
+    mut chunk_changed_events: EventReader<ChunkChanged>,
+    mut commands: Commands,
+) {
+    for chunk_changed in chunk_changed_events.read() {
+        if let Some(mut entity_commands) = commands.get_entity(chunk_changed.0) {
+            entity_commands.try_insert(ChunkDirty);
+        }
+    }
+}
+
+// Runs before dirty chunks are rebuilt. Results for chunks that became dirty again since their
+// rebuild started are out of date, so they are dropped and the chunk waits for its next rebuild.
+pub fn apply_chunk_rebuilds(
+    mut task_query: Query<(Entity, &mut ChunkRebuildTask, Has<ChunkDirty>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    for (entity, mut task, dirty) in task_query.iter_mut() {
+        if !task.0.is_finished() {
+            continue;
+        }
+
+        let rebuild = block_on(&mut task.0);
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.remove::<ChunkRebuildTask>();
+
+        if dirty {
+            continue;
+        }
+
+        if let Some(mesh) = rebuild.mesh {
+            entity_commands
+                .insert(meshes.add(mesh))
+                // Work around mesh AABBs not being updated automatically: https://github.com/bevyengine/bevy/issues/4294
+                .remove::<Aabb>();
+        }
+
+        match rebuild.collider {
+            Some(collider) => {
+                entity_commands.insert(collider);
+            }
+            None => {
+                entity_commands.remove::<Collider>();
+            }
+        }
+    }
+}
+
+// Rebuilds run on the async compute pool so that spawning large grids and big edits don't stall the
+// fixed update. Chunks keep their current mesh and collider until the rebuild is done. Chunks that
+// don't have a collider yet get a fallback so that they take part in physics straight away.
+pub fn rebuild_dirty_chunks(
+    chunk_query: Query<(Entity, &ChunkPos, &Parent, Has<Collider>), With<ChunkDirty>>,
+    grid_query: Query<&Grid>,
+    registry: Res<BlockRegistry>,
+    mut commands: Commands,
+) {
+    if chunk_query.is_empty() {
+        return;
+    }
+
+    let registry = Arc::new(registry.clone());
+    let task_pool = AsyncComputeTaskPool::get();
+
+    for (entity, &chunk_pos, parent, has_collider) in chunk_query.iter() {
+        commands.entity(entity).remove::<ChunkDirty>();
+
+        let Ok(grid) = grid_query.get(parent.get()) else {
+            continue;
+        };
+
+        let Some(chunk) = grid.get_chunk(chunk_pos) else {
+            continue;
+        };
+
+        if !has_collider {
+            if let Some(collider) = generate_fallback_collider_for_chunk(chunk, &registry) {
+                let mass_properties = generate_mass_properties_for_chunk(chunk, &registry);
+                commands.entity(entity).insert((collider, mass_properties));
+            }
+        }
+
+        let neighborhood = chunk_neighborhood(grid, chunk_pos);
+        let registry = registry.clone();
+        let task =
+            task_pool.spawn(async move { rebuild_chunk(&neighborhood, &registry, chunk_pos) });
+
+        // Replacing a rebuild that is still running cancels it
+        commands.entity(entity).insert(ChunkRebuildTask(task));
+    }
+}
@@ -0,0 +1,321 @@
+use std::fmt;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, LoadedFolder};
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::Deserialize;
+
+use super::block::{Block, BlockId};
+use super::shape::{BlockShape, Orientation};
+
+pub const EMPTY_BLOCK_ID: &str = "empty";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BlockTextures {
+    pub albedo: String,
+    #[serde(default)]
+    pub normal: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ThrusterDefinition {
+    // Newtons, pushing the grid towards the side the block's top faces
+    pub max_thrust: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GyroscopeDefinition {
+    // Newton meters around every axis
+    pub max_torque: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GeneratorDefinition {
+    // Watts
+    pub output: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BatteryDefinition {
+    // Joules
+    pub capacity: f32,
+    // Watts the battery charges and discharges at, at most
+    pub max_power: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConsumerDefinition {
+    // Watts drawn whenever the block is connected
+    pub power: f32,
+    // Consumers with a lower priority are supplied first when there isn't enough power to go round
+    #[serde(default)]
+    pub priority: u8,
+}
+
+/// Describes a type of block. Definitions are loaded from `.block.ron` files in `assets/blocks`.
+#[derive(Asset, TypePath, Clone, Debug, Deserialize)]
+pub struct BlockDefinition {
+    // Unique name used to refer to the block in save files
+    pub id: String,
+    pub name: String,
+    // kg/m³
+    pub density: f32,
+    pub hit_points: u32,
+    #[serde(default)]
+    pub textures: Option<BlockTextures>,
+    // Multiplied with the albedo texture
+    #[serde(default = "default_tint")]
+    pub tint: [f32; 3],
+    #[serde(default)]
+    pub metallic: f32,
+    #[serde(default = "default_roughness")]
+    pub roughness: f32,
+    #[serde(default)]
+    pub transparent: bool,
+    #[serde(default = "default_collidable")]
+    pub collidable: bool,
+    #[serde(default)]
+    pub thruster: Option<ThrusterDefinition>,
+    #[serde(default)]
+    pub gyroscope: Option<GyroscopeDefinition>,
+    #[serde(default)]
+    pub generator: Option<GeneratorDefinition>,
+    #[serde(default)]
+    pub battery: Option<BatteryDefinition>,
+    #[serde(default)]
+    pub consumer: Option<ConsumerDefinition>,
+    // Links the power blocks on either side of it into one network
+    #[serde(default)]
+    pub conductor: bool,
+    // A seat the player can pilot the grid from
+    #[serde(default)]
+    pub cockpit: bool,
+}
+
+fn default_tint() -> [f32; 3] {
+    [1.0; 3]
+}
+
+fn default_roughness() -> f32 {
+    0.5
+}
+
+fn default_collidable() -> bool {
+    true
+}
+
+impl BlockDefinition {
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            density: 0.0,
+            hit_points: 0,
+            textures: None,
+            tint: default_tint(),
+            metallic: 0.0,
+            roughness: default_roughness(),
+            transparent: false,
+            collidable: default_collidable(),
+            thruster: None,
+            gyroscope: None,
+            generator: None,
+            battery: None,
+            consumer: None,
+            conductor: false,
+            cockpit: false,
+        }
+    }
+
+    /// Whether the block points somewhere, so that its orientation matters even as a cube
+    pub fn has_facing(&self) -> bool {
+        self.thruster.is_some() || self.cockpit
+    }
+
+    /// Whether the block is part of a power network
+    pub fn carries_power(&self) -> bool {
+        self.generator.is_some()
+            || self.battery.is_some()
+            || self.consumer.is_some()
+            || self.conductor
+    }
+}
+
+/// Maps block ids stored in chunks to their definitions
+#[derive(Resource, Clone)]
+pub struct BlockRegistry {
+    definitions: Vec<BlockDefinition>,
+    ids: HashMap<String, BlockId>,
+}
+
+impl BlockRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            definitions: Vec::new(),
+            ids: HashMap::new(),
+        };
+
+        registry.register(BlockDefinition {
+            transparent: true,
+            collidable: false,
+            ..BlockDefinition::new(EMPTY_BLOCK_ID, "Empty")
+        });
+
+        registry
+    }
+
+    /// Adds a definition to the registry. Redefining a block keeps its existing id so that blocks
+    /// that are already placed pick up the new definition.
+    pub fn register(&mut self, definition: BlockDefinition) -> BlockId {
+        if let Some(&id) = self.ids.get(&definition.id) {
+            self.definitions[id.0 as usize] = definition;
+            return id;
+        }
+
+        let id = BlockId(self.definitions.len() as u16);
+        self.ids.insert(definition.id.clone(), id);
+        self.definitions.push(definition);
+
+        id
+    }
+
+    pub fn get(&self, id: BlockId) -> Option<&BlockDefinition> {
+        self.definitions.get(id.0 as usize)
+    }
+
+    pub fn lookup(&self, id: &str) -> Option<BlockId> {
+        self.ids.get(id).copied()
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = (BlockId, &BlockDefinition)> {
+        self.definitions
+            .iter()
+            .enumerate()
+            .map(|(index, definition)| (BlockId(index as u16), definition))
+    }
+
+    pub fn is_transparent(&self, block: Block) -> bool {
+        self.get(block.id)
+            .is_some_and(|definition| definition.transparent)
+    }
+
+    pub fn is_collidable(&self, block: Block) -> bool {
+        self.get(block.id)
+            .is_some_and(|definition| definition.collidable)
+    }
+
+    /// Gives a block a shape and orientation. Cubes that don't face anywhere drop their orientation
+    /// like they do with `Block::with_shape`.
+    pub fn orient(&self, block: Block, shape: BlockShape, orientation: Orientation) -> Block {
+        if self
+            .get(block.id)
+            .is_some_and(|definition| definition.has_facing())
+        {
+            Block {
+                shape,
+                orientation,
+                ..block
+            }
+        } else {
+            block.with_shape(shape, orientation)
+        }
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+pub enum BlockDefinitionLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for BlockDefinitionLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Ron(error) => write!(f, "invalid block definition: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for BlockDefinitionLoaderError {}
+
+#[derive(Default)]
+pub struct BlockDefinitionLoader;
+
+impl AssetLoader for BlockDefinitionLoader {
+    type Asset = BlockDefinition;
+    type Settings = ();
+    type Error = BlockDefinitionLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(BlockDefinitionLoaderError::Io)?;
+
+            ron::de::from_bytes(&bytes).map_err(BlockDefinitionLoaderError::Ron)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["block.ron"]
+    }
+}
+
+// Keeps the block definitions loaded
+#[derive(Resource)]
+pub struct BlockDefinitionsFolder(pub Handle<LoadedFolder>);
+
+fn load_block_definitions(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(BlockDefinitionsFolder(asset_server.load_folder("blocks")));
+}
+
+fn update_block_registry(
+    mut definition_events: EventReader<AssetEvent<BlockDefinition>>,
+    definitions: Res<Assets<BlockDefinition>>,
+    mut registry: ResMut<BlockRegistry>,
+) {
+    for event in definition_events.read() {
+        let (AssetEvent::Added { id } | AssetEvent::Modified { id }) = event else {
+            continue;
+        };
+
+        let Some(definition) = definitions.get(*id) else {
+            continue;
+        };
+
+        if definition.id == EMPTY_BLOCK_ID {
+            warn!("The {EMPTY_BLOCK_ID} block cannot be redefined");
+            continue;
+        }
+
+        let block_id = registry.register(definition.clone());
+        debug!("Registered block {} as {:?}", definition.id, block_id);
+    }
+}
+
+pub struct BlockRegistryPlugin;
+
+impl Plugin for BlockRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BlockDefinition>()
+            .init_asset_loader::<BlockDefinitionLoader>()
+            .init_resource::<BlockRegistry>()
+            .add_systems(Startup, load_block_definitions)
+            .add_systems(PreUpdate, update_block_registry);
+    }
+}
@@ -0,0 +1,356 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+// Directions of the six sides of a block, starting with the top so that the first orientation
+// leaves blocks as they are
+const SIDES: [IVec3; 6] = [
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// One of the 24 rotations that keep a block lined up with its grid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Orientation(u8);
+
+impl Orientation {
+    pub const COUNT: u8 = 24;
+    pub const DEFAULT: Self = Self(0);
+
+    // The index is the side the block's top faces times four plus its quarter turns around that side
+    pub fn from_index(index: u8) -> Option<Self> {
+        (index < Self::COUNT).then_some(Self(index))
+    }
+
+    pub fn index(self) -> u8 {
+        self.0
+    }
+
+    pub fn rotation(self) -> Quat {
+        let up = SIDES[self.0 as usize / 4].as_vec3();
+        let turns = (self.0 % 4) as f32;
+
+        Quat::from_rotation_arc(Vec3::Y, up) * Quat::from_rotation_y(turns * FRAC_PI_2)
+    }
+
+    /// The orientation closest to a rotation
+    pub fn from_rotation(rotation: Quat) -> Self {
+        (0..Self::COUNT)
+            .map(Self)
+            .max_by(|a, b| {
+                let a = a.rotation().dot(rotation).abs();
+                let b = b.rotation().dot(rotation).abs();
+                a.total_cmp(&b)
+            })
+            .unwrap()
+    }
+
+    /// The orientation after a quarter turn counterclockwise around one of the grid's axes
+    pub fn rotated(self, axis: usize) -> Self {
+        let mut axis_direction = Vec3::ZERO;
+        axis_direction[axis] = 1.0;
+
+        Self::from_rotation(Quat::from_axis_angle(axis_direction, FRAC_PI_2) * self.rotation())
+    }
+
+    /// The orientation that shows the mirror image of a shape across a plane perpendicular to one
+    /// of the grid's axes
+    pub fn mirrored(self, axis: usize, shape: BlockShape) -> Self {
+        let mut reflection = Vec3::ONE;
+        reflection[axis] = -1.0;
+
+        // Reflecting twice is a rotation, so the shape's own mirror symmetry undoes the flip
+        let rotation = Mat3::from_diagonal(reflection)
+            * Mat3::from_quat(self.rotation())
+            * shape.mirror_symmetry();
+
+        Self::from_rotation(Quat::from_mat3(&rotation))
+    }
+
+    /// Turns a direction in the block's own space into the grid's space
+    pub fn rotate(self, direction: IVec3) -> IVec3 {
+        (self.rotation() * direction.as_vec3()).round().as_ivec3()
+    }
+
+    /// Turns a point in block space around the center of the block
+    pub fn rotate_point(self, point: Vec3) -> Vec3 {
+        self.rotation() * (point - 0.5) + 0.5
+    }
+
+    /// Turns a direction in the grid's space into the block's own space
+    pub fn unrotate(self, direction: IVec3) -> IVec3 {
+        (self.rotation().inverse() * direction.as_vec3())
+            .round()
+            .as_ivec3()
+    }
+}
+
+/// A flat face of a block shape
+pub struct ShapeFace {
+    // Corners in block space, where the block spans 0 to 1 on every axis. They are ordered
+    // counterclockwise when looking at the face from outside of the block.
+    pub vertices: &'static [Vec3],
+    // The side of the block the face covers completely, if any
+    pub side: Option<IVec3>,
+}
+
+impl ShapeFace {
+    pub fn normal(&self) -> Vec3 {
+        let [a, b, c, ..] = self.vertices else {
+            unreachable!()
+        };
+
+        (*b - *a).cross(*c - *a).normalize()
+    }
+}
+
+// Corners of the unit cube
+const A: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+const B: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+const C: Vec3 = Vec3::new(1.0, 0.0, 1.0);
+const D: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+const E: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+const F: Vec3 = Vec3::new(1.0, 1.0, 0.0);
+const G: Vec3 = Vec3::new(0.0, 1.0, 1.0);
+const H: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+
+// Corners of the top of a half block
+const HALF_A: Vec3 = Vec3::new(0.0, 0.5, 0.0);
+const HALF_B: Vec3 = Vec3::new(1.0, 0.5, 0.0);
+const HALF_C: Vec3 = Vec3::new(1.0, 0.5, 1.0);
+const HALF_D: Vec3 = Vec3::new(0.0, 0.5, 1.0);
+
+const CUBE_FACES: &[ShapeFace] = &[
+    ShapeFace {
+        vertices: &[A, B, C, D],
+        side: Some(IVec3::NEG_Y),
+    },
+    ShapeFace {
+        vertices: &[E, G, H, F],
+        side: Some(IVec3::Y),
+    },
+    ShapeFace {
+        vertices: &[A, E, F, B],
+        side: Some(IVec3::NEG_Z),
+    },
+    ShapeFace {
+        vertices: &[D, C, H, G],
+        side: Some(IVec3::Z),
+    },
+    ShapeFace {
+        vertices: &[A, D, G, E],
+        side: Some(IVec3::NEG_X),
+    },
+    ShapeFace {
+        vertices: &[B, F, H, C],
+        side: Some(IVec3::X),
+    },
+];
+
+// A wedge rising from the front edge of the bottom to the back edge of the top
+const SLOPE_FACES: &[ShapeFace] = &[
+    ShapeFace {
+        vertices: &[A, B, C, D],
+        side: Some(IVec3::NEG_Y),
+    },
+    ShapeFace {
+        vertices: &[A, E, F, B],
+        side: Some(IVec3::NEG_Z),
+    },
+    ShapeFace {
+        vertices: &[A, D, E],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[B, F, C],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[D, C, F, E],
+        side: None,
+    },
+];
+
+// The tip of a slope where two slopes meet on an outside corner
+const CORNER_FACES: &[ShapeFace] = &[
+    ShapeFace {
+        vertices: &[A, B, D],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[A, E, B],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[A, D, E],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[B, E, D],
+        side: None,
+    },
+];
+
+// A cube with one corner cut off, where two slopes meet on an inside corner
+const INVERTED_CORNER_FACES: &[ShapeFace] = &[
+    ShapeFace {
+        vertices: &[A, B, C, D],
+        side: Some(IVec3::NEG_Y),
+    },
+    ShapeFace {
+        vertices: &[A, E, F, B],
+        side: Some(IVec3::NEG_Z),
+    },
+    ShapeFace {
+        vertices: &[A, D, G, E],
+        side: Some(IVec3::NEG_X),
+    },
+    ShapeFace {
+        vertices: &[B, F, C],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[E, G, F],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[D, C, G],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[F, G, C],
+        side: None,
+    },
+];
+
+// The bottom half of a cube
+const HALF_BLOCK_FACES: &[ShapeFace] = &[
+    ShapeFace {
+        vertices: &[A, B, C, D],
+        side: Some(IVec3::NEG_Y),
+    },
+    ShapeFace {
+        vertices: &[HALF_A, HALF_D, HALF_C, HALF_B],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[A, HALF_A, HALF_B, B],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[D, C, HALF_C, HALF_D],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[A, D, HALF_D, HALF_A],
+        side: None,
+    },
+    ShapeFace {
+        vertices: &[B, HALF_B, HALF_C, C],
+        side: None,
+    },
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlockShape {
+    #[default]
+    Cube,
+    Slope,
+    Corner,
+    InvertedCorner,
+    HalfBlock,
+}
+
+impl BlockShape {
+    pub const ALL: [Self; 5] = [
+        Self::Cube,
+        Self::Slope,
+        Self::Corner,
+        Self::InvertedCorner,
+        Self::HalfBlock,
+    ];
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cube => "Cube",
+            Self::Slope => "Slope",
+            Self::Corner => "Corner",
+            Self::InvertedCorner => "Inverted corner",
+            Self::HalfBlock => "Half block",
+        }
+    }
+
+    pub fn faces(self) -> &'static [ShapeFace] {
+        match self {
+            Self::Cube => CUBE_FACES,
+            Self::Slope => SLOPE_FACES,
+            Self::Corner => CORNER_FACES,
+            Self::InvertedCorner => INVERTED_CORNER_FACES,
+            Self::HalfBlock => HALF_BLOCK_FACES,
+        }
+    }
+
+    /// Corners of the shape in block space, the convex hull of which is the shape
+    pub fn vertices(self) -> Vec<Vec3> {
+        let mut vertices: Vec<Vec3> = Vec::new();
+
+        for &vertex in self.faces().iter().flat_map(|face| face.vertices) {
+            if !vertices.contains(&vertex) {
+                vertices.push(vertex);
+            }
+        }
+
+        vertices
+    }
+
+    /// Returns true if the shape completely covers a side of the block, given in block space
+    pub fn covers_side(self, side: IVec3) -> bool {
+        self.faces().iter().any(|face| face.side == Some(side))
+    }
+
+    // A reflection in block space that leaves the shape as it is
+    fn mirror_symmetry(self) -> Mat3 {
+        match self {
+            // Corners are symmetric across the diagonal plane between their X and Z sides
+            Self::Corner | Self::InvertedCorner => Mat3::from_cols(Vec3::Z, Vec3::Y, Vec3::X),
+            Self::Cube | Self::Slope | Self::HalfBlock => {
+                Mat3::from_diagonal(Vec3::new(-1.0, 1.0, 1.0))
+            }
+        }
+    }
+
+    /// Fraction of the block's volume that the shape fills
+    pub fn volume(self) -> f32 {
+        match self {
+            Self::Cube => 1.0,
+            Self::Slope => 1.0 / 2.0,
+            Self::Corner => 1.0 / 6.0,
+            Self::InvertedCorner => 5.0 / 6.0,
+            Self::HalfBlock => 1.0 / 2.0,
+        }
+    }
+
+    /// Center of mass of the shape in block space
+    pub fn centroid(self) -> Vec3 {
+        match self {
+            Self::Cube => Vec3::splat(0.5),
+            Self::Slope => Vec3::new(0.5, 1.0 / 3.0, 1.0 / 3.0),
+            Self::Corner => Vec3::splat(0.25),
+            // The cube's centroid without the cut off corner's
+            Self::InvertedCorner => Vec3::splat(0.45),
+            Self::HalfBlock => Vec3::new(0.5, 0.25, 0.5),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::utils::HashMap;
+
+use super::block::BLOCK_SIZE;
+use super::chunk::{BlockPos, CHUNK_SIZE};
+use super::registry::BlockRegistry;
+use super::{Grid, GridPos, NEIGHBOR_OFFSETS};
+
+/// A block that is the only connection between a section of a grid and the rest of it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeakJoint {
+    pub pos: GridPos,
+    /// Mass of the lighter side of the joint in kg, which the joint has to carry when the grid
+    /// accelerates
+    pub section_mass: f32,
+}
+
+fn block_mass(grid: &Grid, registry: &BlockRegistry, pos: GridPos) -> f32 {
+    let block = grid.get_block(pos);
+
+    registry.get(block.id).map_or(0.0, |definition| {
+        definition.density * BLOCK_SIZE * BLOCK_SIZE * BLOCK_SIZE * block.shape.volume()
+    })
+}
+
+/// Finds the blocks whose removal would split a grid in two, which are the articulation points of
+/// the graph of blocks connected through their faces
+pub fn find_weak_joints(grid: &Grid, registry: &BlockRegistry) -> Vec<WeakJoint> {
+    let mut positions = Vec::new();
+    for (&chunk_pos, chunk) in grid.chunks() {
+        if chunk.is_empty() {
+            continue;
+        }
+
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    if !chunk.get(x, y, z).is_empty() {
+                        positions.push(GridPos {
+                            chunk_pos,
+                            block_pos: BlockPos { x, y, z },
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let indices: HashMap<GridPos, usize> = positions
+        .iter()
+        .enumerate()
+        .map(|(index, &pos)| (pos, index))
+        .collect();
+    let masses: Vec<f32> = positions
+        .iter()
+        .map(|&pos| block_mass(grid, registry, pos))
+        .collect();
+
+    // Tarjan's algorithm, with an explicit stack since grids can be far larger than the call stack
+    // allows for
+    const UNVISITED: usize = usize::MAX;
+    let mut discovered = vec![UNVISITED; positions.len()];
+    let mut low = vec![0; positions.len()];
+    // Mass and number of blocks of each block's subtree in the depth first search
+    let mut subtree_mass = vec![0.0; positions.len()];
+    let mut subtree_size = vec![0; positions.len()];
+    let mut time = 0;
+
+    let mut joints: HashMap<usize, f32> = HashMap::new();
+
+    for root in 0..positions.len() {
+        if discovered[root] != UNVISITED {
+            continue;
+        }
+
+        // Sections that would be cut off from the rest of the island by each joint
+        let mut sections = Vec::new();
+        // Block, parent and the next neighbor to look at
+        let mut stack = vec![(root, UNVISITED, 0)];
+        discovered[root] = time;
+        low[root] = time;
+        subtree_mass[root] = masses[root];
+        subtree_size[root] = 1;
+        time += 1;
+
+        while let Some(&(block, parent, neighbor)) = stack.last() {
+            if neighbor < NEIGHBOR_OFFSETS.len() {
+                stack.last_mut().unwrap().2 += 1;
+
+                let Some(&next) = indices.get(&(positions[block] + NEIGHBOR_OFFSETS[neighbor]))
+                else {
+                    continue;
+                };
+
+                if discovered[next] == UNVISITED {
+                    discovered[next] = time;
+                    low[next] = time;
+                    subtree_mass[next] = masses[next];
+                    subtree_size[next] = 1;
+                    time += 1;
+                    stack.push((next, block, 0));
+                } else if next != parent {
+                    low[block] = low[block].min(discovered[next]);
+                }
+            } else {
+                stack.pop();
+
+                if parent != UNVISITED {
+                    low[parent] = low[parent].min(low[block]);
+                    subtree_mass[parent] += subtree_mass[block];
+                    subtree_size[parent] += subtree_size[block];
+
+                    if low[block] >= discovered[parent] {
+                        sections.push((parent, subtree_mass[block], subtree_size[block]));
+                    }
+                }
+            }
+        }
+
+        let island_mass = subtree_mass[root];
+        let island_size = subtree_size[root];
+        for (joint, section_mass, section_size) in sections {
+            // The root of the search only splits the island if it has blocks on both sides
+            if section_size + 1 == island_size {
+                continue;
+            }
+
+            // The joint carries whichever side of it is lighter
+            let carried = section_mass
+                .min(island_mass - masses[joint] - section_mass)
+                .max(0.0);
+
+            let heaviest = joints.entry(joint).or_insert(0.0);
+            *heaviest = heaviest.max(carried);
+        }
+    }
+
+    joints
+        .into_iter()
+        .map(|(index, section_mass)| WeakJoint {
+            pos: positions[index],
+            section_mass,
+        })
+        .collect()
+}
@@ -0,0 +1,236 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::{
+    ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor,
+};
+
+use super::block::BlockId;
+use super::registry::BlockRegistry;
+
+/// Texture arrays can't have more layers than this on every platform
+pub const MAX_TEXTURE_LAYERS: usize = 256;
+
+// Layers of blocks without textures are filled with these
+const BLANK_ALBEDO: [u8; 4] = [255, 255, 255, 255];
+const FLAT_NORMAL: [u8; 4] = [128, 128, 255, 255];
+
+/// Chunk UVs are measured in blocks, so textures used on chunk meshes need to repeat
+pub fn tiling_sampler() -> ImageSampler {
+    ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    })
+}
+
+/// Index of the texture array layer holding a block's textures
+pub fn texture_layer(id: BlockId) -> u32 {
+    if (id.0 as usize) < MAX_TEXTURE_LAYERS {
+        id.0 as u32
+    } else {
+        0
+    }
+}
+
+#[derive(Default)]
+struct LayerSources {
+    albedo: Option<Handle<Image>>,
+    normal: Option<Handle<Image>>,
+}
+
+/// The textures of every block, stored as one layer per block id so that chunks made of different
+/// blocks can be drawn with a single material
+#[derive(Resource)]
+pub struct BlockTextureArrays {
+    pub albedo: Handle<Image>,
+    pub normal: Handle<Image>,
+    // Textures waiting to finish loading before they are copied into the arrays
+    pending_layers: Option<Vec<LayerSources>>,
+}
+
+impl FromWorld for BlockTextureArrays {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.resource_mut::<Assets<Image>>();
+
+        // Blank arrays are used until the block textures are loaded
+        Self {
+            albedo: images.add(build_texture_array(
+                &[None],
+                TextureFormat::Rgba8UnormSrgb,
+                BLANK_ALBEDO,
+            )),
+            normal: images.add(build_texture_array(
+                &[None],
+                TextureFormat::Rgba8Unorm,
+                FLAT_NORMAL,
+            )),
+            pending_layers: None,
+        }
+    }
+}
+
+fn build_texture_array(
+    layers: &[Option<&Image>],
+    format: TextureFormat,
+    fallback: [u8; 4],
+) -> Image {
+    // Every layer has to be the same size, which is taken from the first texture
+    let size = layers
+        .iter()
+        .flatten()
+        .map(|image| image.texture_descriptor.size)
+        .next()
+        .unwrap_or(Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        });
+    let layer_len = (size.width * size.height) as usize * fallback.len();
+
+    let mut data = Vec::with_capacity(layer_len * layers.len());
+    for layer in layers {
+        match layer {
+            Some(image)
+                if image.texture_descriptor.size == size
+                    && image.texture_descriptor.format == format
+                    && image.data.len() == layer_len =>
+            {
+                data.extend_from_slice(&image.data);
+            }
+            _ => {
+                if layer.is_some() {
+                    warn!(
+                        "Block textures must all be {}x{} {format:?} images",
+                        size.width, size.height
+                    );
+                }
+
+                data.extend(fallback.iter().cycle().take(layer_len));
+            }
+        }
+    }
+
+    let layer_count = layers.len() as u32;
+    let mut image = Image::new(
+        Extent3d {
+            width: size.width,
+            height: size.height * layer_count,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+    );
+    image.reinterpret_stacked_2d_as_array(layer_count);
+    image.sampler = tiling_sampler();
+
+    image
+}
+
+fn load_block_textures(
+    mut warned: Local<bool>,
+    registry: Res<BlockRegistry>,
+    asset_server: Res<AssetServer>,
+    mut texture_arrays: ResMut<BlockTextureArrays>,
+) {
+    // Blocks past the last layer are drawn with the textures of the first one
+    let block_count = registry.definitions().count();
+    if block_count > MAX_TEXTURE_LAYERS && !*warned {
+        warn!(
+            "Only the first {MAX_TEXTURE_LAYERS} of {block_count} blocks have their own textures"
+        );
+        *warned = true;
+    }
+
+    let layers = registry
+        .definitions()
+        .take(MAX_TEXTURE_LAYERS)
+        .map(|(_, definition)| match &definition.textures {
+            Some(textures) => LayerSources {
+                albedo: Some(asset_server.load(&textures.albedo)),
+                normal: textures.normal.as_ref().map(|normal| {
+                    asset_server.load_with_settings(normal, |settings: &mut ImageLoaderSettings| {
+                        settings.is_srgb = false;
+                    })
+                }),
+            },
+            None => LayerSources::default(),
+        })
+        .collect();
+
+    texture_arrays.pending_layers = Some(layers);
+}
+
+fn build_block_texture_arrays(
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut texture_arrays: ResMut<BlockTextureArrays>,
+) {
+    let Some(pending_layers) = &texture_arrays.pending_layers else {
+        return;
+    };
+
+    // Textures that failed to load are replaced with the fallback color
+    let loading = pending_layers
+        .iter()
+        .flat_map(|layer| [&layer.albedo, &layer.normal])
+        .flatten()
+        .any(|handle| {
+            matches!(
+                asset_server.load_state(handle),
+                LoadState::NotLoaded | LoadState::Loading
+            )
+        });
+    if loading {
+        return;
+    }
+
+    let pending_layers = texture_arrays.pending_layers.take().unwrap();
+
+    let get_images = |handles: Vec<Option<&Handle<Image>>>| -> Vec<Option<&Image>> {
+        handles
+            .into_iter()
+            .map(|handle| handle.and_then(|handle| images.get(handle)))
+            .collect()
+    };
+
+    let albedo = build_texture_array(
+        &get_images(
+            pending_layers
+                .iter()
+                .map(|layer| layer.albedo.as_ref())
+                .collect(),
+        ),
+        TextureFormat::Rgba8UnormSrgb,
+        BLANK_ALBEDO,
+    );
+    let normal = build_texture_array(
+        &get_images(
+            pending_layers
+                .iter()
+                .map(|layer| layer.normal.as_ref())
+                .collect(),
+        ),
+        TextureFormat::Rgba8Unorm,
+        FLAT_NORMAL,
+    );
+
+    images.insert(texture_arrays.albedo.id(), albedo);
+    images.insert(texture_arrays.normal.id(), normal);
+}
+
+pub struct BlockTexturePlugin;
+
+impl Plugin for BlockTexturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockTextureArrays>().add_systems(
+            Update,
+            (
+                load_block_textures.run_if(resource_changed::<BlockRegistry>()),
+                build_block_texture_arrays,
+            )
+                .chain(),
+        );
+    }
+}
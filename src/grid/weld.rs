@@ -0,0 +1,371 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+
+use crate::building::history::EditHistories;
+use crate::building_material::BuildingMaterialType;
+use crate::power::GridPower;
+use crate::rooms::Rooms;
+
+use super::block::{Block, BLOCK_SIZE};
+use super::chunk::{BlockPos, Chunk, ChunkBundle, ChunkChanged, CHUNK_SIZE};
+use super::collider::{grid_mass_properties, point_inertia, GridMassProperties};
+use super::registry::BlockRegistry;
+use super::shape::Orientation;
+use super::{ChunkPos, Grid, GridPos, NEIGHBOR_OFFSETS};
+
+// How far grids can be from lining up, in blocks, and still be welded
+const ALIGNMENT_TOLERANCE: f32 = 0.1;
+
+/// Maps the block coordinates of one grid onto the blocks of another grid whose blocks line up
+/// with it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatticeTransform {
+    // Where the source grid's axes point in the target grid
+    axes: [IVec3; 3],
+    offset: IVec3,
+}
+
+impl LatticeTransform {
+    /// Returns None if the grids are rotated or offset so that their blocks don't line up
+    pub fn between(target: &GlobalTransform, source: &GlobalTransform) -> Option<Self> {
+        let relative = target.affine().inverse() * source.affine();
+
+        let mut axes = [IVec3::ZERO; 3];
+        for (axis, source_axis) in axes.iter_mut().zip([Vec3::X, Vec3::Y, Vec3::Z]) {
+            let direction = relative.transform_vector3(source_axis);
+            let rounded = direction.round();
+
+            if rounded.length_squared() != 1.0 || direction.distance(rounded) > ALIGNMENT_TOLERANCE
+            {
+                return None;
+            }
+
+            *axis = rounded.as_ivec3();
+        }
+
+        // Where the center of the source grid's first block lands in the target grid
+        let origin = relative.transform_point3(Vec3::splat(BLOCK_SIZE / 2.0)) / BLOCK_SIZE
+            - Vec3::splat(0.5);
+        let rounded = origin.round();
+        if origin.distance(rounded) > ALIGNMENT_TOLERANCE {
+            return None;
+        }
+
+        Some(Self {
+            axes,
+            offset: rounded.as_ivec3(),
+        })
+    }
+
+    pub fn apply(&self, coords: IVec3) -> IVec3 {
+        self.axes[0] * coords.x + self.axes[1] * coords.y + self.axes[2] * coords.z + self.offset
+    }
+
+    /// Turns a block of the source grid to keep its orientation relative to the target grid
+    pub fn apply_to_block(&self, block: Block, registry: &BlockRegistry) -> Block {
+        let [x, y, z] = self.axes.map(|axis| axis.as_vec3());
+        let rotation = Quat::from_mat3(&Mat3::from_cols(x, y, z));
+
+        registry.orient(
+            block,
+            block.shape,
+            Orientation::from_rotation(rotation * block.orientation.rotation()),
+        )
+    }
+}
+
+/// Returns true if the block at `pos` in `grid` shares a face with a block of `other_grid`, where
+/// `lattice` maps the blocks of `grid` onto `other_grid`
+pub fn touches_grid(
+    grid: &Grid,
+    pos: GridPos,
+    other_grid: &Grid,
+    lattice: &LatticeTransform,
+) -> bool {
+    if grid.get_block(pos).is_empty() {
+        return false;
+    }
+
+    let coords = pos.to_block_coords();
+    NEIGHBOR_OFFSETS.iter().any(|&(x, y, z)| {
+        let neighbor_coords = lattice.apply(coords + IVec3::new(x as i32, y as i32, z as i32));
+        !other_grid
+            .get_block(GridPos::from_block_coords(neighbor_coords))
+            .is_empty()
+    })
+}
+
+// Mass properties of a grid in world space, with the inertia about its center of mass
+fn world_mass_properties(
+    grid: &Grid,
+    transform: &GlobalTransform,
+    registry: &BlockRegistry,
+) -> GridMassProperties {
+    let properties = grid_mass_properties(grid, registry);
+    let rotation = Mat3::from_quat(transform.compute_transform().rotation);
+
+    GridMassProperties {
+        mass: properties.mass,
+        center_of_mass: transform.transform_point(properties.center_of_mass),
+        inertia: rotation * properties.inertia * rotation.transpose(),
+    }
+}
+
+// Velocity of two grids moving as one, conserving their linear and angular momentum
+fn combined_velocity(
+    target: GridMassProperties,
+    target_velocity: Velocity,
+    source: GridMassProperties,
+    source_velocity: Velocity,
+) -> Option<Velocity> {
+    let total_mass = target.mass + source.mass;
+    if total_mass <= 0.0 {
+        return None;
+    }
+
+    let center_of_mass =
+        (target.center_of_mass * target.mass + source.center_of_mass * source.mass) / total_mass;
+    let linvel =
+        (target_velocity.linvel * target.mass + source_velocity.linvel * source.mass) / total_mass;
+
+    // Angular momentum about the combined center of mass is each grid's spin plus the orbital
+    // momentum of its center of mass
+    let mut angular_momentum = Vec3::ZERO;
+    let mut inertia = Mat3::ZERO;
+    for (properties, velocity) in [(target, target_velocity), (source, source_velocity)] {
+        let offset = properties.center_of_mass - center_of_mass;
+
+        angular_momentum +=
+            properties.inertia * velocity.angvel + offset.cross(velocity.linvel) * properties.mass;
+        inertia += properties.inertia + point_inertia(properties.mass, offset);
+    }
+
+    let angvel = if inertia.determinant() > 0.0 {
+        inertia.inverse() * angular_momentum
+    } else {
+        Vec3::ZERO
+    };
+
+    Some(Velocity { linvel, angvel })
+}
+
+/// Merges the source grid into the target grid. Nothing happens if the grids' blocks don't line up.
+pub struct WeldGrids {
+    pub target: Entity,
+    pub source: Entity,
+}
+
+impl Command for WeldGrids {
+    fn apply(self, world: &mut World) {
+        if self.target == self.source
+            || world.get::<Grid>(self.target).is_none()
+            || world.get::<Grid>(self.source).is_none()
+        {
+            return;
+        }
+
+        let (Some(&target_transform), Some(&source_transform)) = (
+            world.get::<GlobalTransform>(self.target),
+            world.get::<GlobalTransform>(self.source),
+        ) else {
+            return;
+        };
+
+        let Some(lattice) = LatticeTransform::between(&target_transform, &source_transform) else {
+            return;
+        };
+
+        let mut target_grid = world.entity_mut(self.target).take::<Grid>().unwrap();
+        let source_grid = world.entity_mut(self.source).take::<Grid>().unwrap();
+        let source_charges: HashMap<GridPos, f32> = world
+            .get::<GridPower>(self.source)
+            .map(|power| power.charges().collect())
+            .unwrap_or_default();
+
+        // Combine the momentum of both grids
+        let registry = world.resource::<BlockRegistry>();
+        let target_mass = world_mass_properties(&target_grid, &target_transform, registry);
+        let source_mass = world_mass_properties(&source_grid, &source_transform, registry);
+        let target_velocity = world
+            .get::<Velocity>(self.target)
+            .copied()
+            .unwrap_or_default();
+        let source_velocity = world
+            .get::<Velocity>(self.source)
+            .copied()
+            .unwrap_or_default();
+
+        if let Some(velocity) =
+            combined_velocity(target_mass, target_velocity, source_mass, source_velocity)
+        {
+            world.entity_mut(self.target).insert(velocity);
+        }
+
+        let material = target_grid
+            .chunks()
+            .find_map(|(_, chunk)| world.get::<Handle<BuildingMaterialType>>(chunk.entity))
+            .cloned()
+            .unwrap_or_default();
+
+        // The source's chunk entities are moved over to the target for chunks it doesn't have yet
+        let mut spare_chunks: Vec<Entity> = source_grid
+            .chunks()
+            .map(|(_, chunk)| chunk.entity)
+            .collect();
+        let mut changed_chunks: Vec<ChunkPos> = Vec::new();
+        let mut charges = Vec::new();
+
+        for (&chunk_pos, chunk) in source_grid.chunks() {
+            for z in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_SIZE {
+                    for x in 0..CHUNK_SIZE {
+                        let block = chunk.get(x, y, z);
+                        if block.is_empty() {
+                            continue;
+                        }
+
+                        let source_pos = GridPos {
+                            chunk_pos,
+                            block_pos: BlockPos { x, y, z },
+                        };
+                        let pos =
+                            GridPos::from_block_coords(lattice.apply(source_pos.to_block_coords()));
+
+                        // Blocks already in the target win if the grids overlap
+                        if !target_grid.get_block(pos).is_empty() {
+                            continue;
+                        }
+
+                        let block =
+                            lattice.apply_to_block(block, world.resource::<BlockRegistry>());
+
+                        if target_grid.get_chunk(pos.chunk_pos).is_none() {
+                            let entity = match spare_chunks.pop() {
+                                // Rapier needs to attach the chunk's collider to its new body
+                                Some(entity) => {
+                                    world
+                                        .entity_mut(entity)
+                                        .remove::<(Collider, RapierColliderHandle)>()
+                                        .insert(ChunkBundle::new(pos.chunk_pos));
+                                    entity
+                                }
+                                None => world.spawn(ChunkBundle::new(pos.chunk_pos)).id(),
+                            };
+
+                            world
+                                .entity_mut(entity)
+                                .insert(material.clone())
+                                .set_parent(self.target);
+
+                            target_grid.set_chunk(
+                                pos.chunk_pos,
+                                Some(Chunk::filled(entity, Block::EMPTY)),
+                            );
+                        }
+
+                        target_grid
+                            .get_chunk_mut(pos.chunk_pos)
+                            .unwrap()
+                            .set_by_block_pos(pos.block_pos, block);
+
+                        let damage = source_grid.damage(source_pos);
+                        if damage > 0.0 {
+                            target_grid.apply_damage(pos, damage);
+                        }
+
+                        if let Some(&charge) = source_charges.get(&source_pos) {
+                            charges.push((pos, charge));
+                        }
+
+                        if !changed_chunks.contains(&pos.chunk_pos) {
+                            changed_chunks.push(pos.chunk_pos);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Faces on chunk borders depend on the neighboring chunks, so they are remeshed as well
+        let mut dirty_chunks: Vec<Entity> = Vec::new();
+        for chunk_pos in changed_chunks {
+            let neighbors = NEIGHBOR_OFFSETS
+                .map(|(x, y, z)| ChunkPos::new(chunk_pos.x + x, chunk_pos.y + y, chunk_pos.z + z));
+
+            for pos in [chunk_pos].into_iter().chain(neighbors) {
+                if let Some(chunk) = target_grid.get_chunk(pos) {
+                    if !dirty_chunks.contains(&chunk.entity) {
+                        dirty_chunks.push(chunk.entity);
+                    }
+                }
+            }
+        }
+
+        world.entity_mut(self.target).insert(target_grid);
+        if let Some(mut power) = world.get_mut::<GridPower>(self.target) {
+            power.rebuild();
+            for (pos, charge) in charges {
+                power.set_charge(pos, charge);
+            }
+        }
+
+        // Rooms are found again in the welded grid and take the air of both grids' rooms
+        let target_air = world.get::<Rooms>(self.target).and_then(Rooms::air);
+        let source_air = world.get::<Rooms>(self.source).and_then(Rooms::air);
+        match (target_air, source_air) {
+            (Some(target_air), Some(source_air)) => {
+                let source_air = source_air.into_iter().map(|(cell, oxygen)| {
+                    let cell = GridPos::from_block_coords(lattice.apply(cell.to_block_coords()));
+                    (cell, oxygen)
+                });
+
+                world
+                    .entity_mut(self.target)
+                    .insert(Rooms::with_air(target_air.into_iter().chain(source_air)));
+            }
+            // Rooms that haven't been found yet start out full anyway
+            _ => {
+                if let Some(mut rooms) = world.get_mut::<Rooms>(self.target) {
+                    rooms.blocks_moved();
+                }
+            }
+        }
+
+        // The source's edits can still be undone on the blocks they were moved to
+        let source_history = world
+            .get_resource_mut::<EditHistories>()
+            .and_then(|mut histories| histories.0.remove(&self.source));
+        if let Some(mut history) = source_history {
+            let registry = world.resource::<BlockRegistry>();
+            let apply_to_block = |block: Block| {
+                if block.is_empty() {
+                    block
+                } else {
+                    lattice.apply_to_block(block, registry)
+                }
+            };
+
+            history.map_changes(|change| {
+                change.pos =
+                    GridPos::from_block_coords(lattice.apply(change.pos.to_block_coords()));
+                change.previous = apply_to_block(change.previous);
+                change.new = apply_to_block(change.new);
+            });
+
+            world
+                .resource_mut::<EditHistories>()
+                .0
+                .entry(self.target)
+                .or_default()
+                .merge(history);
+        }
+
+        // Any unused chunk entities are still children of the source and are despawned with it
+        world.entity_mut(self.source).despawn_recursive();
+
+        for entity in dirty_chunks {
+            world.send_event(ChunkChanged(entity));
+        }
+    }
+}
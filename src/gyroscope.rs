@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::fixed_update::FixedUpdateSet;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::Grid;
+use crate::thruster::{fire_thrusters, ThrusterControl};
+use crate::PHYSICS_TIMESTEP;
+
+/// Radians per second a grid turns at with full input
+pub const MAX_TURN_RATE: f32 = 2.0;
+
+/// The combined torque of a grid's gyroscopes, found again whenever the grid changes
+#[derive(Component)]
+pub struct Gyroscopes {
+    /// Newton meters around every axis
+    pub max_torque: f32,
+    /// Fraction of the torque the gyroscopes have power for
+    pub power: f32,
+}
+
+impl Default for Gyroscopes {
+    fn default() -> Self {
+        Self {
+            max_torque: 0.0,
+            power: 1.0,
+        }
+    }
+}
+
+pub fn find_gyroscope_torque(grid: &Grid, registry: &BlockRegistry) -> f32 {
+    let mut max_torque = 0.0;
+
+    for (_, chunk) in grid.chunks() {
+        let has_gyroscopes = chunk.palette().any(|block| {
+            registry
+                .get(block.id)
+                .is_some_and(|definition| definition.gyroscope.is_some())
+        });
+        if !has_gyroscopes {
+            continue;
+        }
+
+        max_torque += chunk
+            .blocks()
+            .filter_map(|block| registry.get(block.id)?.gyroscope.as_ref())
+            .map(|gyroscope| gyroscope.max_torque)
+            .sum::<f32>();
+    }
+
+    max_torque
+}
+
+/// The torque that brings a grid to the turn rate asked for, as far as the gyroscopes allow. Axes
+/// without input are stopped if the dampeners are on and left alone otherwise. Everything is in the
+/// grid's space.
+pub fn gyroscope_torque(
+    max_torque: f32,
+    control: &ThrusterControl,
+    angular_velocity: Vec3,
+    inertia: Mat3,
+) -> Vec3 {
+    let mut error = Vec3::ZERO;
+    for axis in 0..3 {
+        if control.angular[axis] != 0.0 {
+            error[axis] = control.angular[axis] * MAX_TURN_RATE - angular_velocity[axis];
+        } else if control.dampeners {
+            error[axis] = -angular_velocity[axis];
+        }
+    }
+
+    (inertia * error / PHYSICS_TIMESTEP).clamp(Vec3::splat(-max_torque), Vec3::splat(max_torque))
+}
+
+pub fn update_gyroscopes(
+    mut grid_query: Query<(Ref<Grid>, &mut Gyroscopes)>,
+    registry: Res<BlockRegistry>,
+) {
+    for (grid, mut gyroscopes) in grid_query.iter_mut() {
+        if grid.is_changed() || registry.is_changed() {
+            gyroscopes.max_torque = find_gyroscope_torque(&grid, &registry);
+        }
+    }
+}
+
+// Runs after the thrusters, adding to the torque they apply
+pub fn turn_gyroscopes(
+    mut grid_query: Query<(
+        &Gyroscopes,
+        &ThrusterControl,
+        &Transform,
+        &Velocity,
+        &ReadMassProperties,
+        &mut ExternalForce,
+    )>,
+) {
+    for (gyroscopes, control, transform, velocity, mass_properties, mut external_force) in
+        grid_query.iter_mut()
+    {
+        let max_torque = gyroscopes.max_torque * gyroscopes.power;
+        if max_torque <= 0.0 {
+            continue;
+        }
+
+        let mass_properties = mass_properties.get();
+        let frame = Mat3::from_quat(mass_properties.principal_inertia_local_frame);
+        let inertia =
+            frame * Mat3::from_diagonal(mass_properties.principal_inertia) * frame.transpose();
+
+        let torque = gyroscope_torque(
+            max_torque,
+            control,
+            transform.rotation.inverse() * velocity.angvel,
+            inertia,
+        );
+        external_force.torque += transform.rotation * torque;
+    }
+}
+
+pub struct GyroscopePlugin;
+
+impl Plugin for GyroscopePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (update_gyroscopes, turn_gyroscopes.after(fire_thrusters))
+                .chain()
+                .in_set(FixedUpdateSet::Update),
+        );
+    }
+}
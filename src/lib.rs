@@ -4,17 +4,24 @@ pub mod app_setup;
 pub mod building;
 pub mod building_material;
 pub mod camera;
+pub mod cockpit;
+pub mod damage;
 pub mod fixed_update;
 pub mod free_camera;
 pub mod grid;
+pub mod gyroscope;
 pub mod pause;
 pub mod player;
 pub mod player_camera;
 pub mod player_controller;
+pub mod power;
 pub mod raycast_selection;
 pub mod reticle;
+pub mod rooms;
+pub mod save;
 pub mod settings;
 pub mod skybox;
+pub mod thruster;
 
 pub const PHYSICS_TIMESTEP: f32 = 1.0 / 64.0;
 pub type UniverseGridPrecision = i32;
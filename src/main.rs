@@ -9,13 +9,15 @@ use big_space::FloatingOrigin;
 use space_game::app_setup::{
     AssetInitialization, SetupBevyPlugins, SetupDebug, SetupGame, SetupMaterials,
 };
+use space_game::building::area::AreaMarker;
 use space_game::building::BuildMarker;
 use space_game::building_material::BuildingMaterial;
 use space_game::camera::ActiveCamera;
 use space_game::fixed_update::{SetupFixedTimeStepSchedule, SetupRapier};
 use space_game::free_camera::FreeCamera;
-use space_game::grid::block::{Block, BlockMaterial, BLOCK_SIZE};
-use space_game::grid::chunk::{Chunk, CHUNK_SIZE_CUBED};
+use space_game::grid::block::{Block, BLOCK_SIZE};
+use space_game::grid::chunk::Chunk;
+use space_game::grid::registry::BlockRegistry;
 use space_game::grid::{command::SpawnGrid, ChunkPos, Grid};
 use space_game::player::SpawnPlayer;
 use space_game::raycast_selection::SelectionSource;
@@ -32,9 +34,32 @@ fn main() {
         .setup_materials()
         .setup_debug()
         .add_systems(Startup, setup_test_scene.after(AssetInitialization))
+        .add_systems(Update, spawn_test_ship)
         .run();
 }
 
+// Block definitions are loaded asynchronously, so the ship is spawned once they are available
+fn spawn_test_ship(mut spawned: Local<bool>, registry: Res<BlockRegistry>, mut commands: Commands) {
+    if *spawned {
+        return;
+    }
+
+    let Some(aluminum) = registry.lookup("aluminum") else {
+        return;
+    };
+
+    let mut cube_grid = Grid::new();
+    let chunk = Chunk::filled(Entity::PLACEHOLDER, Block::new(aluminum));
+    cube_grid.set_chunk(ChunkPos::new(0, 0, 0), Some(chunk));
+
+    commands.add(SpawnGrid::new(
+        Transform::from_xyz(50000.0, 0.0, 0.0),
+        cube_grid,
+    ));
+
+    *spawned = true;
+}
+
 fn setup_test_scene(
     mut ambient_light: ResMut<AmbientLight>,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -97,18 +122,15 @@ fn setup_test_scene(
         BuildMarker,
     ));
 
-    let mut cube_grid = Grid::new();
-    let chunk = Chunk::new(
-        Entity::PLACEHOLDER,
-        [Block {
-            material: BlockMaterial::Aluminum,
-        }; CHUNK_SIZE_CUBED],
-    );
-    cube_grid.set_chunk(ChunkPos::new(0, 0, 0), Some(chunk));
-
-    commands.add(SpawnGrid::new(
-        Transform::from_xyz(50000.0, 0.0, 0.0),
-        cube_grid,
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: BLOCK_SIZE })),
+            material: materials.add(Color::rgba(0.0, 1.0, 0.5, 0.3).into()),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        UniverseGrid::default(),
+        AreaMarker,
     ));
 
     commands.spawn((
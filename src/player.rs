@@ -4,15 +4,9 @@ use bevy::{
     prelude::*,
 };
 use bevy_rapier3d::prelude::*;
-use big_space::FloatingOrigin;
-
-use crate::fixed_update::FixedUpdateSet;
-use crate::player_controller::ActivelyControlled;
-use crate::{camera::ActiveCamera, skybox::SkyboxHandle};
-use crate::{
-    player_camera::{PlayerCamera, PlayerCameraBundle},
-    UniverseGrid,
-};
+
+use crate::{camera::TransferControl, skybox::SkyboxHandle};
+use crate::{player_camera::PlayerCameraBundle, UniverseGrid};
 
 #[derive(Component)]
 pub struct Player;
@@ -26,6 +20,7 @@ pub struct PlayerBundle {
     pub locked_axes: LockedAxes,
     pub damping: Damping,
     pub external_impulse: ExternalImpulse,
+    pub velocity: Velocity,
     pub grid_cell: UniverseGrid,
     pub transform_interpolation: TransformInterpolation,
 }
@@ -36,10 +31,31 @@ pub struct PlayerSpawned(pub Entity);
 pub struct SpawnPlayer {
     pub transform: Transform,
     pub grid_cell: UniverseGrid,
+    pub velocity: Velocity,
+    pub camera_transform: Transform,
+    // Whether the new player should become the actively controlled player
+    pub take_control: bool,
 }
 
 impl Command for SpawnPlayer {
     fn apply(self, world: &mut World) {
+        self.spawn(world);
+    }
+}
+
+impl SpawnPlayer {
+    pub fn new(transform: Transform, grid_cell: UniverseGrid) -> Self {
+        Self {
+            transform,
+            grid_cell,
+            velocity: Velocity::default(),
+            camera_transform: Transform::from_xyz(0.0, 0.95, 0.0),
+            take_control: true,
+        }
+    }
+
+    /// Spawns the player right away and returns its entity
+    pub fn spawn(self, world: &mut World) -> Entity {
         let mut system_state: SystemState<(
             ResMut<Assets<Mesh>>,
             ResMut<Assets<StandardMaterial>>,
@@ -51,6 +67,8 @@ impl Command for SpawnPlayer {
         let (mut meshes, mut materials, skybox_handle, mut commands, mut spawn_events) =
             system_state.get_mut(world);
 
+        let mut camera = Entity::PLACEHOLDER;
+
         let id = commands
             .spawn(PlayerBundle {
                 player: Player,
@@ -76,14 +94,17 @@ impl Command for SpawnPlayer {
                     angular_damping: 4.0,
                 },
                 external_impulse: ExternalImpulse::default(),
+                velocity: self.velocity,
                 grid_cell: self.grid_cell,
                 transform_interpolation: TransformInterpolation::default(),
             })
             .with_children(|parent| {
-                parent.spawn(PlayerCameraBundle::new(
-                    Transform::from_xyz(0.0, 0.95, 0.0),
-                    Skybox(skybox_handle.0.clone()),
-                ));
+                camera = parent
+                    .spawn(PlayerCameraBundle::new(
+                        self.camera_transform,
+                        Skybox(skybox_handle.0.clone()),
+                    ))
+                    .id();
                 parent.spawn(SpotLightBundle {
                     transform: Transform::from_xyz(0.0, 0.95, -0.3),
                     spot_light: SpotLight {
@@ -102,60 +123,16 @@ impl Command for SpawnPlayer {
         spawn_events.send(PlayerSpawned(id));
 
         system_state.apply(world);
-    }
-}
 
-impl SpawnPlayer {
-    pub fn new(transform: Transform, grid_cell: UniverseGrid) -> Self {
-        Self {
-            transform,
-            grid_cell,
-        }
-    }
-}
-
-fn control_newly_spawned_player(
-    mut spawned_events: EventReader<PlayerSpawned>,
-    mut commands: Commands,
-    actively_controlled_query: Query<Entity, With<ActivelyControlled>>,
-    mut camera_set: ParamSet<(
-        Query<(Entity, &mut Camera), With<ActiveCamera>>,
-        Query<&mut Camera, With<PlayerCamera>>,
-    )>,
-    children_query: Query<&Children>,
-) {
-    if let Some(event) = spawned_events.read().last() {
-        // Remove ActivelyControlled component from old entity
-        if let Ok(old_actively_controlled) = actively_controlled_query.get_single() {
-            commands
-                .entity(old_actively_controlled)
-                .remove::<(ActivelyControlled, FloatingOrigin)>();
-        }
-
-        // Remove ActiveCamera component from old camera
-        if let Ok((old_entity, mut old_camera)) = camera_set.p0().get_single_mut() {
-            commands
-                .entity(old_entity)
-                .remove::<(ActiveCamera, FloatingOrigin)>();
-            old_camera.is_active = false;
-        }
-
-        // Set new player to actively controlled
-        commands
-            .entity(event.0)
-            .insert((ActivelyControlled, FloatingOrigin));
-
-        // Set player camera to active
-        let Ok(new_player_children) = children_query.get(event.0) else {
-            return;
-        };
-        for &child in new_player_children {
-            if let Ok(mut camera) = camera_set.p1().get_mut(child) {
-                commands.entity(child).insert(ActiveCamera);
-                camera.is_active = true;
-                break;
+        if self.take_control {
+            TransferControl {
+                controlled: Some(id),
+                camera,
             }
+            .apply(world);
         }
+
+        id
     }
 }
 
@@ -163,9 +140,6 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<PlayerSpawned>().add_systems(
-            FixedUpdate,
-            control_newly_spawned_player.in_set(FixedUpdateSet::Update),
-        );
+        app.add_event::<PlayerSpawned>();
     }
 }
@@ -0,0 +1,463 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::fixed_update::FixedUpdateSet;
+use crate::grid::chunk::{BlockPos, CHUNK_SIZE};
+use crate::grid::registry::BlockRegistry;
+use crate::grid::weld::LatticeTransform;
+use crate::grid::{Grid, GridPos, NEIGHBOR_OFFSETS};
+use crate::gyroscope::{turn_gyroscopes, update_gyroscopes, Gyroscopes};
+use crate::thruster::{fire_thrusters, update_thrusters, Thrusters};
+use crate::PHYSICS_TIMESTEP;
+
+// Checking each changed block costs more than finding the whole network again once a large area
+// has been filled or cleared
+const MAX_CHANGED_BLOCKS: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Battery {
+    pub pos: GridPos,
+    /// Joules
+    pub capacity: f32,
+    /// Watts
+    pub max_power: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Consumer {
+    pub pos: GridPos,
+    /// Watts
+    pub power: f32,
+    pub priority: u8,
+}
+
+/// The power blocks of a grid, which are all connected to each other
+#[derive(Clone, Debug, Default)]
+pub struct PowerNetwork {
+    pub blocks: Vec<GridPos>,
+    /// Watts produced by the network's generators
+    pub generation: f32,
+    pub batteries: Vec<Battery>,
+    /// Sorted by priority
+    pub consumers: Vec<Consumer>,
+    /// Conductors link the network to the networks of other grids they touch
+    pub conductors: Vec<GridPos>,
+}
+
+impl PowerNetwork {
+    fn from_blocks(grid: &Grid, registry: &BlockRegistry, blocks: Vec<GridPos>) -> Self {
+        let mut network = Self {
+            blocks,
+            ..Default::default()
+        };
+
+        for &pos in &network.blocks {
+            let Some(definition) = registry.get(grid.get_block(pos).id) else {
+                continue;
+            };
+
+            if let Some(generator) = &definition.generator {
+                network.generation += generator.output;
+            }
+
+            if let Some(battery) = &definition.battery {
+                network.batteries.push(Battery {
+                    pos,
+                    capacity: battery.capacity,
+                    max_power: battery.max_power,
+                });
+            }
+
+            if let Some(consumer) = &definition.consumer {
+                network.consumers.push(Consumer {
+                    pos,
+                    power: consumer.power,
+                    priority: consumer.priority,
+                });
+            }
+
+            if definition.conductor {
+                network.conductors.push(pos);
+            }
+        }
+
+        network.consumers.sort_by_key(|consumer| consumer.priority);
+
+        network
+    }
+}
+
+fn carries_power(grid: &Grid, registry: &BlockRegistry, pos: GridPos) -> bool {
+    registry
+        .get(grid.get_block(pos).id)
+        .is_some_and(|definition| definition.carries_power())
+}
+
+/// Finds every power block of a grid
+pub fn find_network(grid: &Grid, registry: &BlockRegistry) -> PowerNetwork {
+    let mut blocks = Vec::new();
+
+    for (&chunk_pos, chunk) in grid.chunks() {
+        let has_power = chunk.palette().any(|block| {
+            registry
+                .get(block.id)
+                .is_some_and(|definition| definition.carries_power())
+        });
+        if !has_power {
+            continue;
+        }
+
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let pos = GridPos {
+                        chunk_pos,
+                        block_pos: BlockPos { x, y, z },
+                    };
+
+                    if carries_power(grid, registry, pos) {
+                        blocks.push(pos);
+                    }
+                }
+            }
+        }
+    }
+
+    PowerNetwork::from_blocks(grid, registry, blocks)
+}
+
+/// Returns true if one of the conductors of a grid shares a face with a conductor of another grid
+pub fn conductors_touch(
+    grid: &Grid,
+    transform: &GlobalTransform,
+    conductors: &[GridPos],
+    other_grid: &Grid,
+    other_transform: &GlobalTransform,
+    registry: &BlockRegistry,
+) -> bool {
+    let Some(lattice) = LatticeTransform::between(other_transform, transform) else {
+        return false;
+    };
+
+    conductors.iter().any(|&pos| {
+        let coords = pos.to_block_coords();
+        NEIGHBOR_OFFSETS.iter().any(|&(x, y, z)| {
+            let neighbor_coords = lattice.apply(coords + IVec3::new(x.into(), y.into(), z.into()));
+            let neighbor = other_grid.get_block(GridPos::from_block_coords(neighbor_coords));
+            registry
+                .get(neighbor.id)
+                .is_some_and(|definition| definition.conductor)
+        })
+    })
+}
+
+/// The power network of a grid. Blocks placed or removed through `PlaceBlockRequest` only update
+/// the network where they changed, while other changes to the grid find the whole network again.
+#[derive(Component)]
+pub struct GridPower {
+    network: PowerNetwork,
+    changed: HashSet<GridPos>,
+    rebuild: bool,
+    // Joules stored in each battery
+    charge: HashMap<GridPos, f32>,
+    // Fraction of their power that consumers got in the last tick
+    supplied: HashMap<GridPos, f32>,
+}
+
+impl Default for GridPower {
+    fn default() -> Self {
+        Self {
+            network: PowerNetwork::default(),
+            changed: HashSet::new(),
+            rebuild: true,
+            charge: HashMap::new(),
+            supplied: HashMap::new(),
+        }
+    }
+}
+
+impl GridPower {
+    /// Marks a block to be checked again in the next update
+    pub fn block_changed(&mut self, pos: GridPos) {
+        if self.rebuild {
+            return;
+        }
+
+        self.changed.insert(pos);
+        if self.changed.len() > MAX_CHANGED_BLOCKS {
+            self.rebuild();
+        }
+    }
+
+    /// Marks the whole network to be found again in the next update
+    pub fn rebuild(&mut self) {
+        self.rebuild = true;
+        self.changed.clear();
+    }
+
+    pub fn update(&mut self, grid: &Grid, registry: &BlockRegistry) {
+        if self.rebuild {
+            self.rebuild = false;
+            self.network = find_network(grid, registry);
+        } else if !self.changed.is_empty() {
+            let changed = std::mem::take(&mut self.changed);
+
+            let mut blocks = std::mem::take(&mut self.network.blocks);
+            blocks.retain(|pos| !changed.contains(pos));
+            blocks.extend(
+                changed
+                    .into_iter()
+                    .filter(|&pos| carries_power(grid, registry, pos)),
+            );
+
+            self.network = PowerNetwork::from_blocks(grid, registry, blocks);
+        } else {
+            return;
+        }
+
+        self.charge.retain(|&pos, _| {
+            registry
+                .get(grid.get_block(pos).id)
+                .is_some_and(|definition| definition.battery.is_some())
+        });
+    }
+
+    pub fn network(&self) -> &PowerNetwork {
+        &self.network
+    }
+
+    /// Joules stored in the battery at `pos`
+    pub fn charge(&self, pos: GridPos) -> f32 {
+        self.charge.get(&pos).copied().unwrap_or(0.0)
+    }
+
+    pub fn set_charge(&mut self, pos: GridPos, charge: f32) {
+        self.charge.insert(pos, charge);
+    }
+
+    /// Every battery that holds any charge, along with its charge in joules
+    pub fn charges(&self) -> impl Iterator<Item = (GridPos, f32)> + '_ {
+        self.charge.iter().map(|(&pos, &charge)| (pos, charge))
+    }
+
+    /// Removes and returns the charge of the batteries at positions for which `filter` is true
+    pub fn take_charges(&mut self, filter: impl Fn(GridPos) -> bool) -> Vec<(GridPos, f32)> {
+        let taken: Vec<(GridPos, f32)> = self.charges().filter(|&(pos, _)| filter(pos)).collect();
+        for (pos, _) in &taken {
+            self.charge.remove(pos);
+        }
+
+        taken
+    }
+
+    /// Fraction of its power that the block at `pos` got in the last tick. Blocks that don't need
+    /// power are always fully supplied.
+    pub fn supplied(&self, pos: GridPos) -> f32 {
+        self.supplied.get(&pos).copied().unwrap_or(1.0)
+    }
+
+    /// Shares out the power of the grid's network for `delta_seconds`
+    pub fn distribute(&mut self, delta_seconds: f32) {
+        share_power(&mut [self], delta_seconds);
+    }
+}
+
+/// Shares out power between grids for `delta_seconds`, as if their networks were one. Consumers
+/// are supplied in order of priority, first from the generators and then from the batteries, and
+/// consumers of the same priority share what's left evenly. Power that isn't used charges the
+/// batteries.
+pub fn share_power(grids: &mut [&mut GridPower], delta_seconds: f32) {
+    let mut generation = 0.0;
+    // Batteries and consumers along with the index of their grid
+    let mut batteries = Vec::new();
+    let mut consumers = Vec::new();
+
+    for (index, grid) in grids.iter_mut().enumerate() {
+        grid.supplied.clear();
+        generation += grid.network.generation;
+        batteries.extend(
+            grid.network
+                .batteries
+                .iter()
+                .map(|&battery| (index, battery)),
+        );
+        consumers.extend(
+            grid.network
+                .consumers
+                .iter()
+                .map(|&consumer| (index, consumer)),
+        );
+    }
+    consumers.sort_by_key(|(_, consumer)| consumer.priority);
+
+    let battery_output: Vec<f32> = batteries
+        .iter()
+        .map(|&(index, battery)| {
+            (grids[index].charge(battery.pos) / delta_seconds).clamp(0.0, battery.max_power)
+        })
+        .collect();
+    let total_battery_output: f32 = battery_output.iter().sum();
+
+    let mut supply = generation + total_battery_output;
+    let mut used = 0.0;
+
+    for group in consumers.chunk_by(|(_, a), (_, b)| a.priority == b.priority) {
+        let demand: f32 = group.iter().map(|(_, consumer)| consumer.power).sum();
+        let fraction = if demand > 0.0 {
+            (supply / demand).min(1.0)
+        } else {
+            1.0
+        };
+
+        for &(index, consumer) in group {
+            grids[index].supplied.insert(consumer.pos, fraction);
+        }
+
+        supply = (supply - demand * fraction).max(0.0);
+        used += demand * fraction;
+    }
+
+    let surplus = generation - used;
+    if surplus >= 0.0 {
+        // Batteries are charged in proportion to how much they can take
+        let room: Vec<f32> = batteries
+            .iter()
+            .map(|&(index, battery)| {
+                let charge = grids[index].charge(battery.pos);
+                ((battery.capacity - charge) / delta_seconds).clamp(0.0, battery.max_power)
+            })
+            .collect();
+        let total_room: f32 = room.iter().sum();
+        if total_room <= 0.0 {
+            return;
+        }
+
+        let ratio = (surplus / total_room).min(1.0);
+        for (&(index, battery), room) in batteries.iter().zip(room) {
+            *grids[index].charge.entry(battery.pos).or_default() += room * ratio * delta_seconds;
+        }
+    } else if total_battery_output > 0.0 {
+        // Batteries are drained in proportion to how much they can give
+        let ratio = (-surplus / total_battery_output).min(1.0);
+        for (&(index, battery), output) in batteries.iter().zip(battery_output) {
+            let charge = grids[index].charge.entry(battery.pos).or_default();
+            *charge = (*charge - output * ratio * delta_seconds).max(0.0);
+        }
+    }
+}
+
+pub fn update_grid_power(
+    mut grid_query: Query<(&Grid, &mut GridPower)>,
+    registry: Res<BlockRegistry>,
+) {
+    for (grid, mut power) in grid_query.iter_mut() {
+        if registry.is_changed() {
+            power.rebuild();
+        }
+
+        power.update(grid, &registry);
+    }
+}
+
+// The grid a linked grid shares its power through
+fn linked_root(links: &HashMap<Entity, Entity>, mut entity: Entity) -> Entity {
+    while let Some(&linked) = links.get(&entity) {
+        entity = linked;
+    }
+
+    entity
+}
+
+// Grids whose conductors touch share their power
+pub fn distribute_power(
+    mut grid_query: Query<(Entity, &Grid, &GlobalTransform, &mut GridPower)>,
+    registry: Res<BlockRegistry>,
+) {
+    let conducting: Vec<_> = grid_query
+        .iter()
+        .filter(|(_, _, _, power)| !power.network.conductors.is_empty())
+        .collect();
+
+    let mut links = HashMap::new();
+    for (index, &(entity, grid, transform, power)) in conducting.iter().enumerate() {
+        for &(other_entity, other_grid, other_transform, _) in &conducting[index + 1..] {
+            if !conductors_touch(
+                grid,
+                transform,
+                &power.network.conductors,
+                other_grid,
+                other_transform,
+                &registry,
+            ) {
+                continue;
+            }
+
+            let root = linked_root(&links, entity);
+            let other_root = linked_root(&links, other_entity);
+            if root != other_root {
+                links.insert(root.max(other_root), root.min(other_root));
+            }
+        }
+    }
+
+    let mut grids: Vec<(Entity, Mut<GridPower>)> = grid_query
+        .iter_mut()
+        .map(|(entity, _, _, power)| (linked_root(&links, entity), power))
+        .collect();
+    grids.sort_by_key(|(root, _)| *root);
+
+    for linked in grids.chunk_by_mut(|(a, _), (b, _)| a == b) {
+        let mut powers: Vec<&mut GridPower> =
+            linked.iter_mut().map(|(_, power)| &mut **power).collect();
+        share_power(&mut powers, PHYSICS_TIMESTEP);
+    }
+}
+
+// Thrusters and gyroscopes lose force in proportion to the power they are missing
+pub fn power_grid_blocks(
+    mut grid_query: Query<(&Grid, &GridPower, &mut Thrusters, &mut Gyroscopes)>,
+    registry: Res<BlockRegistry>,
+) {
+    for (grid, power, mut thrusters, mut gyroscopes) in grid_query.iter_mut() {
+        for thruster in thrusters.0.iter_mut() {
+            thruster.power = power.supplied(thruster.pos);
+        }
+
+        if gyroscopes.max_torque <= 0.0 {
+            continue;
+        }
+
+        let mut missing_torque = 0.0;
+        for (&pos, supplied) in power.supplied.iter() {
+            if let Some(gyroscope) = registry
+                .get(grid.get_block(pos).id)
+                .and_then(|definition| definition.gyroscope.as_ref())
+            {
+                missing_torque += gyroscope.max_torque * (1.0 - supplied);
+            }
+        }
+
+        gyroscopes.power = (1.0 - missing_torque / gyroscopes.max_torque).max(0.0);
+    }
+}
+
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                update_grid_power,
+                distribute_power,
+                power_grid_blocks
+                    .after(update_thrusters)
+                    .after(update_gyroscopes)
+                    .before(fire_thrusters)
+                    .before(turn_gyroscopes),
+            )
+                .chain()
+                .in_set(FixedUpdateSet::Update),
+        );
+    }
+}
@@ -0,0 +1,412 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::fixed_update::FixedUpdateSet;
+use crate::grid::block::BLOCK_SIZE;
+use crate::grid::chunk::CHUNK_SIZE;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::{Grid, GridPos, NEIGHBOR_OFFSETS};
+
+// Past this many changed blocks in one update, such as after filling or clearing an area, every
+// room is found again instead of flooding from each block
+const MAX_CHANGED_BLOCKS: usize = 4096;
+
+/// Empty cells of a grid that are sealed off from space
+#[derive(Clone, Debug, Default)]
+pub struct Room {
+    pub cells: Vec<GridPos>,
+    /// m³ of air at full pressure
+    pub oxygen: f32,
+}
+
+impl Room {
+    /// m³
+    pub fn volume(&self) -> f32 {
+        self.cells.len() as f32 * BLOCK_SIZE * BLOCK_SIZE * BLOCK_SIZE
+    }
+
+    /// From 0 in a vacuum to 1 at full pressure
+    pub fn pressure(&self) -> f32 {
+        let volume = self.volume();
+        if volume > 0.0 {
+            (self.oxygen / volume).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Sent when a room is opened to space, with one of the cells that the air escaped from
+#[derive(Event)]
+pub struct RoomVented {
+    pub grid: Entity,
+    pub pos: GridPos,
+    /// m³ of air at full pressure
+    pub oxygen: f32,
+}
+
+// Air passes through a side of a cell unless a block that collides covers the side completely, so
+// a half block or a slope in a wall lets the air out past it
+fn seals_side(grid: &Grid, registry: &BlockRegistry, pos: GridPos, direction: IVec3) -> bool {
+    let block = grid.get_block(pos);
+    registry.is_collidable(block) && block.covers_side(direction)
+}
+
+// Cells can hold air unless their block seals every side
+fn is_open(grid: &Grid, registry: &BlockRegistry, pos: GridPos) -> bool {
+    NEIGHBOR_OFFSETS
+        .iter()
+        .any(|&offset| !seals_side(grid, registry, pos, offset_direction(offset)))
+}
+
+// Air can flow between neighboring cells if neither side of the face between them is sealed
+fn is_connected(
+    grid: &Grid,
+    registry: &BlockRegistry,
+    pos: GridPos,
+    offset: (i16, i16, i16),
+) -> bool {
+    let direction = offset_direction(offset);
+    !seals_side(grid, registry, pos, direction)
+        && !seals_side(grid, registry, pos + offset, -direction)
+}
+
+fn offset_direction(offset: (i16, i16, i16)) -> IVec3 {
+    IVec3::new(offset.0 as i32, offset.1 as i32, offset.2 as i32)
+}
+
+// Block coordinates of the corners of the box around every chunk of a grid. The minimum is
+// inclusive and the maximum exclusive.
+fn grid_bounds(grid: &Grid) -> (IVec3, IVec3) {
+    let mut min = IVec3::MAX;
+    let mut max = IVec3::MIN;
+
+    for (chunk_pos, _) in grid.chunks() {
+        let chunk_coords = IVec3::new(chunk_pos.x as i32, chunk_pos.y as i32, chunk_pos.z as i32);
+        min = min.min(chunk_coords * CHUNK_SIZE as i32);
+        max = max.max((chunk_coords + 1) * CHUNK_SIZE as i32);
+    }
+
+    (min, max)
+}
+
+/// Flood fills the open cells connected to `start`. Returns `None` as soon as the fill leaves the
+/// grid's bounds or reaches a cell in `outside`, since the cells are open to space then, and adds
+/// the cells it reached to `outside`.
+pub fn find_room_cells(
+    grid: &Grid,
+    registry: &BlockRegistry,
+    start: GridPos,
+    bounds: (IVec3, IVec3),
+    outside: &mut HashSet<GridPos>,
+) -> Option<Vec<GridPos>> {
+    let (min, max) = bounds;
+    if !is_open(grid, registry, start) || outside.contains(&start) {
+        return None;
+    }
+
+    // The cells double as the queue of cells whose neighbors still need checking
+    let mut cells = vec![start];
+    let mut visited = HashSet::new();
+    visited.insert(start);
+    let mut next = 0;
+
+    while next < cells.len() {
+        let pos = cells[next];
+        next += 1;
+
+        for offset in NEIGHBOR_OFFSETS {
+            let neighbor_pos = pos + offset;
+            if !is_connected(grid, registry, pos, offset) {
+                continue;
+            }
+
+            let coords = neighbor_pos.to_block_coords();
+            if coords.cmplt(min).any() || coords.cmpge(max).any() || outside.contains(&neighbor_pos)
+            {
+                outside.extend(cells);
+                return None;
+            }
+
+            if visited.insert(neighbor_pos) {
+                cells.push(neighbor_pos);
+            }
+        }
+    }
+
+    Some(cells)
+}
+
+/// The rooms of a grid. Blocks placed or removed through `PlaceBlockRequest` only update the rooms
+/// around them, while other changes to the grid find every room again.
+#[derive(Component)]
+pub struct Rooms {
+    rooms: HashMap<u32, Room>,
+    room_ids: HashMap<GridPos, u32>,
+    next_id: u32,
+    changed: HashSet<GridPos>,
+    rebuild: bool,
+    blocks_moved: bool,
+    // Rooms found when a grid is spawned start out full, unless the grid was loaded or split off
+    // with air of its own
+    fill_new_rooms: bool,
+}
+
+impl Default for Rooms {
+    fn default() -> Self {
+        Self {
+            rooms: HashMap::new(),
+            room_ids: HashMap::new(),
+            next_id: 0,
+            changed: HashSet::new(),
+            rebuild: true,
+            blocks_moved: false,
+            fill_new_rooms: true,
+        }
+    }
+}
+
+impl Rooms {
+    /// Marks the rooms around a block to be found again in the next update
+    pub fn block_changed(&mut self, pos: GridPos) {
+        if self.rebuild {
+            return;
+        }
+
+        self.changed.insert(pos);
+        if self.changed.len() > MAX_CHANGED_BLOCKS {
+            self.rebuild();
+        }
+    }
+
+    /// Marks every room to be found again in the next update
+    pub fn rebuild(&mut self) {
+        self.rebuild = true;
+        self.changed.clear();
+    }
+
+    /// Marks every room to be found again after blocks were moved to or from another grid. Rooms
+    /// that are gone aren't vented then, since their air may well have moved with them.
+    pub fn blocks_moved(&mut self) {
+        self.rebuild();
+        self.blocks_moved = true;
+    }
+
+    /// Finds the rooms that changed again. Air spreads out evenly through the rooms that merge or
+    /// split, and whatever reaches space is lost. Returns the air lost by each room that was opened
+    /// to space, along with one of the cells it escaped from.
+    pub fn update(&mut self, grid: &Grid, registry: &BlockRegistry) -> Vec<(GridPos, f32)> {
+        let bounds = grid_bounds(grid);
+        let mut outside = HashSet::new();
+        let mut starts = Vec::new();
+        let mut dropped = Vec::new();
+        let blocks_moved = std::mem::take(&mut self.blocks_moved);
+
+        if self.rebuild {
+            self.rebuild = false;
+            self.room_ids.clear();
+            dropped.extend(self.rooms.drain().map(|(_, room)| room));
+
+            let (min, max) = bounds;
+            for z in min.z..max.z {
+                for y in min.y..max.y {
+                    for x in min.x..max.x {
+                        starts.push(GridPos::from_block_coords(IVec3::new(x, y, z)));
+                    }
+                }
+            }
+        } else if !self.changed.is_empty() {
+            // Every room touching a changed block is dropped. Whatever is left of them is still
+            // connected to one of the changed blocks or their neighbors, so it's found again from
+            // there.
+            for pos in std::mem::take(&mut self.changed) {
+                starts.push(pos);
+                starts.extend(NEIGHBOR_OFFSETS.map(|offset| pos + offset));
+            }
+
+            for pos in &starts {
+                let Some(id) = self.room_ids.get(pos).copied() else {
+                    continue;
+                };
+
+                if let Some(room) = self.rooms.remove(&id) {
+                    for cell in &room.cells {
+                        self.room_ids.remove(cell);
+                    }
+                    dropped.push(room);
+                }
+            }
+        } else {
+            return Vec::new();
+        }
+
+        // The air of the dropped rooms is spread over their cells and moves with them
+        let mut air: HashMap<GridPos, f32> = HashMap::new();
+        for room in &dropped {
+            let air_per_cell = room.oxygen / room.cells.len() as f32;
+            for &cell in &room.cells {
+                air.insert(cell, air_per_cell);
+            }
+        }
+
+        for start in starts {
+            if self.room_ids.contains_key(&start) {
+                continue;
+            }
+
+            let Some(cells) = find_room_cells(grid, registry, start, bounds, &mut outside) else {
+                continue;
+            };
+
+            let mut room = Room { cells, oxygen: 0.0 };
+            room.oxygen = if self.fill_new_rooms {
+                room.volume()
+            } else {
+                room.cells.iter().filter_map(|cell| air.get(cell)).sum()
+            };
+
+            self.add_room(room);
+        }
+        self.fill_new_rooms = false;
+
+        if blocks_moved {
+            return Vec::new();
+        }
+
+        let mut vented = Vec::new();
+        for room in &dropped {
+            let air_per_cell = room.oxygen / room.cells.len() as f32;
+            let escaped: Vec<GridPos> = room
+                .cells
+                .iter()
+                .copied()
+                .filter(|cell| !self.room_ids.contains_key(cell) && is_open(grid, registry, *cell))
+                .collect();
+
+            if !escaped.is_empty() && air_per_cell > 0.0 {
+                vented.push((escaped[0], air_per_cell * escaped.len() as f32));
+            }
+        }
+
+        vented
+    }
+
+    /// Rooms for a grid whose rooms held the given air, along with one cell of each room. Rooms
+    /// without any of the cells start out empty.
+    pub fn with_air(air: impl IntoIterator<Item = (GridPos, f32)>) -> Rooms {
+        let mut rooms = Rooms {
+            blocks_moved: true,
+            fill_new_rooms: false,
+            ..Default::default()
+        };
+
+        // The rooms are found again, and take the air of the cells in them
+        for (cell, oxygen) in air {
+            rooms.add_room(Room {
+                cells: vec![cell],
+                oxygen,
+            });
+        }
+
+        rooms
+    }
+
+    /// The air of each room along with one of its cells, or None if the rooms haven't been found
+    /// yet and will start out full
+    pub fn air(&self) -> Option<Vec<(GridPos, f32)>> {
+        if self.fill_new_rooms {
+            return None;
+        }
+
+        Some(
+            self.rooms
+                .values()
+                .filter_map(|room| Some((*room.cells.first()?, room.oxygen)))
+                .collect(),
+        )
+    }
+
+    /// Moves the rooms touching the blocks of `grid`, which was split off from this grid, into rooms
+    /// for it. Their air moves with them and is spread over the rooms they turn out to be in there.
+    pub fn split_off(&mut self, grid: &Grid) -> Rooms {
+        let mut rooms = Rooms {
+            blocks_moved: true,
+            fill_new_rooms: false,
+            ..Default::default()
+        };
+
+        let ids: Vec<u32> = self
+            .rooms
+            .iter()
+            .filter(|(_, room)| {
+                room.cells.iter().any(|&cell| {
+                    NEIGHBOR_OFFSETS
+                        .iter()
+                        .any(|&offset| !grid.get_block(cell + offset).is_empty())
+                })
+            })
+            .map(|(&id, _)| id)
+            .collect();
+
+        for id in ids {
+            let room = self.rooms.remove(&id).unwrap();
+            for cell in &room.cells {
+                self.room_ids.remove(cell);
+            }
+            rooms.add_room(room);
+        }
+
+        rooms
+    }
+
+    fn add_room(&mut self, room: Room) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        for &cell in &room.cells {
+            self.room_ids.insert(cell, id);
+        }
+        self.rooms.insert(id, room);
+    }
+
+    pub fn rooms(&self) -> impl Iterator<Item = &Room> {
+        self.rooms.values()
+    }
+
+    pub fn room_at(&self, pos: GridPos) -> Option<&Room> {
+        self.rooms.get(self.room_ids.get(&pos)?)
+    }
+
+    pub fn room_at_mut(&mut self, pos: GridPos) -> Option<&mut Room> {
+        self.rooms.get_mut(self.room_ids.get(&pos)?)
+    }
+}
+
+pub fn update_rooms(
+    mut grid_query: Query<(Entity, &Grid, &mut Rooms)>,
+    registry: Res<BlockRegistry>,
+    mut room_vented_writer: EventWriter<RoomVented>,
+) {
+    for (entity, grid, mut rooms) in grid_query.iter_mut() {
+        if registry.is_changed() {
+            rooms.rebuild();
+        }
+
+        for (pos, oxygen) in rooms.update(grid, &registry) {
+            room_vented_writer.send(RoomVented {
+                grid: entity,
+                pos,
+                oxygen,
+            });
+        }
+    }
+}
+
+pub struct RoomPlugin;
+
+impl Plugin for RoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RoomVented>()
+            .add_systems(FixedUpdate, update_rooms.in_set(FixedUpdateSet::Update));
+    }
+}
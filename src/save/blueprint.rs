@@ -0,0 +1,174 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::building::clipboard::Clipboard;
+use crate::grid::block::Block;
+use crate::grid::blueprint::Blueprint;
+use crate::grid::registry::BlockRegistry;
+
+use super::grid::SavedBlock;
+use super::{read_save_data, read_save_header, write_save, SaveError};
+
+const BLUEPRINT_MAGIC: [u8; 4] = *b"SGBP";
+pub const BLUEPRINT_FORMAT_VERSION: u16 = 1;
+
+// Like saved chunks, blocks are stored as indices into a palette
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct BlueprintData {
+    palette: Vec<SavedBlock>,
+    blocks: Vec<([i32; 3], u16)>,
+}
+
+impl BlueprintData {
+    fn new(blueprint: &Blueprint, registry: &BlockRegistry) -> Result<Self, SaveError> {
+        let mut palette: Vec<Block> = Vec::new();
+
+        let blocks = blueprint
+            .blocks
+            .iter()
+            .map(|&(offset, block)| {
+                let index = match palette.iter().position(|entry| *entry == block) {
+                    Some(index) => index,
+                    None => {
+                        palette.push(block);
+                        palette.len() - 1
+                    }
+                };
+
+                (offset.to_array(), index as u16)
+            })
+            .collect();
+
+        Ok(Self {
+            palette: palette
+                .into_iter()
+                .map(|block| SavedBlock::new(block, registry))
+                .collect::<Result<Vec<SavedBlock>, SaveError>>()?,
+            blocks,
+        })
+    }
+
+    fn into_blueprint(self, registry: &BlockRegistry) -> Result<Blueprint, SaveError> {
+        let palette = self
+            .palette
+            .into_iter()
+            .map(|block| block.into_block(registry))
+            .collect::<Result<Vec<Block>, SaveError>>()?;
+
+        let blocks = self
+            .blocks
+            .into_iter()
+            .map(|(offset, index)| {
+                palette
+                    .get(index as usize)
+                    .map(|&block| (IVec3::from_array(offset), block))
+                    .ok_or(SaveError::InvalidData("block index out of range"))
+            })
+            .collect::<Result<Vec<(IVec3, Block)>, SaveError>>()?;
+
+        Ok(Blueprint { blocks })
+    }
+}
+
+pub fn write_blueprint(
+    writer: impl Write,
+    blueprint: &Blueprint,
+    registry: &BlockRegistry,
+) -> Result<(), SaveError> {
+    write_save(
+        writer,
+        BLUEPRINT_MAGIC,
+        BLUEPRINT_FORMAT_VERSION,
+        &BlueprintData::new(blueprint, registry)?,
+    )
+}
+
+pub fn read_blueprint(reader: impl Read, registry: &BlockRegistry) -> Result<Blueprint, SaveError> {
+    let (version, data_reader) = read_save_header(reader, BLUEPRINT_MAGIC)?;
+
+    let blueprint_data: BlueprintData = match version {
+        1 => read_save_data(data_reader)?,
+        _ => return Err(SaveError::UnsupportedVersion(version)),
+    };
+
+    blueprint_data.into_blueprint(registry)
+}
+
+/// Saves the blueprint in the clipboard to a file
+pub struct SaveBlueprint {
+    pub path: PathBuf,
+}
+
+impl SaveBlueprint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Command for SaveBlueprint {
+    fn apply(self, world: &mut World) {
+        let Some(blueprint) = &world.resource::<Clipboard>().blueprint else {
+            warn!("There is no blueprint in the clipboard to save");
+            return;
+        };
+        let registry = world.resource::<BlockRegistry>();
+
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| File::create(&self.path))
+            .map_err(SaveError::from)
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                write_blueprint(&mut writer, blueprint, registry)?;
+                writer.flush()?;
+                Ok(())
+            });
+
+        match result {
+            Ok(()) => info!("Saved blueprint to {}", self.path.display()),
+            Err(error) => error!(
+                "Failed to save blueprint to {}: {error}",
+                self.path.display()
+            ),
+        }
+    }
+}
+
+/// Loads a blueprint from a file into the clipboard
+pub struct LoadBlueprint {
+    pub path: PathBuf,
+}
+
+impl LoadBlueprint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Command for LoadBlueprint {
+    fn apply(self, world: &mut World) {
+        let result = File::open(&self.path)
+            .map_err(SaveError::from)
+            .and_then(|file| {
+                read_blueprint(BufReader::new(file), world.resource::<BlockRegistry>())
+            });
+
+        match result {
+            Ok(blueprint) => {
+                info!("Loaded blueprint from {}", self.path.display());
+                world.resource_mut::<Clipboard>().blueprint = Some(blueprint);
+            }
+            Err(error) => error!(
+                "Failed to load blueprint from {}: {error}",
+                self.path.display()
+            ),
+        }
+    }
+}
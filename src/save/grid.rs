@@ -0,0 +1,281 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::grid::block::Block;
+use crate::grid::chunk::{Chunk, CHUNK_SIZE_CUBED};
+use crate::grid::command::SpawnGrid;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::shape::{BlockShape, Orientation};
+use crate::grid::{ChunkPos, Grid, GridPos};
+
+use super::{read_save_data, read_save_header, write_save, SaveError};
+
+const GRID_MAGIC: [u8; 4] = *b"SGGR";
+pub const GRID_FORMAT_VERSION: u16 = 1;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SavedTransform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl From<Transform> for SavedTransform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            scale: transform.scale.to_array(),
+        }
+    }
+}
+
+impl From<SavedTransform> for Transform {
+    fn from(transform: SavedTransform) -> Self {
+        Self {
+            translation: Vec3::from_array(transform.translation),
+            rotation: Quat::from_array(transform.rotation),
+            scale: Vec3::from_array(transform.scale),
+        }
+    }
+}
+
+// Blocks are saved as their registry ids, since numeric block ids depend on the load order
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedBlock {
+    id: String,
+    shape: u8,
+    orientation: u8,
+}
+
+impl SavedBlock {
+    pub fn new(block: Block, registry: &BlockRegistry) -> Result<Self, SaveError> {
+        let definition = registry
+            .get(block.id)
+            .ok_or_else(|| SaveError::UnknownBlock(format!("#{}", block.id.0)))?;
+
+        Ok(Self {
+            id: definition.id.clone(),
+            shape: block.shape.index(),
+            orientation: block.orientation.index(),
+        })
+    }
+
+    pub fn into_block(self, registry: &BlockRegistry) -> Result<Block, SaveError> {
+        let shape = BlockShape::from_index(self.shape)
+            .ok_or(SaveError::InvalidData("unknown block shape"))?;
+        let orientation = Orientation::from_index(self.orientation)
+            .ok_or(SaveError::InvalidData("unknown block orientation"))?;
+
+        registry
+            .lookup(&self.id)
+            .map(|id| registry.orient(Block::new(id), shape, orientation))
+            .ok_or(SaveError::UnknownBlock(self.id))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedChunk {
+    pos: [i16; 3],
+    palette: Vec<SavedBlock>,
+    // Palette index of every block in the chunk, empty if the chunk is uniform
+    indices: Vec<u16>,
+}
+
+impl SavedChunk {
+    fn new(pos: ChunkPos, chunk: &Chunk, registry: &BlockRegistry) -> Result<Self, SaveError> {
+        let palette: Vec<Block> = chunk.palette().collect();
+
+        let indices = if chunk.is_uniform() {
+            Vec::new()
+        } else {
+            chunk
+                .blocks()
+                .map(|block| palette.iter().position(|entry| *entry == block).unwrap() as u16)
+                .collect()
+        };
+
+        Ok(Self {
+            pos: [pos.x, pos.y, pos.z],
+            palette: palette
+                .into_iter()
+                .map(|block| SavedBlock::new(block, registry))
+                .collect::<Result<Vec<SavedBlock>, SaveError>>()?,
+            indices,
+        })
+    }
+
+    fn into_chunk(self, registry: &BlockRegistry) -> Result<(ChunkPos, Chunk), SaveError> {
+        let pos = ChunkPos::new(self.pos[0], self.pos[1], self.pos[2]);
+
+        let palette = self
+            .palette
+            .into_iter()
+            .map(|block| block.into_block(registry))
+            .collect::<Result<Vec<Block>, SaveError>>()?;
+
+        let Some(&first_block) = palette.first() else {
+            return Err(SaveError::InvalidData("chunk has an empty palette"));
+        };
+
+        if self.indices.is_empty() {
+            return Ok((pos, Chunk::filled(Entity::PLACEHOLDER, first_block)));
+        }
+
+        if self.indices.len() != CHUNK_SIZE_CUBED {
+            return Err(SaveError::InvalidData("chunk has the wrong size"));
+        }
+
+        let mut blocks = [first_block; CHUNK_SIZE_CUBED];
+        for (block, &index) in blocks.iter_mut().zip(self.indices.iter()) {
+            *block = *palette
+                .get(index as usize)
+                .ok_or(SaveError::InvalidData("block index out of range"))?;
+        }
+
+        Ok((pos, Chunk::new(Entity::PLACEHOLDER, blocks)))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridData {
+    transform: SavedTransform,
+    chunks: Vec<SavedChunk>,
+    // Damage taken by each damaged block
+    damage: Vec<([i32; 3], f32)>,
+}
+
+impl GridData {
+    pub fn new(
+        grid: &Grid,
+        transform: Transform,
+        registry: &BlockRegistry,
+    ) -> Result<Self, SaveError> {
+        Ok(Self {
+            transform: transform.into(),
+            chunks: grid
+                .chunks()
+                .map(|(pos, chunk)| SavedChunk::new(*pos, chunk, registry))
+                .collect::<Result<Vec<SavedChunk>, SaveError>>()?,
+            damage: grid
+                .damaged_blocks()
+                .map(|(pos, damage)| (pos.to_block_coords().to_array(), damage))
+                .collect(),
+        })
+    }
+
+    pub fn into_grid(self, registry: &BlockRegistry) -> Result<(Grid, Transform), SaveError> {
+        let mut grid = Grid::new();
+
+        for saved_chunk in self.chunks {
+            let (pos, chunk) = saved_chunk.into_chunk(registry)?;
+            grid.set_chunk(pos, Some(chunk));
+        }
+
+        for (pos, damage) in self.damage {
+            grid.apply_damage(GridPos::from_block_coords(IVec3::from_array(pos)), damage);
+        }
+
+        Ok((grid, self.transform.into()))
+    }
+}
+
+pub fn write_grid(
+    writer: impl Write,
+    grid: &Grid,
+    transform: Transform,
+    registry: &BlockRegistry,
+) -> Result<(), SaveError> {
+    write_save(
+        writer,
+        GRID_MAGIC,
+        GRID_FORMAT_VERSION,
+        &GridData::new(grid, transform, registry)?,
+    )
+}
+
+pub fn read_grid(
+    reader: impl Read,
+    registry: &BlockRegistry,
+) -> Result<(Grid, Transform), SaveError> {
+    let (version, data_reader) = read_save_header(reader, GRID_MAGIC)?;
+
+    // Older versions are migrated to the current format here as the format evolves
+    let grid_data: GridData = match version {
+        1 => read_save_data(data_reader)?,
+        _ => return Err(SaveError::UnsupportedVersion(version)),
+    };
+
+    grid_data.into_grid(registry)
+}
+
+/// Saves the grid on an entity to a file
+pub struct SaveGrid {
+    pub grid: Entity,
+    pub path: PathBuf,
+}
+
+impl SaveGrid {
+    pub fn new(grid: Entity, path: impl Into<PathBuf>) -> Self {
+        Self {
+            grid,
+            path: path.into(),
+        }
+    }
+}
+
+impl Command for SaveGrid {
+    fn apply(self, world: &mut World) {
+        let Some(entity) = world.get_entity(self.grid) else {
+            return;
+        };
+
+        let (Some(grid), Some(&transform)) = (entity.get::<Grid>(), entity.get::<Transform>())
+        else {
+            return;
+        };
+        let registry = world.resource::<BlockRegistry>();
+
+        let result = File::create(&self.path)
+            .map_err(SaveError::from)
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                write_grid(&mut writer, grid, transform, registry)?;
+                writer.flush()?;
+                Ok(())
+            });
+
+        if let Err(error) = result {
+            error!("Failed to save grid to {}: {error}", self.path.display());
+        }
+    }
+}
+
+/// Loads a grid from a file and spawns it
+pub struct LoadGrid {
+    pub path: PathBuf,
+}
+
+impl LoadGrid {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Command for LoadGrid {
+    fn apply(self, world: &mut World) {
+        let result = File::open(&self.path)
+            .map_err(SaveError::from)
+            .and_then(|file| read_grid(BufReader::new(file), world.resource::<BlockRegistry>()));
+
+        match result {
+            Ok((grid, transform)) => SpawnGrid::new(transform, grid).apply(world),
+            Err(error) => error!("Failed to load grid from {}: {error}", self.path.display()),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub mod blueprint;
+pub mod grid;
+pub mod world;
+
+use blueprint::{LoadBlueprint, SaveBlueprint};
+use world::{LoadWorld, SaveWorld};
+
+const QUICK_SAVE_PATH: &str = "saves/quicksave.world";
+const CLIPBOARD_BLUEPRINT_PATH: &str = "blueprints/clipboard.blueprint";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Encoding(bincode::Error),
+    InvalidMagic,
+    InvalidData(&'static str),
+    UnsupportedVersion(u16),
+    UnknownBlock(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Encoding(error) => write!(f, "invalid save data: {error}"),
+            Self::InvalidMagic => write!(f, "not a save file of the expected type"),
+            Self::InvalidData(reason) => write!(f, "invalid save data: {reason}"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "save format version {version} is not supported")
+            }
+            Self::UnknownBlock(id) => write!(f, "unknown block {id}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<bincode::Error> for SaveError {
+    fn from(error: bincode::Error) -> Self {
+        Self::Encoding(error)
+    }
+}
+
+/// Save files start with an uncompressed header made of a magic number and a format version,
+/// followed by the deflate compressed data. The version is readable without decompressing so
+/// that older formats can be migrated when loading.
+pub fn write_save<T: Serialize>(
+    writer: impl Write,
+    magic: [u8; 4],
+    version: u16,
+    data: &T,
+) -> Result<(), SaveError> {
+    let mut writer = writer;
+    writer.write_all(&magic)?;
+    writer.write_all(&version.to_le_bytes())?;
+
+    let mut encoder = DeflateEncoder::new(writer, Compression::default());
+    bincode::serialize_into(&mut encoder, data)?;
+    encoder.finish()?;
+
+    Ok(())
+}
+
+/// Reads a save header, returning the format version and a reader for the decompressed data
+pub fn read_save_header<R: Read>(
+    reader: R,
+    magic: [u8; 4],
+) -> Result<(u16, DeflateDecoder<R>), SaveError> {
+    let mut reader = reader;
+
+    let mut file_magic = [0; 4];
+    reader.read_exact(&mut file_magic)?;
+    if file_magic != magic {
+        return Err(SaveError::InvalidMagic);
+    }
+
+    let mut version = [0; 2];
+    reader.read_exact(&mut version)?;
+
+    Ok((u16::from_le_bytes(version), DeflateDecoder::new(reader)))
+}
+
+pub fn read_save_data<T: DeserializeOwned>(reader: impl Read) -> Result<T, SaveError> {
+    Ok(bincode::deserialize_from(reader)?)
+}
+
+fn quick_save(mut commands: Commands) {
+    commands.add(SaveWorld::new(QUICK_SAVE_PATH));
+}
+
+fn quick_load(mut commands: Commands) {
+    commands.add(LoadWorld::new(QUICK_SAVE_PATH));
+}
+
+fn save_clipboard_blueprint(mut commands: Commands) {
+    commands.add(SaveBlueprint::new(CLIPBOARD_BLUEPRINT_PATH));
+}
+
+fn load_clipboard_blueprint(mut commands: Commands) {
+    commands.add(LoadBlueprint::new(CLIPBOARD_BLUEPRINT_PATH));
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                quick_save.run_if(input_just_pressed(KeyCode::F5)),
+                quick_load.run_if(input_just_pressed(KeyCode::F8)),
+                save_clipboard_blueprint.run_if(input_just_pressed(KeyCode::F6)),
+                load_clipboard_blueprint.run_if(input_just_pressed(KeyCode::F7)),
+            ),
+        );
+    }
+}
@@ -0,0 +1,412 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+use bevy_rapier3d::render::DebugRenderContext;
+use big_space::FloatingOrigin;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::TransferControl;
+use crate::cockpit::{EnterCockpit, Piloted, Seated};
+use crate::free_camera::FreeCamera;
+use crate::grid::command::SpawnGrid;
+use crate::grid::registry::BlockRegistry;
+use crate::grid::{Grid, GridPos};
+use crate::player::{Player, SpawnPlayer};
+use crate::player_camera::PlayerCamera;
+use crate::player_controller::ActivelyControlled;
+use crate::power::GridPower;
+use crate::rooms::Rooms;
+use crate::settings::Settings;
+use crate::UniverseGrid;
+
+use super::grid::{GridData, SavedTransform};
+use super::{read_save_data, read_save_header, write_save, SaveError};
+
+const WORLD_MAGIC: [u8; 4] = *b"SGWD";
+pub const WORLD_FORMAT_VERSION: u16 = 1;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct SavedVelocity {
+    linear: [f32; 3],
+    angular: [f32; 3],
+}
+
+impl From<Velocity> for SavedVelocity {
+    fn from(velocity: Velocity) -> Self {
+        Self {
+            linear: velocity.linvel.to_array(),
+            angular: velocity.angvel.to_array(),
+        }
+    }
+}
+
+impl From<SavedVelocity> for Velocity {
+    fn from(velocity: SavedVelocity) -> Self {
+        Self {
+            linvel: Vec3::from_array(velocity.linear),
+            angvel: Vec3::from_array(velocity.angular),
+        }
+    }
+}
+
+fn save_cell(cell: UniverseGrid) -> [i32; 3] {
+    [cell.x, cell.y, cell.z]
+}
+
+fn load_cell(cell: [i32; 3]) -> UniverseGrid {
+    UniverseGrid::new(cell[0], cell[1], cell[2])
+}
+
+fn save_pos(pos: GridPos) -> [i32; 3] {
+    pos.to_block_coords().to_array()
+}
+
+fn load_pos(pos: [i32; 3]) -> GridPos {
+    GridPos::from_block_coords(IVec3::from_array(pos))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedGrid {
+    grid: GridData,
+    cell: [i32; 3],
+    velocity: SavedVelocity,
+    // Joules stored in each battery that holds any charge
+    charges: Vec<([i32; 3], f32)>,
+    // Air in each room along with one of its cells, or None if the rooms should start out full
+    air: Option<Vec<([i32; 3], f32)>>,
+}
+
+/// The cockpit a player is sitting in
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedSeat {
+    // Index of the cockpit's grid
+    grid: usize,
+    cockpit: [i32; 3],
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedPlayer {
+    transform: SavedTransform,
+    cell: [i32; 3],
+    velocity: SavedVelocity,
+    camera_transform: SavedTransform,
+    seat: Option<SavedSeat>,
+}
+
+/// A snapshot of every grid and player in the world along with the settings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldData {
+    grids: Vec<SavedGrid>,
+    players: Vec<SavedPlayer>,
+    // Index of the actively controlled player, if the player was not using the free camera
+    controlled_player: Option<usize>,
+    // Settings are stored as RON rather than bincode, so that adding a setting doesn't change the
+    // save format
+    settings: String,
+}
+
+impl WorldData {
+    pub fn capture(world: &mut World) -> Result<Self, SaveError> {
+        let mut grid_query = world.query::<(
+            Entity,
+            &Grid,
+            &Transform,
+            &UniverseGrid,
+            Option<&Velocity>,
+            Option<&GridPower>,
+            Option<&Rooms>,
+        )>();
+        let registry = world.resource::<BlockRegistry>();
+        let mut grid_indices = HashMap::new();
+        let mut grids = Vec::new();
+
+        for (entity, grid, transform, cell, velocity, power, rooms) in grid_query.iter(world) {
+            grid_indices.insert(entity, grids.len());
+            grids.push(SavedGrid {
+                grid: GridData::new(grid, *transform, registry)?,
+                cell: save_cell(*cell),
+                velocity: velocity.copied().unwrap_or_default().into(),
+                charges: power
+                    .map(|power| {
+                        power
+                            .charges()
+                            .map(|(pos, charge)| (save_pos(pos), charge))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                air: rooms.and_then(Rooms::air).map(|air| {
+                    air.into_iter()
+                        .map(|(cell, oxygen)| (save_pos(cell), oxygen))
+                        .collect()
+                }),
+            });
+        }
+
+        let mut camera_query = world.query_filtered::<&Transform, With<PlayerCamera>>();
+        let mut piloted_query = world.query::<(&Piloted, Has<ActivelyControlled>)>();
+        let mut players = Vec::new();
+        let mut controlled_player = None;
+
+        for (transform, cell, velocity, children, seated, actively_controlled) in world
+            .query_filtered::<(
+                &Transform,
+                &UniverseGrid,
+                Option<&Velocity>,
+                Option<&Children>,
+                Option<&Seated>,
+                Has<ActivelyControlled>,
+            ), With<Player>>()
+            .iter(world)
+        {
+            let camera_transform = children
+                .and_then(|children| {
+                    children
+                        .iter()
+                        .find_map(|&child| camera_query.get(world, child).ok())
+                })
+                .copied()
+                .unwrap_or_default();
+
+            // Pilots hand control to their grid, and get it back when they are seated again
+            let piloted = seated.and_then(|seated| {
+                let (piloted, grid_controlled) = piloted_query.get(world, seated.grid).ok()?;
+                let grid = *grid_indices.get(&seated.grid)?;
+                Some((
+                    SavedSeat {
+                        grid,
+                        cockpit: save_pos(piloted.cockpit),
+                    },
+                    grid_controlled,
+                ))
+            });
+
+            if actively_controlled || piloted.as_ref().is_some_and(|&(_, controlled)| controlled) {
+                controlled_player = Some(players.len());
+            }
+
+            players.push(SavedPlayer {
+                transform: (*transform).into(),
+                cell: save_cell(*cell),
+                velocity: velocity.copied().unwrap_or_default().into(),
+                camera_transform: camera_transform.into(),
+                seat: piloted.map(|(seat, _)| seat),
+            });
+        }
+
+        let settings = ron::to_string(world.resource::<Settings>()).unwrap_or_else(|error| {
+            warn!("Failed to save settings: {error}");
+            String::new()
+        });
+
+        Ok(Self {
+            grids,
+            players,
+            controlled_player,
+            settings,
+        })
+    }
+
+    /// Replaces every grid and player in the world with the ones in the snapshot
+    pub fn restore(self, world: &mut World) -> Result<(), SaveError> {
+        // Decode everything before touching the world so that a bad save leaves it intact
+        let grids = self
+            .grids
+            .into_iter()
+            .map(|saved_grid| {
+                let (grid, transform) = saved_grid
+                    .grid
+                    .into_grid(world.resource::<BlockRegistry>())?;
+                let spawn_grid = SpawnGrid {
+                    transform,
+                    grid_cell: load_cell(saved_grid.cell),
+                    velocity: saved_grid.velocity.into(),
+                    grid,
+                };
+                Ok((spawn_grid, saved_grid.charges, saved_grid.air))
+            })
+            .collect::<Result<Vec<_>, SaveError>>()?;
+
+        if self
+            .controlled_player
+            .is_some_and(|index| index >= self.players.len())
+        {
+            return Err(SaveError::InvalidData("controlled player does not exist"));
+        }
+
+        if self
+            .players
+            .iter()
+            .filter_map(|player| player.seat.as_ref())
+            .any(|seat| seat.grid >= grids.len())
+        {
+            return Err(SaveError::InvalidData(
+                "seated player's grid does not exist",
+            ));
+        }
+
+        // Settings that can't be read are left as they are rather than failing the whole load
+        let settings = match ron::from_str::<Settings>(&self.settings) {
+            Ok(settings) => Some(settings),
+            Err(error) => {
+                warn!("Failed to load settings: {error}");
+                None
+            }
+        };
+
+        let old_entities: Vec<Entity> = world
+            .query_filtered::<Entity, Or<(With<Grid>, With<Player>)>>()
+            .iter(world)
+            .collect();
+        for entity in old_entities {
+            world.entity_mut(entity).despawn_recursive();
+        }
+
+        let mut grid_entities = Vec::with_capacity(grids.len());
+        for (spawn_grid, charges, air) in grids {
+            let entity = spawn_grid.spawn(world);
+
+            let mut power = world.get_mut::<GridPower>(entity).unwrap();
+            for (pos, charge) in charges {
+                power.set_charge(load_pos(pos), charge);
+            }
+
+            if let Some(air) = air {
+                world.entity_mut(entity).insert(Rooms::with_air(
+                    air.into_iter()
+                        .map(|(cell, oxygen)| (load_pos(cell), oxygen)),
+                ));
+            }
+
+            grid_entities.push(entity);
+        }
+
+        for (index, player) in self.players.into_iter().enumerate() {
+            let entity = SpawnPlayer {
+                transform: player.transform.into(),
+                grid_cell: load_cell(player.cell),
+                velocity: player.velocity.into(),
+                camera_transform: player.camera_transform.into(),
+                take_control: self.controlled_player == Some(index),
+            }
+            .spawn(world);
+
+            if let Some(seat) = player.seat {
+                EnterCockpit {
+                    player: entity,
+                    grid: grid_entities[seat.grid],
+                    cockpit: load_pos(seat.cockpit),
+                }
+                .apply(world);
+            }
+        }
+
+        // The floating origin was on a despawned player, so fall back to the free camera
+        let has_floating_origin = world
+            .query_filtered::<(), With<FloatingOrigin>>()
+            .iter(world)
+            .next()
+            .is_some();
+        if !has_floating_origin {
+            let free_camera = world
+                .query_filtered::<Entity, With<FreeCamera>>()
+                .iter(world)
+                .next();
+
+            match free_camera {
+                Some(camera) => TransferControl {
+                    controlled: None,
+                    camera,
+                }
+                .apply(world),
+                None => warn!("No camera was available to take control after loading"),
+            }
+        }
+
+        if let Some(settings) = settings {
+            if let Some(mut debug_render_context) = world.get_resource_mut::<DebugRenderContext>() {
+                debug_render_context.enabled = settings.draw_debug;
+            }
+            world.insert_resource(settings);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn write_world(writer: impl Write, world_data: &WorldData) -> Result<(), SaveError> {
+    write_save(writer, WORLD_MAGIC, WORLD_FORMAT_VERSION, world_data)
+}
+
+pub fn read_world(reader: impl Read) -> Result<WorldData, SaveError> {
+    let (version, data_reader) = read_save_header(reader, WORLD_MAGIC)?;
+
+    // Older versions are migrated to the current format here as the format evolves
+    match version {
+        1 => read_save_data(data_reader),
+        _ => Err(SaveError::UnsupportedVersion(version)),
+    }
+}
+
+/// Saves the whole world to a file
+pub struct SaveWorld {
+    pub path: PathBuf,
+}
+
+impl SaveWorld {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Command for SaveWorld {
+    fn apply(self, world: &mut World) {
+        let result = WorldData::capture(world).and_then(|world_data| {
+            self.path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| File::create(&self.path))
+                .map_err(SaveError::from)
+                .and_then(|file| {
+                    let mut writer = BufWriter::new(file);
+                    write_world(&mut writer, &world_data)?;
+                    writer.flush()?;
+                    Ok(())
+                })
+        });
+
+        match result {
+            Ok(()) => info!("Saved world to {}", self.path.display()),
+            Err(error) => error!("Failed to save world to {}: {error}", self.path.display()),
+        }
+    }
+}
+
+/// Replaces the current world with one loaded from a file
+pub struct LoadWorld {
+    pub path: PathBuf,
+}
+
+impl LoadWorld {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Command for LoadWorld {
+    fn apply(self, world: &mut World) {
+        let result = File::open(&self.path)
+            .map_err(SaveError::from)
+            .and_then(|file| read_world(BufReader::new(file)))
+            .and_then(|world_data| world_data.restore(world));
+
+        match result {
+            Ok(()) => info!("Loaded world from {}", self.path.display()),
+            Err(error) => error!("Failed to load world from {}: {error}", self.path.display()),
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 use bevy_rapier3d::render::DebugRenderContext;
+use serde::{Deserialize, Serialize};
 
-#[derive(Resource)]
+/// Saved by name, so settings missing from a save keep their defaults
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub first_person_sensitivity: f32,
     pub free_camera_sensitivity: f32,
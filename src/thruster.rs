@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::fixed_update::FixedUpdateSet;
+use crate::grid::block::BLOCK_SIZE;
+use crate::grid::chunk::{BlockPos, CHUNK_SIZE};
+use crate::grid::registry::BlockRegistry;
+use crate::grid::{Grid, GridPos};
+use crate::PHYSICS_TIMESTEP;
+
+/// A thruster block of a grid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thruster {
+    pub pos: GridPos,
+    /// The direction the thruster pushes the grid in, in the grid's space
+    pub direction: IVec3,
+    /// Newtons
+    pub max_thrust: f32,
+    /// Fraction of its thrust the thruster has power for
+    pub power: f32,
+}
+
+/// Finds every thruster of a grid. Thrusters push towards the side their top faces.
+pub fn find_thrusters(grid: &Grid, registry: &BlockRegistry) -> Vec<Thruster> {
+    let mut thrusters = Vec::new();
+
+    for (&chunk_pos, chunk) in grid.chunks() {
+        let has_thrusters = chunk.palette().any(|block| {
+            registry
+                .get(block.id)
+                .is_some_and(|definition| definition.thruster.is_some())
+        });
+        if !has_thrusters {
+            continue;
+        }
+
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let block = chunk.get(x, y, z);
+                    let Some(thruster) = registry
+                        .get(block.id)
+                        .and_then(|definition| definition.thruster.as_ref())
+                    else {
+                        continue;
+                    };
+
+                    thrusters.push(Thruster {
+                        pos: GridPos {
+                            chunk_pos,
+                            block_pos: BlockPos { x, y, z },
+                        },
+                        direction: block.orientation.rotate(IVec3::Y),
+                        max_thrust: thruster.max_thrust,
+                        power: 1.0,
+                    });
+                }
+            }
+        }
+    }
+
+    thrusters
+}
+
+/// The thrusters of a grid, found again whenever the grid changes
+#[derive(Component, Default)]
+pub struct Thrusters(pub Vec<Thruster>);
+
+/// How a grid's thrusters and gyroscopes are fired
+#[derive(Component)]
+pub struct ThrusterControl {
+    /// The direction to accelerate in, in the grid's space. Each thruster fires with the part of
+    /// this along its direction, up to full thrust.
+    pub linear: Vec3,
+    /// How fast to turn around each of the grid's axes, from -1 to 1
+    pub angular: Vec3,
+    /// Whether the grid is slowed down to a stop along and around the axes without any input
+    pub dampeners: bool,
+}
+
+impl Default for ThrusterControl {
+    fn default() -> Self {
+        Self {
+            linear: Vec3::ZERO,
+            angular: Vec3::ZERO,
+            dampeners: true,
+        }
+    }
+}
+
+impl ThrusterControl {
+    /// Lets go of the controls, leaving the dampeners as they are
+    pub fn clear_input(&mut self) {
+        self.linear = Vec3::ZERO;
+        self.angular = Vec3::ZERO;
+    }
+
+    /// How hard to accelerate along each axis of the grid, from -1 to 1 of the thrust available in
+    /// that direction. Dampeners stop the grid along the axes without input as quickly as the
+    /// thrusters allow. `velocity` is in the grid's space and `mass` in kg.
+    pub fn linear_demand(&self, thrusters: &[Thruster], velocity: Vec3, mass: f32) -> Vec3 {
+        let mut demand = self.linear;
+        if !self.dampeners {
+            return demand;
+        }
+
+        for axis in 0..3 {
+            if self.linear[axis] != 0.0 {
+                continue;
+            }
+
+            let needed = -velocity[axis] * mass / PHYSICS_TIMESTEP;
+            let mut direction = IVec3::ZERO;
+            direction[axis] = needed.signum() as i32;
+
+            let available: f32 = thrusters
+                .iter()
+                .filter(|thruster| thruster.direction == direction)
+                .map(|thruster| thruster.max_thrust * thruster.power)
+                .sum();
+            if available > 0.0 {
+                demand[axis] = (needed / available).clamp(-1.0, 1.0);
+            }
+        }
+
+        demand
+    }
+}
+
+impl Thruster {
+    /// How hard the thruster fires for a demand from `ThrusterControl::linear_demand`
+    pub fn throttle(&self, demand: Vec3) -> f32 {
+        demand.dot(self.direction.as_vec3()).clamp(0.0, 1.0)
+    }
+}
+
+pub fn update_thrusters(
+    mut grid_query: Query<(Ref<Grid>, &mut Thrusters)>,
+    registry: Res<BlockRegistry>,
+) {
+    for (grid, mut thrusters) in grid_query.iter_mut() {
+        if grid.is_changed() || registry.is_changed() {
+            thrusters.0 = find_thrusters(&grid, &registry);
+        }
+    }
+}
+
+// Thrusters push at their own position, so thrusters that aren't lined up with the center of mass
+// turn the grid as well
+pub fn fire_thrusters(
+    mut grid_query: Query<(
+        &Thrusters,
+        &ThrusterControl,
+        &Transform,
+        &Velocity,
+        &ReadMassProperties,
+        &mut ExternalForce,
+    )>,
+) {
+    for (thrusters, control, transform, velocity, mass_properties, mut external_force) in
+        grid_query.iter_mut()
+    {
+        let mass_properties = mass_properties.get();
+        let demand = control.linear_demand(
+            &thrusters.0,
+            transform.rotation.inverse() * velocity.linvel,
+            mass_properties.mass,
+        );
+
+        let center_of_mass = mass_properties.local_center_of_mass;
+        let mut force = Vec3::ZERO;
+        let mut torque = Vec3::ZERO;
+
+        for thruster in &thrusters.0 {
+            let thrust = thruster.direction.as_vec3()
+                * thruster.max_thrust
+                * thruster.power
+                * thruster.throttle(demand);
+            let position = (thruster.pos.to_block_coords().as_vec3() + 0.5) * BLOCK_SIZE;
+
+            force += thrust;
+            torque += (position - center_of_mass).cross(thrust);
+        }
+
+        *external_force = ExternalForce {
+            force: transform.rotation * force,
+            torque: transform.rotation * torque,
+        };
+    }
+}
+
+pub struct ThrusterPlugin;
+
+impl Plugin for ThrusterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (update_thrusters, fire_thrusters)
+                .chain()
+                .in_set(FixedUpdateSet::Update),
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+use space_game::building::events::{FillAreaRequest, UndoRequest};
+use space_game::grid::block::Block;
+use space_game::grid::chunk::{Chunk, ChunkChanged};
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+// Spawns a chunk of aluminum and returns the grid and the aluminum block
+fn spawn_test_grid(app: &mut App) -> (Entity, Block) {
+    let aluminum = Block::new(
+        app.world
+            .resource_mut::<BlockRegistry>()
+            .register(BlockDefinition::new("aluminum", "Aluminum")),
+    );
+
+    let mut grid = Grid::new();
+    grid.set_chunk(
+        ChunkPos::new(0, 0, 0),
+        Some(Chunk::filled(Entity::PLACEHOLDER, aluminum)),
+    );
+
+    let grid_entity = spawn_grid(app, SpawnGrid::new(Transform::IDENTITY, grid));
+    app.fixed_update();
+
+    (grid_entity, aluminum)
+}
+
+fn count_blocks(grid: &Grid, min: IVec3, max: IVec3, block: Block) -> usize {
+    let mut count = 0;
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                if grid.get_block(pos(x, y, z)) == block {
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    count
+}
+
+#[derive(Resource, Default)]
+struct ChangedChunks(Vec<Entity>);
+
+fn collect_changed_chunks(
+    mut chunk_changed_events: EventReader<ChunkChanged>,
+    mut changed_chunks: ResMut<ChangedChunks>,
+) {
+    changed_chunks
+        .0
+        .extend(chunk_changed_events.read().map(|event| event.0));
+}
+
+#[test]
+fn fill_creates_chunks_and_changes_each_once() {
+    let mut app = App::game_test();
+    let (grid_entity, aluminum) = spawn_test_grid(&mut app);
+
+    app.init_resource::<ChangedChunks>()
+        .add_systems(Last, collect_changed_chunks);
+
+    // Extends from inside the existing chunk into two new chunks, corners given in reverse
+    app.world.send_event(FillAreaRequest {
+        grid: grid_entity,
+        start: pos(20, 2, 3),
+        end: pos(14, 0, 0),
+        block: aluminum,
+    });
+    app.fixed_update();
+
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    assert_eq!(
+        7 * 3 * 4,
+        count_blocks(grid, IVec3::new(14, 0, 0), IVec3::new(20, 2, 3), aluminum)
+    );
+    assert_eq!(2, grid.chunks().count());
+
+    let mut changed_chunks = app.world.resource::<ChangedChunks>().0.clone();
+    let change_count = changed_chunks.len();
+    changed_chunks.sort();
+    changed_chunks.dedup();
+    assert_eq!(change_count, changed_chunks.len());
+    assert_eq!(2, change_count);
+}
+
+#[test]
+fn clearing_an_area_is_undone_in_one_step() {
+    let mut app = App::game_test();
+    let (grid_entity, aluminum) = spawn_test_grid(&mut app);
+
+    app.world.send_event(FillAreaRequest {
+        grid: grid_entity,
+        start: pos(0, 0, 0),
+        end: pos(15, 15, 7),
+        block: Block::EMPTY,
+    });
+    app.fixed_update();
+
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    assert_eq!(
+        16 * 16 * 8,
+        count_blocks(grid, IVec3::ZERO, IVec3::new(15, 15, 7), Block::EMPTY)
+    );
+
+    app.world.send_event(UndoRequest { grid: grid_entity });
+    app.fixed_update();
+
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    assert_eq!(
+        16 * 16 * 16,
+        count_blocks(grid, IVec3::ZERO, IVec3::splat(15), aluminum)
+    );
+}
+
+#[test]
+fn refilling_a_cleared_chunk_keeps_it() {
+    let mut app = App::game_test();
+    let (grid_entity, aluminum) = spawn_test_grid(&mut app);
+
+    // Clearing the whole chunk empties it before the fill in the same update puts blocks back
+    app.world.send_event(FillAreaRequest {
+        grid: grid_entity,
+        start: pos(0, 0, 0),
+        end: pos(15, 15, 15),
+        block: Block::EMPTY,
+    });
+    app.world.send_event(FillAreaRequest {
+        grid: grid_entity,
+        start: pos(0, 0, 0),
+        end: pos(3, 3, 3),
+        block: aluminum,
+    });
+    app.fixed_update();
+
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    assert_eq!(1, grid.chunks().count());
+    assert_eq!(
+        4 * 4 * 4,
+        count_blocks(grid, IVec3::ZERO, IVec3::splat(15), aluminum)
+    );
+}
@@ -0,0 +1,53 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use space_game::grid::block::BLOCK_SIZE;
+use space_game::grid::chunk::CHUNK_SIZE;
+use space_game::grid::{block_at_surface, ChunkPos, GridPos};
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+const CHUNK_WIDTH: f32 = CHUNK_SIZE as f32 * BLOCK_SIZE;
+
+#[test]
+fn hit_face_belongs_to_the_block_behind_it() {
+    let chunk_pos = ChunkPos::new(1, 0, 0);
+    let chunk_transform = GlobalTransform::from_translation(Vec3::new(CHUNK_WIDTH, 0.0, 0.0));
+
+    // The top face of the block at (17, 2, 3), hit slightly off its center
+    let point = Vec3::new(17.3, 3.0, 3.6) * BLOCK_SIZE;
+    let (block, face) = block_at_surface(&chunk_transform, chunk_pos, point, Vec3::Y);
+
+    assert_eq!(pos(17, 2, 3), block);
+    assert_eq!(IVec3::Y, face);
+}
+
+#[test]
+fn normals_are_turned_into_the_grid() {
+    let chunk_pos = ChunkPos::new(0, 0, 0);
+    // Turned so that the chunk's +X points along the world's -Z
+    let chunk_transform =
+        GlobalTransform::from(Transform::from_rotation(Quat::from_rotation_y(FRAC_PI_2)));
+
+    let local_point = Vec3::new(5.0, 1.5, 2.5) * BLOCK_SIZE;
+    let point = chunk_transform.transform_point(local_point);
+    let (block, face) = block_at_surface(&chunk_transform, chunk_pos, point, Vec3::NEG_Z);
+
+    assert_eq!(pos(4, 1, 2), block);
+    assert_eq!(IVec3::X, face);
+}
+
+#[test]
+fn faces_on_chunk_borders_can_belong_to_the_next_chunk() {
+    let chunk_pos = ChunkPos::new(0, 0, 0);
+
+    // The bottom face of the chunk is the top face of the chunk below it
+    let point = Vec3::new(1.5, 0.0, 1.5) * BLOCK_SIZE;
+    let (block, face) = block_at_surface(&GlobalTransform::IDENTITY, chunk_pos, point, Vec3::Y);
+
+    assert_eq!(pos(1, -1, 1), block);
+    assert_eq!(IVec3::Y, face);
+}
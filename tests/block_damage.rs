@@ -0,0 +1,150 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use space_game::damage::DamageBlockRequest;
+use space_game::grid::block::{Block, BLOCK_SIZE};
+use space_game::grid::chunk::{Chunk, CHUNK_SIZE};
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+fn register_aluminum(registry: &mut BlockRegistry) -> Block {
+    Block::new(registry.register(BlockDefinition {
+        hit_points: 100,
+        ..BlockDefinition::new("aluminum", "Aluminum")
+    }))
+}
+
+fn test_grid(block: Block) -> Grid {
+    let mut grid = Grid::new();
+    grid.set_chunk(
+        ChunkPos::new(0, 0, 0),
+        Some(Chunk::filled(Entity::PLACEHOLDER, block)),
+    );
+
+    grid
+}
+
+#[test]
+fn damage_adds_up() {
+    let aluminum = register_aluminum(&mut BlockRegistry::new());
+    let mut grid = test_grid(aluminum);
+
+    assert_eq!(30.0, grid.apply_damage(pos(1, 2, 3), 30.0));
+    assert_eq!(50.0, grid.apply_damage(pos(1, 2, 3), 20.0));
+    assert_eq!(0.0, grid.damage(pos(3, 2, 1)));
+
+    // Empty space can't be damaged
+    assert_eq!(0.0, grid.apply_damage(pos(-1, 0, 0), 10.0));
+
+    grid.repair(pos(1, 2, 3));
+    assert_eq!(0.0, grid.damage(pos(1, 2, 3)));
+}
+
+#[test]
+fn blocks_out_of_hit_points_are_removed() {
+    let mut app = App::game_test();
+    let aluminum = register_aluminum(&mut app.world.resource_mut::<BlockRegistry>());
+    let grid_entity = spawn_grid(
+        &mut app,
+        SpawnGrid::new(Transform::IDENTITY, test_grid(aluminum)),
+    );
+    app.fixed_update();
+
+    app.world.send_event_batch([
+        DamageBlockRequest {
+            grid: grid_entity,
+            pos: pos(1, 2, 3),
+            amount: 60.0,
+        },
+        DamageBlockRequest {
+            grid: grid_entity,
+            pos: pos(1, 2, 3),
+            amount: 60.0,
+        },
+        DamageBlockRequest {
+            grid: grid_entity,
+            pos: pos(3, 2, 1),
+            amount: 60.0,
+        },
+    ]);
+
+    // Destroyed blocks are removed the tick after they are damaged
+    app.fixed_update();
+    app.fixed_update();
+
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    assert!(grid.get_block(pos(1, 2, 3)).is_empty());
+    assert_eq!(0.0, grid.damage(pos(1, 2, 3)));
+
+    assert_eq!(aluminum, grid.get_block(pos(3, 2, 1)));
+    assert_eq!(60.0, grid.damage(pos(3, 2, 1)));
+}
+
+#[test]
+fn ramming_damages_both_grids() {
+    let mut app = App::game_test();
+    let aluminum = register_aluminum(&mut app.world.resource_mut::<BlockRegistry>());
+    let target = spawn_grid(
+        &mut app,
+        SpawnGrid::new(Transform::IDENTITY, test_grid(aluminum)),
+    );
+    app.fixed_update();
+
+    let chunk_width = CHUNK_SIZE as f32 * BLOCK_SIZE;
+    let rammer = spawn_grid(
+        &mut app,
+        SpawnGrid {
+            velocity: Velocity::linear(Vec3::NEG_X * 20.0),
+            ..SpawnGrid::new(
+                Transform::from_xyz(chunk_width + 1.0, 0.0, 0.0),
+                test_grid(aluminum),
+            )
+        },
+    );
+
+    // Whether any block on a face of a grid was hit
+    let face_damaged = |grid: &Grid, x: i32| {
+        (0..CHUNK_SIZE as i32).any(|z| {
+            (0..CHUNK_SIZE as i32)
+                .any(|y| grid.damage(pos(x, y, z)) > 0.0 || grid.get_block(pos(x, y, z)).is_empty())
+        })
+    };
+    let last = CHUNK_SIZE as i32 - 1;
+
+    let mut damaged = false;
+    for _ in 0..128 {
+        app.fixed_update();
+
+        let target_grid = app.world.get::<Grid>(target).unwrap();
+        let rammer_grid = app.world.get::<Grid>(rammer).unwrap();
+        if face_damaged(target_grid, last) && face_damaged(rammer_grid, 0) {
+            damaged = true;
+            break;
+        }
+    }
+
+    assert!(damaged);
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use space_game::grid::block::{Block, BlockId};
+use space_game::grid::shape::{BlockShape, Orientation};
+
+const ALUMINUM: Block = Block::new(BlockId(1));
+
+fn all_orientations() -> impl Iterator<Item = Orientation> {
+    (0..Orientation::COUNT).map(|index| Orientation::from_index(index).unwrap())
+}
+
+#[test]
+fn orientations_are_distinct_rotations() {
+    let orientations: Vec<Orientation> = all_orientations().collect();
+
+    for (index, orientation) in orientations.iter().enumerate() {
+        // Every orientation points the block's axes somewhere different
+        let axes = [IVec3::X, IVec3::Y].map(|axis| orientation.rotate(axis));
+        for other in &orientations[index + 1..] {
+            assert_ne!(axes, [IVec3::X, IVec3::Y].map(|axis| other.rotate(axis)));
+        }
+
+        assert_eq!(
+            Orientation::from_rotation(orientation.rotation()),
+            *orientation
+        );
+    }
+
+    assert_eq!(Orientation::from_index(Orientation::COUNT), None);
+}
+
+#[test]
+fn four_quarter_turns_return_to_the_start() {
+    for orientation in all_orientations() {
+        for axis in 0..3 {
+            let mut rotated = orientation;
+            for _ in 0..4 {
+                rotated = rotated.rotated(axis);
+            }
+
+            assert_eq!(rotated, orientation);
+        }
+    }
+}
+
+#[test]
+fn quarter_turn_around_y_turns_x_towards_negative_z() {
+    let orientation = Orientation::default().rotated(1);
+
+    assert_eq!(orientation.rotate(IVec3::X), IVec3::NEG_Z);
+    assert_eq!(orientation.unrotate(IVec3::NEG_Z), IVec3::X);
+}
+
+#[test]
+fn covered_sides_follow_the_orientation() {
+    let slope = ALUMINUM.with_shape(BlockShape::Slope, Orientation::default());
+    assert!(slope.covers_side(IVec3::NEG_Y));
+    assert!(slope.covers_side(IVec3::NEG_Z));
+    assert!(!slope.covers_side(IVec3::Y));
+    assert!(!slope.covers_side(IVec3::X));
+
+    // Turned around X, the full back of the slope faces up and the bottom faces backwards
+    let turned = ALUMINUM.with_shape(BlockShape::Slope, Orientation::default().rotated(0));
+    assert!(turned.covers_side(IVec3::Y));
+    assert!(turned.covers_side(IVec3::NEG_Z));
+    assert!(!turned.covers_side(IVec3::NEG_Y));
+
+    assert!(ALUMINUM.covers_side(IVec3::X));
+    assert!(!Block::EMPTY.covers_side(IVec3::X));
+}
+
+#[test]
+fn cubes_ignore_orientation() {
+    let cube = ALUMINUM.with_shape(BlockShape::Cube, Orientation::default().rotated(2));
+
+    assert_eq!(cube, ALUMINUM);
+}
+
+#[test]
+fn shape_faces_point_outwards() {
+    for shape in BlockShape::ALL {
+        for face in shape.faces() {
+            let face_center =
+                face.vertices.iter().copied().sum::<Vec3>() / face.vertices.len() as f32;
+
+            assert!(
+                face.normal().dot(face_center - shape.centroid()) > 0.0,
+                "{} has an inward facing face",
+                shape.name()
+            );
+
+            if let Some(side) = face.side {
+                assert_eq!(face.normal(), side.as_vec3());
+            }
+        }
+    }
+}
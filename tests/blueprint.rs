@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+
+use space_game::grid::block::Block;
+use space_game::grid::blueprint::Blueprint;
+use space_game::grid::chunk::Chunk;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+use space_game::save::blueprint::{read_blueprint, write_blueprint};
+use space_game::save::SaveError;
+
+use crate::scaffolding::test_registry;
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Sets a block, adding an empty chunk for it if needed
+fn set_block(grid: &mut Grid, coords: IVec3, block: Block) {
+    let pos = GridPos::from_block_coords(coords);
+    if grid.get_chunk(pos.chunk_pos).is_none() {
+        grid.set_chunk(
+            pos.chunk_pos,
+            Some(Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY)),
+        );
+    }
+
+    grid.get_chunk_mut(pos.chunk_pos)
+        .unwrap()
+        .set_by_block_pos(pos.block_pos, block);
+}
+
+fn grid_with(blocks: &[(IVec3, Block)]) -> Grid {
+    let mut grid = Grid::new();
+    for &(coords, block) in blocks {
+        set_block(&mut grid, coords, block);
+    }
+
+    grid
+}
+
+// Returns the definitions of the aluminum and steel blocks
+fn block_definitions() -> [BlockDefinition; 2] {
+    [
+        BlockDefinition::new("aluminum", "Aluminum"),
+        BlockDefinition::new("steel", "Steel"),
+    ]
+}
+
+#[test]
+fn copying_stores_blocks_relative_to_the_anchor() {
+    let (_, [aluminum, steel]) = test_registry(block_definitions());
+    let grid = grid_with(&[
+        (IVec3::new(-1, 0, 0), aluminum),
+        (IVec3::new(0, 0, 0), steel),
+        (IVec3::new(0, 2, 1), aluminum),
+        // Outside of the copied area
+        (IVec3::new(5, 0, 0), steel),
+    ]);
+
+    let blueprint = Blueprint::copy(&grid, pos(-1, 0, 0), pos(0, 2, 1), pos(0, 0, 0));
+
+    assert_eq!(blueprint.blocks.len(), 3);
+    assert!(blueprint.blocks.contains(&(IVec3::new(-1, 0, 0), aluminum)));
+    assert!(blueprint.blocks.contains(&(IVec3::ZERO, steel)));
+    assert!(blueprint.blocks.contains(&(IVec3::new(0, 2, 1), aluminum)));
+}
+
+#[test]
+fn rotating_turns_blocks_around_the_anchor() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let mut blueprint = Blueprint {
+        blocks: vec![(IVec3::new(1, 2, 3), aluminum)],
+    };
+
+    blueprint.rotate(1, &registry);
+    assert_eq!(blueprint.blocks[0].0, IVec3::new(3, 2, -1));
+
+    // Four quarter turns around any axis end where they started
+    for axis in 0..3 {
+        let original = blueprint.clone();
+        for _ in 0..4 {
+            blueprint.rotate(axis, &registry);
+        }
+        assert_eq!(blueprint, original);
+    }
+}
+
+#[test]
+fn pasting_places_blocks_at_the_anchor() {
+    let (_, [aluminum, steel]) = test_registry(block_definitions());
+    let blueprint = Blueprint {
+        blocks: vec![(IVec3::ZERO, aluminum), (IVec3::new(0, -1, 0), steel)],
+    };
+
+    let placements: Vec<_> = blueprint.placements(pos(16, 0, 3)).collect();
+
+    assert_eq!(
+        placements,
+        vec![(pos(16, 0, 3), aluminum), (pos(16, -1, 3), steel)]
+    );
+    assert_eq!(placements[1].0.chunk_pos, ChunkPos::new(1, -1, 0));
+}
+
+#[test]
+fn blueprints_become_grids() {
+    let (_, [aluminum, steel]) = test_registry(block_definitions());
+    let blueprint = Blueprint {
+        blocks: vec![(IVec3::ZERO, aluminum), (IVec3::new(-3, 0, 0), steel)],
+    };
+
+    let grid = blueprint.to_grid();
+
+    assert_eq!(grid.get_block(pos(0, 0, 0)), aluminum);
+    assert_eq!(grid.get_block(pos(-3, 0, 0)), steel);
+    assert_eq!(grid.get_block(pos(-1, 0, 0)), Block::EMPTY);
+    assert_eq!(grid.chunks().count(), 2);
+}
+
+#[test]
+fn blueprint_round_trips() {
+    let (registry, [aluminum, steel]) = test_registry(block_definitions());
+    let blueprint = Blueprint {
+        blocks: vec![
+            (IVec3::ZERO, aluminum),
+            (IVec3::new(-40, 7, 2), steel),
+            (IVec3::new(1, 1, 1), aluminum),
+        ],
+    };
+
+    let mut data = Vec::new();
+    write_blueprint(&mut data, &blueprint, &registry).unwrap();
+
+    assert_eq!(
+        read_blueprint(data.as_slice(), &registry).unwrap(),
+        blueprint
+    );
+}
+
+#[test]
+fn blueprints_with_unknown_blocks_fail_to_load() {
+    let (registry, [_, steel]) = test_registry(block_definitions());
+    let blueprint = Blueprint {
+        blocks: vec![(IVec3::ZERO, steel)],
+    };
+
+    let mut data = Vec::new();
+    write_blueprint(&mut data, &blueprint, &registry).unwrap();
+
+    let mut registry = BlockRegistry::new();
+    registry.register(BlockDefinition::new("aluminum", "Aluminum"));
+
+    assert!(matches!(
+        read_blueprint(data.as_slice(), &registry),
+        Err(SaveError::UnknownBlock(id)) if id == "steel"
+    ));
+}
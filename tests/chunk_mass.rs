@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use space_game::grid::block::{Block, BLOCK_SIZE};
+use space_game::grid::chunk::{Chunk, CHUNK_SIZE_CUBED};
+use space_game::grid::collider::{
+    generate_fallback_collider_for_chunk, generate_mass_properties_for_chunk,
+};
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::shape::{BlockShape, Orientation};
+
+use crate::scaffolding::test_registry;
+
+mod scaffolding;
+
+// Returns the definitions of a light and a heavy block
+fn block_definitions() -> [BlockDefinition; 2] {
+    [
+        BlockDefinition {
+            density: 1000.0,
+            ..BlockDefinition::new("light", "Light")
+        },
+        BlockDefinition {
+            density: 3000.0,
+            ..BlockDefinition::new("heavy", "Heavy")
+        },
+    ]
+}
+
+fn mass_properties(chunk: &Chunk, registry: &BlockRegistry) -> MassProperties {
+    match generate_mass_properties_for_chunk(chunk, registry) {
+        ColliderMassProperties::MassProperties(mass_properties) => mass_properties,
+        other => panic!("expected mass properties, got {other:?}"),
+    }
+}
+
+fn assert_close(expected: f32, actual: f32) {
+    assert!(
+        (expected - actual).abs() <= expected.abs() * 1e-4 + 1e-4,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn mass_comes_from_block_density() {
+    let (registry, [_, heavy]) = test_registry(block_definitions());
+    let chunk = Chunk::filled(Entity::PLACEHOLDER, heavy);
+    let mass_properties = mass_properties(&chunk, &registry);
+
+    let block_mass = 3000.0 * BLOCK_SIZE * BLOCK_SIZE * BLOCK_SIZE;
+    assert_close(block_mass * CHUNK_SIZE_CUBED as f32, mass_properties.mass);
+    assert!(mass_properties
+        .local_center_of_mass
+        .abs_diff_eq(Vec3::splat(2.0), 1e-4));
+}
+
+#[test]
+fn center_of_mass_moves_towards_denser_blocks() {
+    let (registry, [light, heavy]) = test_registry(block_definitions());
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    chunk.set(0, 0, 0, light);
+    chunk.set(1, 0, 0, heavy);
+
+    let mass_properties = mass_properties(&chunk, &registry);
+
+    // Three quarters of the mass is in the second block
+    let expected_x = (0.5 * 0.25 + 1.5 * 0.75) * BLOCK_SIZE;
+    assert_close(expected_x, mass_properties.local_center_of_mass.x);
+    assert_close(0.5 * BLOCK_SIZE, mass_properties.local_center_of_mass.y);
+    assert_close(0.5 * BLOCK_SIZE, mass_properties.local_center_of_mass.z);
+}
+
+#[test]
+fn single_block_has_cube_inertia() {
+    let (registry, [light, _]) = test_registry(block_definitions());
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    chunk.set(3, 4, 5, light);
+
+    let mass_properties = mass_properties(&chunk, &registry);
+
+    let expected = mass_properties.mass * BLOCK_SIZE * BLOCK_SIZE / 6.0;
+    for inertia in mass_properties.principal_inertia.to_array() {
+        assert_close(expected, inertia);
+    }
+}
+
+#[test]
+fn empty_chunk_has_no_mass() {
+    let (registry, _) = test_registry(block_definitions());
+    let chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+
+    assert_eq!(
+        ColliderMassProperties::Mass(0.0),
+        generate_mass_properties_for_chunk(&chunk, &registry)
+    );
+}
+
+#[test]
+fn shapes_weigh_their_share_of_a_block() {
+    let (registry, [_, heavy]) = test_registry(block_definitions());
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    chunk.set(
+        0,
+        0,
+        0,
+        heavy.with_shape(BlockShape::HalfBlock, Orientation::default()),
+    );
+
+    let mass_properties = mass_properties(&chunk, &registry);
+
+    let block_mass = 3000.0 * BLOCK_SIZE * BLOCK_SIZE * BLOCK_SIZE;
+    assert_close(block_mass / 2.0, mass_properties.mass);
+    assert!(mass_properties
+        .local_center_of_mass
+        .abs_diff_eq(Vec3::new(0.5, 0.25, 0.5) * BLOCK_SIZE, 1e-4));
+}
+
+#[test]
+fn fallback_collider_surrounds_collidable_blocks() {
+    let (registry, [light, heavy]) = test_registry(block_definitions());
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    assert!(generate_fallback_collider_for_chunk(&chunk, &registry).is_none());
+
+    chunk.set(1, 2, 3, light);
+    chunk.set(4, 2, 5, heavy);
+
+    let collider = generate_fallback_collider_for_chunk(&chunk, &registry).unwrap();
+    let aabb = collider.raw.compute_local_aabb();
+    let min = Vec3::new(aabb.mins.x, aabb.mins.y, aabb.mins.z);
+    let max = Vec3::new(aabb.maxs.x, aabb.maxs.y, aabb.maxs.z);
+
+    assert!(min.abs_diff_eq(Vec3::new(1.0, 2.0, 3.0) * BLOCK_SIZE, 1e-4));
+    assert!(max.abs_diff_eq(Vec3::new(5.0, 3.0, 6.0) * BLOCK_SIZE, 1e-4));
+}
@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+
+use space_game::grid::block::Block;
+use space_game::grid::chunk::{Chunk, CHUNK_SIZE, CHUNK_SIZE_CUBED};
+use space_game::grid::mesh::generate_chunk_mesh;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::shape::{BlockShape, Orientation};
+use space_game::grid::textures::texture_layer;
+use space_game::grid::{ChunkPos, Grid};
+
+use crate::scaffolding::test_registry;
+
+mod scaffolding;
+
+const EMPTY: Block = Block::EMPTY;
+
+// Returns the definitions of the aluminum and glass blocks
+fn block_definitions() -> [BlockDefinition; 2] {
+    [
+        BlockDefinition::new("aluminum", "Aluminum"),
+        BlockDefinition {
+            transparent: true,
+            ..BlockDefinition::new("glass", "Glass")
+        },
+    ]
+}
+
+fn quad_count(mesh: &Mesh) -> usize {
+    mesh.indices().unwrap().len() / 6
+}
+
+fn mesh_single_chunk(chunk: Chunk, registry: &BlockRegistry) -> Mesh {
+    let mut grid = Grid::new();
+    grid.set_chunk(ChunkPos::new(0, 0, 0), Some(chunk));
+
+    generate_chunk_mesh(&grid, registry, ChunkPos::new(0, 0, 0)).unwrap()
+}
+
+fn chunk_with(block: Block, positions: &[(u8, u8, u8)]) -> Chunk {
+    let mut chunk = Chunk::new(Entity::PLACEHOLDER, [EMPTY; CHUNK_SIZE_CUBED]);
+
+    for &(x, y, z) in positions {
+        chunk.set(x, y, z, block);
+    }
+
+    chunk
+}
+
+#[test]
+fn empty_chunk_has_no_faces() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let chunk = chunk_with(aluminum, &[]);
+
+    assert_eq!(0, quad_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn single_block_has_six_faces() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let chunk = chunk_with(aluminum, &[(3, 4, 5)]);
+
+    assert_eq!(6, quad_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn solid_chunk_is_merged_into_six_faces() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let chunk = Chunk::new(Entity::PLACEHOLDER, [aluminum; CHUNK_SIZE_CUBED]);
+
+    assert_eq!(6, quad_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn row_of_blocks_is_merged_into_six_faces() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let positions: Vec<(u8, u8, u8)> = (0..CHUNK_SIZE).map(|x| (x, 0, 0)).collect();
+    let chunk = chunk_with(aluminum, &positions);
+
+    assert_eq!(6, quad_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn l_shape_faces() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let chunk = chunk_with(aluminum, &[(0, 0, 0), (1, 0, 0), (0, 1, 0)]);
+
+    assert_eq!(10, quad_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn separated_blocks_are_not_merged() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let chunk = chunk_with(aluminum, &[(0, 0, 0), (2, 0, 0)]);
+
+    assert_eq!(12, quad_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn merged_face_uvs_tile_per_block() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let chunk = Chunk::new(Entity::PLACEHOLDER, [aluminum; CHUNK_SIZE_CUBED]);
+    let mesh = mesh_single_chunk(chunk, &registry);
+
+    let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        panic!("chunk mesh should have UVs");
+    };
+
+    for uv in uvs {
+        assert!(uv[0] == 0.0 || uv[0] == CHUNK_SIZE as f32);
+        assert!(uv[1] == 0.0 || uv[1] == CHUNK_SIZE as f32);
+    }
+}
+
+#[test]
+fn faces_between_solid_chunks_are_culled() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let mut grid = Grid::new();
+    let left_pos = ChunkPos::new(0, 0, 0);
+    let right_pos = ChunkPos::new(1, 0, 0);
+    grid.set_chunk(
+        left_pos,
+        Some(Chunk::new(
+            Entity::PLACEHOLDER,
+            [aluminum; CHUNK_SIZE_CUBED],
+        )),
+    );
+    grid.set_chunk(
+        right_pos,
+        Some(Chunk::new(
+            Entity::PLACEHOLDER,
+            [aluminum; CHUNK_SIZE_CUBED],
+        )),
+    );
+
+    assert_eq!(
+        5,
+        quad_count(&generate_chunk_mesh(&grid, &registry, left_pos).unwrap())
+    );
+    assert_eq!(
+        5,
+        quad_count(&generate_chunk_mesh(&grid, &registry, right_pos).unwrap())
+    );
+}
+
+#[test]
+fn border_blocks_touching_across_chunks_are_culled() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let mut grid = Grid::new();
+    let bottom_pos = ChunkPos::new(0, -1, 0);
+    let top_pos = ChunkPos::new(0, 0, 0);
+    grid.set_chunk(
+        bottom_pos,
+        Some(chunk_with(aluminum, &[(2, CHUNK_SIZE - 1, 2)])),
+    );
+    grid.set_chunk(top_pos, Some(chunk_with(aluminum, &[(2, 0, 2)])));
+
+    assert_eq!(
+        5,
+        quad_count(&generate_chunk_mesh(&grid, &registry, bottom_pos).unwrap())
+    );
+    assert_eq!(
+        5,
+        quad_count(&generate_chunk_mesh(&grid, &registry, top_pos).unwrap())
+    );
+}
+
+#[test]
+fn missing_chunk_has_no_mesh() {
+    let (registry, _) = test_registry(block_definitions());
+    let grid = Grid::new();
+
+    assert!(generate_chunk_mesh(&grid, &registry, ChunkPos::new(0, 0, 0)).is_none());
+}
+
+#[test]
+fn faces_behind_transparent_blocks_are_kept() {
+    let (registry, [aluminum, glass]) = test_registry(block_definitions());
+    let mut chunk = chunk_with(aluminum, &[(0, 0, 0)]);
+    chunk.set(1, 0, 0, glass);
+
+    // The aluminum face behind the glass is still drawn, the glass face against the aluminum is not
+    assert_eq!(11, quad_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn faces_between_same_transparent_blocks_are_culled() {
+    let (registry, [_, glass]) = test_registry(block_definitions());
+    let chunk = chunk_with(glass, &[(0, 0, 0), (1, 0, 0)]);
+
+    assert_eq!(6, quad_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn vertex_colors_hold_the_texture_layer() {
+    let (registry, [aluminum, glass]) = test_registry(block_definitions());
+    let mut chunk = chunk_with(aluminum, &[(0, 0, 0)]);
+    chunk.set(4, 0, 0, glass);
+
+    let mesh = mesh_single_chunk(chunk, &registry);
+    let Some(VertexAttributeValues::Float32x4(colors)) = mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+    else {
+        panic!("chunk mesh has no vertex colors");
+    };
+
+    let layers: Vec<f32> = colors.iter().map(|color| color[3]).collect();
+    assert_eq!(48, layers.len());
+    for block in [aluminum, glass] {
+        let layer = texture_layer(block.id) as f32;
+        assert_eq!(24, layers.iter().filter(|&&other| other == layer).count());
+    }
+}
+
+fn triangle_count(mesh: &Mesh) -> usize {
+    mesh.indices().unwrap().len() / 3
+}
+
+#[test]
+fn slope_has_its_own_faces() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let slope = aluminum.with_shape(BlockShape::Slope, Orientation::default());
+    let chunk = chunk_with(slope, &[(3, 4, 5)]);
+
+    // Three quads and two triangles
+    assert_eq!(8, triangle_count(&mesh_single_chunk(chunk, &registry)));
+}
+
+#[test]
+fn cube_face_against_full_side_of_shape_is_hidden() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let slope = aluminum.with_shape(BlockShape::Slope, Orientation::default());
+    let mut chunk = chunk_with(slope, &[(3, 4, 5)]);
+    // Against the full bottom of the slope
+    chunk.set(3, 3, 5, aluminum);
+
+    // Five cube faces plus the slope without its bottom
+    assert_eq!(
+        5 * 2 + 6,
+        triangle_count(&mesh_single_chunk(chunk, &registry))
+    );
+}
+
+#[test]
+fn cube_face_against_partial_side_of_shape_is_visible() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let slope = aluminum.with_shape(BlockShape::Slope, Orientation::default());
+    let mut chunk = chunk_with(slope, &[(3, 4, 5)]);
+    // Against the sloped front of the slope
+    chunk.set(3, 4, 6, aluminum);
+
+    assert_eq!(
+        6 * 2 + 8,
+        triangle_count(&mesh_single_chunk(chunk, &registry))
+    );
+}
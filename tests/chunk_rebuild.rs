@@ -0,0 +1,148 @@
+use std::thread;
+use std::time::Duration;
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use space_game::grid::block::Block;
+use space_game::grid::chunk::{Chunk, ChunkChanged, ChunkDirty};
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::rebuild::ChunkRebuildTask;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::{ChunkPos, Grid};
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+// Spawns a grid of two chunks and returns the chunks' entities
+fn spawn_test_grid(app: &mut App) -> [Entity; 2] {
+    let aluminum = Block::new(
+        app.world
+            .resource_mut::<BlockRegistry>()
+            .register(BlockDefinition::new("aluminum", "Aluminum")),
+    );
+
+    let mut grid = Grid::new();
+    for x in 0..2 {
+        grid.set_chunk(
+            ChunkPos::new(x, 0, 0),
+            Some(Chunk::filled(Entity::PLACEHOLDER, aluminum)),
+        );
+    }
+
+    let grid_entity = spawn_grid(app, SpawnGrid::new(Transform::IDENTITY, grid));
+
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    [
+        grid.get_chunk(ChunkPos::new(0, 0, 0)).unwrap().entity,
+        grid.get_chunk(ChunkPos::new(1, 0, 0)).unwrap().entity,
+    ]
+}
+
+fn rebuilding(app: &mut App) -> bool {
+    app.world
+        .query_filtered::<(), Or<(With<ChunkDirty>, With<ChunkRebuildTask>)>>()
+        .iter(&app.world)
+        .next()
+        .is_some()
+}
+
+fn wait_for_rebuilds(app: &mut App) {
+    for _ in 0..1000 {
+        app.fixed_update();
+        if !rebuilding(app) {
+            return;
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    panic!("chunks were not rebuilt");
+}
+
+#[test]
+fn spawned_grids_are_rebuilt_in_the_background() {
+    let mut app = App::game_test();
+    let chunks = spawn_test_grid(&mut app);
+
+    // Chunks collide as soon as they are spawned
+    for entity in chunks {
+        assert!(app.world.get::<ChunkDirty>(entity).is_some());
+        assert!(app.world.get::<Collider>(entity).is_some());
+    }
+
+    wait_for_rebuilds(&mut app);
+
+    for entity in chunks {
+        assert!(app.world.get::<Handle<Mesh>>(entity).is_some());
+        assert!(app.world.get::<Collider>(entity).is_some());
+    }
+}
+
+#[test]
+fn batch_of_changes_rebuilds_each_chunk_once() {
+    let mut app = App::game_test();
+    let [first, second] = spawn_test_grid(&mut app);
+    wait_for_rebuilds(&mut app);
+
+    // Events for entities that aren't chunks anymore don't stop the rest of the batch
+    let despawned = app.world.spawn_empty().id();
+    app.world.despawn(despawned);
+
+    app.world.send_event_batch([
+        ChunkChanged(despawned),
+        ChunkChanged(first),
+        ChunkChanged(first),
+        ChunkChanged(second),
+        ChunkChanged(first),
+    ]);
+    app.fixed_update();
+
+    // Each chunk has a single rebuild running and is no longer waiting for another one
+    let mut task_query = app
+        .world
+        .query_filtered::<Entity, (With<ChunkRebuildTask>, Without<ChunkDirty>)>();
+    let mut rebuilt: Vec<Entity> = task_query.iter(&app.world).collect();
+    rebuilt.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(expected, rebuilt);
+
+    wait_for_rebuilds(&mut app);
+    assert!(app.world.get::<Handle<Mesh>>(first).is_some());
+}
+
+#[test]
+fn emptied_chunk_loses_its_collider() {
+    let mut app = App::game_test();
+    let [first, _] = spawn_test_grid(&mut app);
+    wait_for_rebuilds(&mut app);
+
+    app.world
+        .query::<&mut Grid>()
+        .single_mut(&mut app.world)
+        .set_chunk(
+            ChunkPos::new(0, 0, 0),
+            Some(Chunk::filled(first, Block::EMPTY)),
+        );
+    app.world
+        .send_event_batch([ChunkChanged(first), ChunkChanged(first)]);
+
+    wait_for_rebuilds(&mut app);
+    assert!(app.world.get::<Collider>(first).is_none());
+}
@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use space_game::grid::block::{Block, BlockId};
+use space_game::grid::chunk::{Chunk, CHUNK_SIZE, CHUNK_SIZE_CUBED};
+
+const EMPTY: Block = Block::EMPTY;
+
+const ALUMINUM: Block = Block::new(BlockId(1));
+
+fn checkerboard(x: u8, y: u8, z: u8) -> Block {
+    if (x + y + z) & 1 == 0 {
+        ALUMINUM
+    } else {
+        EMPTY
+    }
+}
+
+#[test]
+fn filled_chunk_is_uniform() {
+    let chunk = Chunk::filled(Entity::PLACEHOLDER, ALUMINUM);
+
+    assert!(chunk.is_uniform());
+    assert_eq!(vec![ALUMINUM], chunk.palette().collect::<Vec<_>>());
+    assert_eq!(ALUMINUM, chunk.get(3, 7, 15));
+}
+
+#[test]
+fn uniform_block_array_is_compressed() {
+    let chunk = Chunk::new(Entity::PLACEHOLDER, [EMPTY; CHUNK_SIZE_CUBED]);
+
+    assert!(chunk.is_uniform());
+    assert!(chunk.is_empty());
+}
+
+#[test]
+fn mixed_chunk_round_trips_blocks() {
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, EMPTY);
+
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                chunk.set(x, y, z, checkerboard(x, y, z));
+            }
+        }
+    }
+
+    assert!(!chunk.is_uniform());
+    assert!(!chunk.is_empty());
+
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                assert_eq!(checkerboard(x, y, z), chunk.get(x, y, z));
+            }
+        }
+    }
+}
+
+#[test]
+fn blocks_iterate_in_index_order() {
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, EMPTY);
+    chunk.set(1, 2, 3, ALUMINUM);
+
+    let index = chunk.pos_to_index(1, 2, 3);
+
+    for (i, block) in chunk.blocks().enumerate() {
+        if i == index {
+            assert_eq!(ALUMINUM, block);
+        } else {
+            assert_eq!(EMPTY, block);
+        }
+    }
+}
+
+#[test]
+fn chunk_becomes_uniform_when_last_block_is_removed() {
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, EMPTY);
+    chunk.set(0, 0, 0, ALUMINUM);
+    chunk.set(15, 15, 15, ALUMINUM);
+
+    chunk.set(0, 0, 0, EMPTY);
+    assert!(!chunk.is_uniform());
+
+    chunk.set(15, 15, 15, EMPTY);
+    assert!(chunk.is_uniform());
+    assert!(chunk.is_empty());
+}
@@ -0,0 +1,153 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use big_space::FloatingOrigin;
+
+use space_game::building::events::PlaceBlockRequest;
+use space_game::camera::ActiveCamera;
+use space_game::cockpit::{EnterCockpit, ExitCockpit, Piloted, Seated};
+use space_game::grid::block::Block;
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::shape::{BlockShape, Orientation};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+use space_game::player::{Player, SpawnPlayer};
+use space_game::player_controller::ActivelyControlled;
+use space_game::thruster::ThrusterControl;
+use space_game::UniverseGrid;
+
+use crate::scaffolding::{FixedUpdate, GameTest, MockInput};
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+fn cockpit_pos() -> GridPos {
+    pos(1, 0, 0)
+}
+
+// Spawns a grid with a cockpit turned a quarter turn to the left and a player, and returns the
+// grid and the player
+fn spawn_ship(app: &mut App) -> (Entity, Entity) {
+    let (aluminum, cockpit) = {
+        let mut registry = app.world.resource_mut::<BlockRegistry>();
+        let aluminum = Block::new(registry.register(BlockDefinition::new("aluminum", "Aluminum")));
+        let cockpit = Block::new(registry.register(BlockDefinition {
+            cockpit: true,
+            ..BlockDefinition::new("cockpit", "Cockpit")
+        }));
+
+        (
+            aluminum,
+            registry.orient(cockpit, BlockShape::Cube, Orientation::default().rotated(1)),
+        )
+    };
+
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    chunk.set(0, 0, 0, aluminum);
+    chunk.set(1, 0, 0, cockpit);
+    let mut grid = Grid::new();
+    grid.set_chunk(ChunkPos::new(0, 0, 0), Some(chunk));
+
+    let grid = spawn_grid(app, SpawnGrid::new(Transform::IDENTITY, grid));
+    SpawnPlayer::new(Transform::from_xyz(0.0, 3.0, 0.0), UniverseGrid::default())
+        .apply(&mut app.world);
+    app.fixed_update();
+
+    let player = app
+        .world
+        .query_filtered::<Entity, With<Player>>()
+        .single(&app.world);
+
+    (grid, player)
+}
+
+fn enter_cockpit(app: &mut App, grid: Entity, player: Entity) {
+    EnterCockpit {
+        player,
+        grid,
+        cockpit: cockpit_pos(),
+    }
+    .apply(&mut app.world);
+}
+
+// Returns the entity in control along with the parent of the active camera
+fn control(app: &mut App) -> (Entity, Entity) {
+    let controlled = app
+        .world
+        .query_filtered::<Entity, (With<ActivelyControlled>, With<FloatingOrigin>)>()
+        .single(&app.world);
+    let camera_parent = app
+        .world
+        .query_filtered::<&Parent, With<ActiveCamera>>()
+        .single(&app.world)
+        .get();
+
+    (controlled, camera_parent)
+}
+
+#[test]
+fn entering_and_leaving_hands_over_control() {
+    let mut app = App::game_test();
+    let (grid, player) = spawn_ship(&mut app);
+
+    enter_cockpit(&mut app, grid, player);
+    assert_eq!(control(&mut app), (grid, grid));
+    assert!(app.world.get::<Seated>(player).is_some());
+    let seat_camera = app.world.get::<Piloted>(grid).unwrap().camera;
+
+    ExitCockpit { player }.apply(&mut app.world);
+    assert_eq!(control(&mut app), (player, player));
+    assert!(app.world.get::<Seated>(player).is_none());
+    assert!(app.world.get::<Piloted>(grid).is_none());
+    assert!(app.world.get_entity(seat_camera).is_none());
+}
+
+#[test]
+fn controls_are_relative_to_the_seat() {
+    let mut app = App::game_test();
+    let (grid, player) = spawn_ship(&mut app);
+    enter_cockpit(&mut app, grid, player);
+
+    app.mock_key_press(KeyCode::W);
+    app.fixed_update();
+
+    // The cockpit faces the grid's -X axis
+    let control = app.world.get::<ThrusterControl>(grid).unwrap();
+    assert!(control.linear.distance(Vec3::NEG_X) < 1e-5);
+    assert_eq!(control.angular, Vec3::ZERO);
+}
+
+#[test]
+fn destroying_the_cockpit_ejects_the_pilot() {
+    let mut app = App::game_test();
+    let (grid, player) = spawn_ship(&mut app);
+    enter_cockpit(&mut app, grid, player);
+
+    app.world.send_event(PlaceBlockRequest {
+        grid,
+        pos: cockpit_pos(),
+        block: Block::EMPTY,
+        record_history: false,
+    });
+    app.fixed_update();
+    app.fixed_update();
+
+    assert!(app.world.get::<Seated>(player).is_none());
+    assert_eq!(control(&mut app), (player, player));
+}
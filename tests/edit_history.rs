@@ -0,0 +1,205 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+use space_game::building::events::{PlaceBlockRequest, RedoRequest, UndoRequest};
+use space_game::building::history::{
+    BlockChange, EditHistories, EditHistory, LastEditedGrid, Transaction, MAX_HISTORY_LENGTH,
+};
+use space_game::grid::block::{Block, BlockId};
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+// Sets a block, adding an empty chunk for it if needed
+fn set_block(grid: &mut Grid, coords: IVec3, block: Block) {
+    let pos = GridPos::from_block_coords(coords);
+    if grid.get_chunk(pos.chunk_pos).is_none() {
+        grid.set_chunk(
+            pos.chunk_pos,
+            Some(Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY)),
+        );
+    }
+
+    grid.get_chunk_mut(pos.chunk_pos)
+        .unwrap()
+        .set_by_block_pos(pos.block_pos, block);
+}
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+// The history never looks blocks up, so any block will do outside of the app
+const PLACED: Block = Block::new(BlockId(1));
+
+fn pos(x: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, 0, 0))
+}
+
+fn placement(x: i32) -> Transaction {
+    Transaction {
+        changes: vec![BlockChange {
+            pos: pos(x),
+            previous: Block::EMPTY,
+            new: PLACED,
+        }],
+    }
+}
+
+#[test]
+fn undo_reverts_latest_transaction() {
+    let mut history = EditHistory::default();
+    history.record(placement(0));
+    history.record(placement(1));
+
+    let requests = history.undo(Entity::PLACEHOLDER).unwrap();
+    assert_eq!(1, requests.len());
+    assert_eq!(pos(1), requests[0].pos);
+    assert_eq!(Block::EMPTY, requests[0].block);
+    assert!(!requests[0].record_history);
+
+    let requests = history.redo(Entity::PLACEHOLDER).unwrap();
+    assert_eq!(pos(1), requests[0].pos);
+    assert_eq!(PLACED, requests[0].block);
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn new_edits_discard_undone_edits() {
+    let mut history = EditHistory::default();
+    history.record(placement(0));
+    history.undo(Entity::PLACEHOLDER);
+    assert!(history.can_redo());
+
+    history.record(placement(1));
+    assert!(!history.can_redo());
+    assert!(history.redo(Entity::PLACEHOLDER).is_none());
+}
+
+#[test]
+fn history_length_is_limited() {
+    let mut history = EditHistory::default();
+    for x in 0..MAX_HISTORY_LENGTH as i32 + 10 {
+        history.record(placement(x));
+    }
+
+    let mut undo_count = 0;
+    while history.undo(Entity::PLACEHOLDER).is_some() {
+        undo_count += 1;
+    }
+    assert_eq!(MAX_HISTORY_LENGTH, undo_count);
+}
+
+// Spawns a row of aluminum along the X axis and returns the grid and the aluminum block
+fn spawn_row(app: &mut App, length: i32) -> (Entity, Block) {
+    let aluminum = Block::new(
+        app.world
+            .resource_mut::<BlockRegistry>()
+            .register(BlockDefinition::new("aluminum", "Aluminum")),
+    );
+
+    let mut grid = Grid::new();
+    for x in 0..length {
+        set_block(&mut grid, IVec3::new(x, 0, 0), aluminum);
+    }
+
+    let grid_entity = spawn_grid(app, SpawnGrid::new(Transform::IDENTITY, grid));
+    app.fixed_update();
+
+    (grid_entity, aluminum)
+}
+
+fn remove_block(app: &mut App, grid: Entity, x: i32) {
+    app.world.send_event(PlaceBlockRequest {
+        grid,
+        pos: pos(x),
+        block: Block::EMPTY,
+        record_history: true,
+    });
+}
+
+#[test]
+fn undo_restores_deleted_chunks() {
+    let mut app = App::game_test();
+    // The last block is alone in the second chunk
+    let (grid_entity, aluminum) = spawn_row(&mut app, 17);
+
+    remove_block(&mut app, grid_entity, 16);
+    app.fixed_update();
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    assert!(grid.get_chunk(ChunkPos::new(1, 0, 0)).is_none());
+
+    app.world.send_event(UndoRequest { grid: grid_entity });
+    app.fixed_update();
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    assert_eq!(aluminum, grid.get_block(pos(16)));
+    let chunk_entity = grid.get_chunk(ChunkPos::new(1, 0, 0)).unwrap().entity;
+    assert_eq!(
+        grid_entity,
+        app.world.get::<Parent>(chunk_entity).unwrap().get()
+    );
+
+    app.world.send_event(RedoRequest { grid: grid_entity });
+    app.fixed_update();
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    assert_eq!(Block::EMPTY, grid.get_block(pos(16)));
+}
+
+#[test]
+fn changes_from_one_update_are_undone_together() {
+    let mut app = App::game_test();
+    let (grid_entity, aluminum) = spawn_row(&mut app, 10);
+
+    for x in 7..10 {
+        remove_block(&mut app, grid_entity, x);
+    }
+    app.fixed_update();
+
+    app.world.send_event(UndoRequest { grid: grid_entity });
+    app.fixed_update();
+
+    let grid = app.world.get::<Grid>(grid_entity).unwrap();
+    for x in 7..10 {
+        assert_eq!(aluminum, grid.get_block(pos(x)));
+    }
+    assert!(!app.world.resource::<EditHistories>().0[&grid_entity].can_undo());
+}
+
+#[test]
+fn undo_restores_a_deleted_grid() {
+    let mut app = App::game_test();
+    let (grid_entity, aluminum) = spawn_row(&mut app, 1);
+
+    // Removing the only block despawns the grid
+    remove_block(&mut app, grid_entity, 0);
+    app.fixed_update();
+    assert!(app.world.get_entity(grid_entity).is_none());
+
+    app.world.send_event(UndoRequest { grid: grid_entity });
+    app.fixed_update();
+
+    let restored_entity = app.world.resource::<LastEditedGrid>().0.unwrap();
+    let grid = app.world.get::<Grid>(restored_entity).unwrap();
+    assert_eq!(aluminum, grid.get_block(pos(0)));
+
+    // The history moved over to the new grid
+    app.world.send_event(RedoRequest {
+        grid: restored_entity,
+    });
+    app.fixed_update();
+    assert!(app.world.get_entity(restored_entity).is_none());
+}
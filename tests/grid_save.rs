@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+
+use space_game::grid::block::Block;
+use space_game::grid::chunk::Chunk;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::shape::{BlockShape, Orientation};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+use space_game::save::grid::{read_grid, write_grid, GRID_FORMAT_VERSION};
+use space_game::save::SaveError;
+
+use crate::scaffolding::test_registry;
+
+mod scaffolding;
+
+const EMPTY: Block = Block::EMPTY;
+
+// Returns the definition of the aluminum block
+fn block_definitions() -> [BlockDefinition; 1] {
+    [BlockDefinition::new("aluminum", "Aluminum")]
+}
+
+fn test_grid(aluminum: Block) -> Grid {
+    let mut grid = Grid::new();
+
+    grid.set_chunk(
+        ChunkPos::new(0, 0, 0),
+        Some(Chunk::filled(Entity::PLACEHOLDER, aluminum)),
+    );
+
+    let mut mixed_chunk = Chunk::filled(Entity::PLACEHOLDER, EMPTY);
+    mixed_chunk.set(1, 2, 3, aluminum);
+    mixed_chunk.set(15, 0, 7, aluminum);
+    mixed_chunk.set(
+        4,
+        5,
+        6,
+        aluminum.with_shape(BlockShape::Corner, Orientation::from_index(13).unwrap()),
+    );
+    grid.set_chunk(ChunkPos::new(-1, 4, 2), Some(mixed_chunk));
+
+    grid.apply_damage(GridPos::from_block_coords(IVec3::new(1, 2, 3)), 25.0);
+    grid.apply_damage(GridPos::from_block_coords(IVec3::new(-15, 66, 35)), 40.0);
+
+    grid
+}
+
+#[test]
+fn grid_round_trips() {
+    let (registry, [aluminum]) = test_registry(block_definitions());
+    let grid = test_grid(aluminum);
+    let transform = Transform::from_xyz(1.0, -2.0, 3.5).with_rotation(Quat::from_rotation_y(1.2));
+
+    let mut data = Vec::new();
+    write_grid(&mut data, &grid, transform, &registry).unwrap();
+
+    let (loaded_grid, loaded_transform) = read_grid(data.as_slice(), &registry).unwrap();
+
+    assert_eq!(transform, loaded_transform);
+    assert_eq!(grid.chunks().count(), loaded_grid.chunks().count());
+
+    for (pos, chunk) in grid.chunks() {
+        let loaded_chunk = loaded_grid.get_chunk(*pos).unwrap();
+        assert!(chunk.blocks().eq(loaded_chunk.blocks()));
+        assert_eq!(chunk.is_uniform(), loaded_chunk.is_uniform());
+    }
+
+    assert_eq!(
+        grid.damaged_blocks().count(),
+        loaded_grid.damaged_blocks().count()
+    );
+    for (pos, damage) in grid.damaged_blocks() {
+        assert_eq!(damage, loaded_grid.damage(pos));
+    }
+}
+
+#[test]
+fn grid_save_is_compressed() {
+    let (registry, [aluminum]) = test_registry(block_definitions());
+    let mut data = Vec::new();
+    write_grid(
+        &mut data,
+        &test_grid(aluminum),
+        Transform::default(),
+        &registry,
+    )
+    .unwrap();
+
+    // A mixed chunk alone has 4096 block indices
+    assert!(data.len() < 1024);
+}
+
+#[test]
+fn newer_format_version_is_rejected() {
+    let (registry, [aluminum]) = test_registry(block_definitions());
+    let mut data = Vec::new();
+    write_grid(
+        &mut data,
+        &test_grid(aluminum),
+        Transform::default(),
+        &registry,
+    )
+    .unwrap();
+    data[4..6].copy_from_slice(&(GRID_FORMAT_VERSION + 1).to_le_bytes());
+
+    assert!(matches!(
+        read_grid(data.as_slice(), &registry),
+        Err(SaveError::UnsupportedVersion(version)) if version == GRID_FORMAT_VERSION + 1
+    ));
+}
+
+#[test]
+fn wrong_file_type_is_rejected() {
+    let (registry, _) = test_registry(block_definitions());
+    let data = b"not a grid".to_vec();
+
+    assert!(matches!(
+        read_grid(data.as_slice(), &registry),
+        Err(SaveError::InvalidMagic)
+    ));
+}
+
+#[test]
+fn unknown_block_is_rejected() {
+    let (registry, [aluminum]) = test_registry(block_definitions());
+    let mut data = Vec::new();
+    write_grid(
+        &mut data,
+        &test_grid(aluminum),
+        Transform::default(),
+        &registry,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        read_grid(data.as_slice(), &BlockRegistry::new()),
+        Err(SaveError::UnknownBlock(id)) if id == "aluminum"
+    ));
+}
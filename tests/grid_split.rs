@@ -0,0 +1,220 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use space_game::building::events::PlaceBlockRequest;
+use space_game::grid::block::{Block, BLOCK_SIZE};
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::connectivity::{find_cut_off_islands, find_islands};
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::{Grid, GridPos};
+use space_game::UniverseGrid;
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Sets a block, adding an empty chunk for it if needed
+fn set_block(grid: &mut Grid, coords: IVec3, block: Block) {
+    let pos = GridPos::from_block_coords(coords);
+    if grid.get_chunk(pos.chunk_pos).is_none() {
+        grid.set_chunk(
+            pos.chunk_pos,
+            Some(Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY)),
+        );
+    }
+
+    grid.get_chunk_mut(pos.chunk_pos)
+        .unwrap()
+        .set_by_block_pos(pos.block_pos, block);
+}
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+fn register_aluminum(registry: &mut BlockRegistry) -> Block {
+    Block::new(registry.register(BlockDefinition::new("aluminum", "Aluminum")))
+}
+
+// Builds a grid with a row of blocks along the x axis
+fn grid_with_row(block: Block, xs: impl IntoIterator<Item = i32>) -> Grid {
+    let mut grid = Grid::new();
+    for x in xs {
+        set_block(&mut grid, IVec3::new(x, 0, 0), block);
+    }
+
+    grid
+}
+
+#[test]
+fn connected_blocks_form_one_island() {
+    let aluminum = register_aluminum(&mut BlockRegistry::new());
+    // The row crosses a chunk border
+    let grid = grid_with_row(aluminum, 10..20);
+
+    let islands = find_islands(&grid);
+    assert_eq!(1, islands.len());
+    assert_eq!(10, islands[0].len());
+}
+
+#[test]
+fn islands_are_sorted_by_size() {
+    let aluminum = register_aluminum(&mut BlockRegistry::new());
+    let grid = grid_with_row(aluminum, (0..3).chain(5..12).chain(20..24));
+
+    let sizes: Vec<usize> = find_islands(&grid).iter().map(Vec::len).collect();
+    assert_eq!(vec![7, 4, 3], sizes);
+}
+
+#[test]
+fn only_cut_off_islands_are_found() {
+    let aluminum = register_aluminum(&mut BlockRegistry::new());
+    let grid = grid_with_row(aluminum, (0..5).chain(6..20));
+
+    let islands = find_cut_off_islands(&grid, &[pos(5, 0, 0)]);
+    assert_eq!(1, islands.len());
+    assert_eq!(5, islands[0].len());
+
+    // Removing the end of the row doesn't cut anything off
+    assert!(find_cut_off_islands(&grid, &[pos(20, 0, 0)]).is_empty());
+}
+
+#[test]
+fn removing_a_connecting_block_splits_the_grid() {
+    let mut app = App::game_test();
+    let aluminum = register_aluminum(&mut app.world.resource_mut::<BlockRegistry>());
+
+    let grid_entity = spawn_grid(
+        &mut app,
+        SpawnGrid {
+            transform: Transform::from_xyz(1.0, 2.0, 3.0),
+            grid_cell: UniverseGrid::new(4, 5, 6),
+            velocity: Velocity::linear(Vec3::X),
+            grid: grid_with_row(aluminum, 0..20),
+        },
+    );
+    app.fixed_update();
+
+    app.world.send_event(PlaceBlockRequest {
+        grid: grid_entity,
+        pos: pos(5, 0, 0),
+        block: Block::EMPTY,
+        record_history: true,
+    });
+    app.fixed_update();
+
+    let mut grid_query = app
+        .world
+        .query::<(Entity, &Grid, &UniverseGrid, &Velocity)>();
+    let grids: Vec<_> = grid_query.iter(&app.world).collect();
+    assert_eq!(2, grids.len());
+
+    for (entity, grid, cell, velocity) in grids {
+        let block_count = find_islands(grid)[0].len();
+        if entity == grid_entity {
+            assert_eq!(14, block_count);
+        } else {
+            assert_eq!(5, block_count);
+        }
+
+        assert_eq!(1, find_islands(grid).len());
+        assert_eq!(UniverseGrid::new(4, 5, 6), *cell);
+        assert_eq!(Vec3::X, velocity.linvel);
+    }
+}
+
+#[test]
+fn split_off_blocks_keep_their_damage() {
+    let mut app = App::game_test();
+    let aluminum = register_aluminum(&mut app.world.resource_mut::<BlockRegistry>());
+
+    let grid_entity = spawn_grid(
+        &mut app,
+        SpawnGrid::new(Transform::IDENTITY, grid_with_row(aluminum, 0..20)),
+    );
+    app.fixed_update();
+
+    app.world
+        .get_mut::<Grid>(grid_entity)
+        .unwrap()
+        .apply_damage(pos(2, 0, 0), 5.0);
+
+    app.world.send_event(PlaceBlockRequest {
+        grid: grid_entity,
+        pos: pos(5, 0, 0),
+        block: Block::EMPTY,
+        record_history: true,
+    });
+    app.fixed_update();
+
+    let mut grid_query = app.world.query::<(Entity, &Grid)>();
+    for (entity, grid) in grid_query.iter(&app.world) {
+        if entity == grid_entity {
+            assert_eq!(0.0, grid.damage(pos(2, 0, 0)));
+        } else {
+            assert_eq!(5.0, grid.damage(pos(2, 0, 0)));
+        }
+    }
+}
+
+#[test]
+fn split_off_islands_keep_moving_with_the_spin_of_the_grid() {
+    let mut app = App::game_test();
+    let aluminum = Block::new(app.world.resource_mut::<BlockRegistry>().register(
+        BlockDefinition {
+            density: 1000.0,
+            ..BlockDefinition::new("aluminum", "Aluminum")
+        },
+    ));
+
+    let grid_entity = spawn_grid(
+        &mut app,
+        SpawnGrid {
+            velocity: Velocity::angular(Vec3::Y),
+            ..SpawnGrid::new(Transform::IDENTITY, grid_with_row(aluminum, 0..20))
+        },
+    );
+    app.fixed_update();
+
+    app.world.send_event(PlaceBlockRequest {
+        grid: grid_entity,
+        pos: pos(5, 0, 0),
+        block: Block::EMPTY,
+        record_history: true,
+    });
+    app.fixed_update();
+
+    let mut grid_query = app
+        .world
+        .query_filtered::<(Entity, &Velocity), With<Grid>>();
+    let mut island_velocity = Vec3::ZERO;
+    let mut grid_velocity = Vec3::ZERO;
+    for (entity, velocity) in grid_query.iter(&app.world) {
+        if entity == grid_entity {
+            grid_velocity = velocity.linvel;
+        } else {
+            island_velocity = velocity.linvel;
+        }
+    }
+
+    // The centers of the two parts are 10.5 blocks apart, and they move apart at the speed that
+    // the spin gave them
+    let expected = 10.5 * BLOCK_SIZE;
+    assert!((island_velocity - grid_velocity).z > expected * 0.95);
+    assert!((island_velocity - grid_velocity).z < expected * 1.05);
+}
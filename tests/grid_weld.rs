@@ -0,0 +1,220 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use space_game::building::events::{FillAreaRequest, PlaceBlockRequest};
+use space_game::grid::block::{Block, BLOCK_SIZE};
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::connectivity::find_islands;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::weld::LatticeTransform;
+use space_game::grid::{Grid, GridPos};
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Sets a block, adding an empty chunk for it if needed
+fn set_block(grid: &mut Grid, coords: IVec3, block: Block) {
+    let pos = GridPos::from_block_coords(coords);
+    if grid.get_chunk(pos.chunk_pos).is_none() {
+        grid.set_chunk(
+            pos.chunk_pos,
+            Some(Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY)),
+        );
+    }
+
+    grid.get_chunk_mut(pos.chunk_pos)
+        .unwrap()
+        .set_by_block_pos(pos.block_pos, block);
+}
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+// Builds a grid with a row of blocks along the x axis
+fn grid_with_row(block: Block, xs: impl IntoIterator<Item = i32>) -> Grid {
+    let mut grid = Grid::new();
+    for x in xs {
+        set_block(&mut grid, IVec3::new(x, 0, 0), block);
+    }
+
+    grid
+}
+
+#[test]
+fn offset_grids_line_up() {
+    let target = GlobalTransform::from_xyz(1.0, 0.0, 0.0);
+    let source = GlobalTransform::from_xyz(1.0 + 6.0 * BLOCK_SIZE, 0.0, -2.0 * BLOCK_SIZE);
+
+    let lattice = LatticeTransform::between(&target, &source).unwrap();
+    assert_eq!(IVec3::new(6, 0, -2), lattice.apply(IVec3::ZERO));
+    assert_eq!(IVec3::new(7, 1, -2), lattice.apply(IVec3::new(1, 1, 0)));
+}
+
+#[test]
+fn rotated_grids_line_up() {
+    let target = GlobalTransform::IDENTITY;
+    let source = GlobalTransform::from(
+        Transform::from_xyz(11.0 * BLOCK_SIZE, 0.0, BLOCK_SIZE)
+            .with_rotation(Quat::from_rotation_y(std::f32::consts::PI)),
+    );
+
+    let lattice = LatticeTransform::between(&target, &source).unwrap();
+    assert_eq!(IVec3::new(10, 0, 0), lattice.apply(IVec3::ZERO));
+    assert_eq!(IVec3::new(9, 0, 0), lattice.apply(IVec3::X));
+}
+
+#[test]
+fn misaligned_grids_do_not_line_up() {
+    let target = GlobalTransform::IDENTITY;
+
+    let offset = GlobalTransform::from_xyz(BLOCK_SIZE / 2.0, 0.0, 0.0);
+    assert_eq!(None, LatticeTransform::between(&target, &offset));
+
+    let rotated = GlobalTransform::from(Transform::from_rotation(Quat::from_rotation_z(0.5)));
+    assert_eq!(None, LatticeTransform::between(&target, &rotated));
+}
+
+#[test]
+fn bridging_block_welds_grids() {
+    let mut app = App::game_test();
+    let aluminum = Block::new(app.world.resource_mut::<BlockRegistry>().register(
+        BlockDefinition {
+            density: 1000.0,
+            ..BlockDefinition::new("aluminum", "Aluminum")
+        },
+    ));
+
+    let target = spawn_grid(
+        &mut app,
+        SpawnGrid {
+            velocity: Velocity::linear(Vec3::new(0.1, 0.0, 0.0)),
+            ..SpawnGrid::new(Transform::IDENTITY, grid_with_row(aluminum, 0..5))
+        },
+    );
+    spawn_grid(
+        &mut app,
+        SpawnGrid::new(
+            Transform::from_xyz(6.0 * BLOCK_SIZE, 0.0, 0.0),
+            grid_with_row(aluminum, 0..5),
+        ),
+    );
+    app.fixed_update();
+
+    app.world.send_event(PlaceBlockRequest {
+        grid: target,
+        pos: pos(5, 0, 0),
+        block: aluminum,
+        record_history: true,
+    });
+    app.fixed_update();
+
+    let mut grid_query = app.world.query::<(Entity, &Grid, &Velocity, &Children)>();
+    let (entity, grid, velocity, children) = grid_query.single(&app.world);
+    assert_eq!(target, entity);
+
+    let islands = find_islands(grid);
+    assert_eq!(1, islands.len());
+    assert_eq!(11, islands[0].len());
+
+    for (_, chunk) in grid.chunks() {
+        assert!(children.contains(&chunk.entity));
+    }
+
+    // Six of the eleven blocks were moving
+    assert!(velocity
+        .linvel
+        .abs_diff_eq(Vec3::new(0.1, 0.0, 0.0) * 6.0 / 11.0, 1e-4));
+}
+
+#[test]
+fn welded_blocks_keep_their_damage() {
+    let mut app = App::game_test();
+    let aluminum = Block::new(
+        app.world
+            .resource_mut::<BlockRegistry>()
+            .register(BlockDefinition::new("aluminum", "Aluminum")),
+    );
+
+    let target = spawn_grid(
+        &mut app,
+        SpawnGrid::new(Transform::IDENTITY, grid_with_row(aluminum, 0..5)),
+    );
+    let source = spawn_grid(
+        &mut app,
+        SpawnGrid::new(
+            Transform::from_xyz(6.0 * BLOCK_SIZE, 0.0, 0.0),
+            grid_with_row(aluminum, 0..5),
+        ),
+    );
+    app.fixed_update();
+
+    app.world
+        .get_mut::<Grid>(source)
+        .unwrap()
+        .apply_damage(pos(2, 0, 0), 5.0);
+
+    app.world.send_event(PlaceBlockRequest {
+        grid: target,
+        pos: pos(5, 0, 0),
+        block: aluminum,
+        record_history: true,
+    });
+    app.fixed_update();
+
+    let grid = app.world.get::<Grid>(target).unwrap();
+    assert_eq!(5.0, grid.damage(pos(8, 0, 0)));
+}
+
+#[test]
+fn filled_area_welds_the_grids_it_touches() {
+    let mut app = App::game_test();
+    let aluminum = Block::new(
+        app.world
+            .resource_mut::<BlockRegistry>()
+            .register(BlockDefinition::new("aluminum", "Aluminum")),
+    );
+
+    let target = spawn_grid(
+        &mut app,
+        SpawnGrid::new(Transform::IDENTITY, grid_with_row(aluminum, 0..5)),
+    );
+    spawn_grid(
+        &mut app,
+        SpawnGrid::new(
+            Transform::from_xyz(8.0 * BLOCK_SIZE, 0.0, 0.0),
+            grid_with_row(aluminum, 0..5),
+        ),
+    );
+    app.fixed_update();
+
+    // Only the far face of the area touches the other grid
+    app.world.send_event(FillAreaRequest {
+        grid: target,
+        start: pos(5, -1, -1),
+        end: pos(7, 1, 1),
+        block: aluminum,
+    });
+    app.fixed_update();
+
+    let mut grid_query = app.world.query::<(Entity, &Grid)>();
+    let (entity, grid) = grid_query.single(&app.world);
+    assert_eq!(target, entity);
+    assert_eq!(aluminum, grid.get_block(pos(12, 0, 0)));
+}
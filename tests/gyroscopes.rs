@@ -0,0 +1,142 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use space_game::grid::block::Block;
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry, GyroscopeDefinition};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+use space_game::gyroscope::{find_gyroscope_torque, gyroscope_torque, MAX_TURN_RATE};
+use space_game::thruster::{Thruster, ThrusterControl};
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+// Registers the test blocks and returns a grid built from them
+fn test_grid(registry: &mut BlockRegistry) -> Grid {
+    let aluminum = Block::new(registry.register(BlockDefinition {
+        density: 2700.0,
+        ..BlockDefinition::new("aluminum", "Aluminum")
+    }));
+    let gyroscope = Block::new(registry.register(BlockDefinition {
+        density: 2700.0,
+        gyroscope: Some(GyroscopeDefinition { max_torque: 500.0 }),
+        ..BlockDefinition::new("gyroscope", "Gyroscope")
+    }));
+
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    for x in 0..4 {
+        chunk.set(x, 0, 0, aluminum);
+    }
+    chunk.set(1, 1, 0, gyroscope);
+    chunk.set(2, 1, 0, gyroscope);
+
+    let mut grid = Grid::new();
+    grid.set_chunk(ChunkPos::new(0, 0, 0), Some(chunk));
+
+    grid
+}
+
+fn thruster(direction: IVec3) -> Thruster {
+    Thruster {
+        pos: GridPos::from_block_coords(IVec3::ZERO),
+        direction,
+        max_thrust: 1000.0,
+        power: 1.0,
+    }
+}
+
+#[test]
+fn gyroscopes_add_up() {
+    let mut registry = BlockRegistry::new();
+    let grid = test_grid(&mut registry);
+
+    assert_eq!(find_gyroscope_torque(&grid, &registry), 1000.0);
+}
+
+#[test]
+fn dampeners_stop_axes_without_input() {
+    let mut control = ThrusterControl::default();
+    control.angular.x = 1.0;
+
+    // Turning towards the turn rate asked for around X and against the spin around Z, limited by
+    // the gyroscopes
+    let torque = gyroscope_torque(
+        100.0,
+        &control,
+        Vec3::new(MAX_TURN_RATE, 0.0, 5.0),
+        Mat3::IDENTITY,
+    );
+    assert_eq!(torque, Vec3::new(0.0, 0.0, -100.0));
+
+    control.dampeners = false;
+    let torque = gyroscope_torque(100.0, &control, Vec3::new(0.0, 0.0, 5.0), Mat3::IDENTITY);
+    assert_eq!(torque, Vec3::new(100.0, 0.0, 0.0));
+}
+
+#[test]
+fn dampeners_are_capped_by_the_thrust_available() {
+    let thrusters = [
+        thruster(IVec3::X),
+        thruster(IVec3::NEG_X),
+        thruster(IVec3::Y),
+    ];
+    let mut control = ThrusterControl::default();
+
+    // Stopping 10 kg moving at 1 m/s within a tick takes 640 N
+    let demand = control.linear_demand(&thrusters, Vec3::new(1.0, 0.0, 0.0), 10.0);
+    assert!((demand.x + 0.64).abs() < 1e-5);
+
+    // There are no thrusters pushing down or along Z
+    let demand = control.linear_demand(&thrusters, Vec3::new(0.0, 1.0, -100.0), 10.0);
+    assert_eq!(demand, Vec3::ZERO);
+
+    let demand = control.linear_demand(&thrusters, Vec3::new(0.0, -100.0, 0.0), 10.0);
+    assert_eq!(demand, Vec3::Y);
+
+    // Input always wins, and nothing is dampened with the dampeners off
+    control.linear = Vec3::X;
+    let demand = control.linear_demand(&thrusters, Vec3::new(1.0, 0.0, 0.0), 10.0);
+    assert_eq!(demand, Vec3::X);
+
+    control.linear = Vec3::ZERO;
+    control.dampeners = false;
+    let demand = control.linear_demand(&thrusters, Vec3::new(1.0, 0.0, 0.0), 10.0);
+    assert_eq!(demand, Vec3::ZERO);
+}
+
+#[test]
+fn dampeners_stop_spinning_grids() {
+    let mut app = App::game_test();
+    let grid = test_grid(&mut app.world.resource_mut::<BlockRegistry>());
+
+    let grid_entity = spawn_grid(
+        &mut app,
+        SpawnGrid {
+            velocity: Velocity::angular(Vec3::Y),
+            ..SpawnGrid::new(Transform::IDENTITY, grid)
+        },
+    );
+
+    for _ in 0..64 {
+        app.fixed_update();
+    }
+
+    let velocity = app.world.get::<Velocity>(grid_entity).unwrap();
+    assert!(velocity.angvel.length() < 0.01);
+}
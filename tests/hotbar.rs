@@ -0,0 +1,45 @@
+use space_game::building::hotbar::{Hotbar, HOTBAR_SLOTS};
+use space_game::grid::registry::BlockDefinition;
+
+use crate::scaffolding::test_registry;
+
+mod scaffolding;
+
+// Returns the definitions of the aluminum and steel blocks
+fn block_definitions() -> [BlockDefinition; 2] {
+    [
+        BlockDefinition::new("aluminum", "Aluminum"),
+        BlockDefinition::new("steel", "Steel"),
+    ]
+}
+
+#[test]
+fn registered_blocks_fill_empty_slots() {
+    let (registry, [aluminum, steel]) = test_registry(block_definitions());
+    let mut hotbar = Hotbar::default();
+    hotbar.slots[0] = Some(steel.id);
+
+    hotbar.fill_from_registry(&registry);
+
+    // The empty block is never put on the hotbar and blocks already on it aren't repeated
+    assert_eq!(hotbar.slots[0], Some(steel.id));
+    assert_eq!(hotbar.slots[1], Some(aluminum.id));
+    assert!(hotbar.slots[2..].iter().all(|slot| slot.is_none()));
+
+    assert_eq!(hotbar.selected_block(&registry), Some(steel));
+}
+
+#[test]
+fn scrolling_wraps_around() {
+    let (registry, _) = test_registry(block_definitions());
+    let mut hotbar = Hotbar::default();
+
+    hotbar.scroll(-1);
+    assert_eq!(hotbar.selected, HOTBAR_SLOTS - 1);
+
+    hotbar.scroll(2);
+    assert_eq!(hotbar.selected, 1);
+
+    // Empty slots don't place anything
+    assert_eq!(hotbar.selected_block(&registry), None);
+}
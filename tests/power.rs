@@ -0,0 +1,339 @@
+use bevy::prelude::*;
+
+use space_game::building::events::PlaceBlockRequest;
+use space_game::grid::block::{Block, BLOCK_SIZE};
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{
+    BatteryDefinition, BlockDefinition, BlockRegistry, ConsumerDefinition, GeneratorDefinition,
+    ThrusterDefinition,
+};
+use space_game::grid::{Grid, GridPos};
+use space_game::power::{conductors_touch, share_power, GridPower};
+use space_game::thruster::Thrusters;
+
+use crate::scaffolding::{register_blocks, test_registry, FixedUpdate, GameTest};
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Sets a block, adding an empty chunk for it if needed
+fn set_block(grid: &mut Grid, coords: IVec3, block: Block) {
+    let pos = GridPos::from_block_coords(coords);
+    if grid.get_chunk(pos.chunk_pos).is_none() {
+        grid.set_chunk(
+            pos.chunk_pos,
+            Some(Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY)),
+        );
+    }
+
+    grid.get_chunk_mut(pos.chunk_pos)
+        .unwrap()
+        .set_by_block_pos(pos.block_pos, block);
+}
+
+fn grid_with(blocks: &[(IVec3, Block)]) -> Grid {
+    let mut grid = Grid::new();
+    for &(coords, block) in blocks {
+        set_block(&mut grid, coords, block);
+    }
+
+    grid
+}
+
+struct TestBlocks {
+    aluminum: Block,
+    generator: Block,
+    battery: Block,
+    conductor: Block,
+    lamp: Block,
+    heater: Block,
+    thruster: Block,
+}
+
+impl From<[Block; 7]> for TestBlocks {
+    fn from([aluminum, generator, battery, conductor, lamp, heater, thruster]: [Block; 7]) -> Self {
+        Self {
+            aluminum,
+            generator,
+            battery,
+            conductor,
+            lamp,
+            heater,
+            thruster,
+        }
+    }
+}
+
+// Returns the definitions of the test blocks in the order of their fields
+fn block_definitions() -> [BlockDefinition; 7] {
+    [
+        BlockDefinition::new("aluminum", "Aluminum"),
+        BlockDefinition {
+            generator: Some(GeneratorDefinition { output: 100.0 }),
+            ..BlockDefinition::new("generator", "Generator")
+        },
+        BlockDefinition {
+            battery: Some(BatteryDefinition {
+                capacity: 1000.0,
+                max_power: 50.0,
+            }),
+            ..BlockDefinition::new("battery", "Battery")
+        },
+        BlockDefinition {
+            conductor: true,
+            ..BlockDefinition::new("conductor", "Conductor")
+        },
+        BlockDefinition {
+            consumer: Some(ConsumerDefinition {
+                power: 60.0,
+                priority: 0,
+            }),
+            ..BlockDefinition::new("lamp", "Lamp")
+        },
+        BlockDefinition {
+            consumer: Some(ConsumerDefinition {
+                power: 40.0,
+                priority: 1,
+            }),
+            ..BlockDefinition::new("heater", "Heater")
+        },
+        BlockDefinition {
+            density: 1000.0,
+            thruster: Some(ThrusterDefinition { max_thrust: 1000.0 }),
+            consumer: Some(ConsumerDefinition {
+                power: 40.0,
+                priority: 0,
+            }),
+            ..BlockDefinition::new("thruster", "Thruster")
+        },
+    ]
+}
+
+#[test]
+fn power_blocks_in_a_grid_share_one_network() {
+    let (registry, blocks) = test_registry(block_definitions());
+    let blocks = TestBlocks::from(blocks);
+    // The lamp is only connected to the generator through aluminum
+    let grid = grid_with(&[
+        (IVec3::new(14, 0, 0), blocks.generator),
+        (IVec3::new(15, 0, 0), blocks.aluminum),
+        (IVec3::new(16, 0, 0), blocks.aluminum),
+        (IVec3::new(17, 0, 0), blocks.lamp),
+    ]);
+
+    let mut power = GridPower::default();
+    power.update(&grid, &registry);
+    power.distribute(1.0);
+
+    let network = power.network();
+    assert_eq!(network.blocks.len(), 2);
+    assert_eq!(network.generation, 100.0);
+    assert!(network.conductors.is_empty());
+
+    assert_eq!(power.supplied(pos(17, 0, 0)), 1.0);
+}
+
+#[test]
+fn consumers_are_supplied_by_priority() {
+    let (registry, blocks) = test_registry(block_definitions());
+    let blocks = TestBlocks::from(blocks);
+    // 100 W for a 60 W lamp and two 40 W heaters
+    let grid = grid_with(&[
+        (IVec3::new(0, 0, 0), blocks.generator),
+        (IVec3::new(1, 0, 0), blocks.heater),
+        (IVec3::new(2, 0, 0), blocks.lamp),
+        (IVec3::new(3, 0, 0), blocks.heater),
+    ]);
+
+    let mut power = GridPower::default();
+    power.update(&grid, &registry);
+    power.distribute(1.0);
+
+    assert_eq!(power.supplied(pos(2, 0, 0)), 1.0);
+    assert_eq!(power.supplied(pos(1, 0, 0)), 0.5);
+    assert_eq!(power.supplied(pos(3, 0, 0)), 0.5);
+    // Blocks that don't need power are always supplied
+    assert_eq!(power.supplied(pos(0, 0, 0)), 1.0);
+}
+
+#[test]
+fn batteries_store_spare_power() {
+    let (registry, blocks) = test_registry(block_definitions());
+    let blocks = TestBlocks::from(blocks);
+    let charging_grid = grid_with(&[
+        (IVec3::new(0, 0, 0), blocks.generator),
+        (IVec3::new(1, 0, 0), blocks.battery),
+        (IVec3::new(2, 0, 0), blocks.heater),
+    ]);
+    let draining_grid = grid_with(&[
+        (IVec3::new(0, 0, 0), blocks.battery),
+        (IVec3::new(1, 0, 0), blocks.lamp),
+    ]);
+
+    let mut charging = GridPower::default();
+    charging.update(&charging_grid, &registry);
+    charging.distribute(1.0);
+
+    // 60 W are spare, but the battery only charges at 50 W
+    assert_eq!(charging.charge(pos(1, 0, 0)), 50.0);
+
+    let mut draining = GridPower::default();
+    draining.update(&draining_grid, &registry);
+    draining.set_charge(pos(0, 0, 0), 100.0);
+    draining.distribute(1.0);
+
+    // The battery can only give 50 W of the 60 W the lamp needs
+    assert_eq!(draining.supplied(pos(1, 0, 0)), 50.0 / 60.0);
+    assert_eq!(draining.charge(pos(0, 0, 0)), 50.0);
+
+    draining.distribute(1.0);
+    draining.distribute(1.0);
+    assert_eq!(draining.charge(pos(0, 0, 0)), 0.0);
+    assert_eq!(draining.supplied(pos(1, 0, 0)), 0.0);
+}
+
+#[test]
+fn changed_blocks_update_the_network() {
+    let (registry, blocks) = test_registry(block_definitions());
+    let blocks = TestBlocks::from(blocks);
+    let mut grid = grid_with(&[
+        (IVec3::new(0, 0, 0), blocks.generator),
+        (IVec3::new(1, 0, 0), blocks.aluminum),
+        (IVec3::new(2, 0, 0), blocks.lamp),
+    ]);
+
+    let mut power = GridPower::default();
+    power.update(&grid, &registry);
+    assert_eq!(power.network().generation, 100.0);
+
+    set_block(&mut grid, IVec3::new(0, 1, 0), blocks.generator);
+    power.block_changed(pos(0, 1, 0));
+    power.update(&grid, &registry);
+    assert_eq!(power.network().generation, 200.0);
+    assert_eq!(power.network().blocks.len(), 3);
+
+    set_block(&mut grid, IVec3::new(0, 0, 0), Block::EMPTY);
+    set_block(&mut grid, IVec3::new(0, 1, 0), Block::EMPTY);
+    power.block_changed(pos(0, 0, 0));
+    power.block_changed(pos(0, 1, 0));
+    power.update(&grid, &registry);
+    assert_eq!(power.network().generation, 0.0);
+    assert_eq!(power.network().blocks.len(), 1);
+}
+
+#[test]
+fn conductors_link_touching_grids() {
+    let (registry, blocks) = test_registry(block_definitions());
+    let blocks = TestBlocks::from(blocks);
+    let powered_grid = grid_with(&[
+        (IVec3::new(0, 0, 0), blocks.generator),
+        (IVec3::new(1, 0, 0), blocks.conductor),
+    ]);
+    let unpowered_grid = grid_with(&[
+        (IVec3::new(0, 0, 0), blocks.conductor),
+        (IVec3::new(1, 0, 0), blocks.lamp),
+    ]);
+
+    let mut powered = GridPower::default();
+    powered.update(&powered_grid, &registry);
+    let mut unpowered = GridPower::default();
+    unpowered.update(&unpowered_grid, &registry);
+
+    let powered_transform = GlobalTransform::IDENTITY;
+    let touching = GlobalTransform::from_xyz(2.0 * BLOCK_SIZE, 0.0, 0.0);
+    let apart = GlobalTransform::from_xyz(3.0 * BLOCK_SIZE, 0.0, 0.0);
+    assert!(conductors_touch(
+        &powered_grid,
+        &powered_transform,
+        &powered.network().conductors,
+        &unpowered_grid,
+        &touching,
+        &registry,
+    ));
+    assert!(!conductors_touch(
+        &powered_grid,
+        &powered_transform,
+        &powered.network().conductors,
+        &unpowered_grid,
+        &apart,
+        &registry,
+    ));
+
+    share_power(&mut [&mut powered, &mut unpowered], 1.0);
+    assert_eq!(unpowered.supplied(pos(1, 0, 0)), 1.0);
+}
+
+#[test]
+fn removing_the_generator_cuts_the_power() {
+    let mut app = App::game_test();
+    let blocks = TestBlocks::from(register_blocks(
+        &mut app.world.resource_mut::<BlockRegistry>(),
+        block_definitions(),
+    ));
+
+    let grid = grid_with(&[
+        (IVec3::new(0, 0, 0), blocks.generator),
+        (IVec3::new(1, 0, 0), blocks.aluminum),
+        (IVec3::new(2, 0, 0), blocks.thruster),
+    ]);
+    let grid_entity = SpawnGrid::new(Transform::IDENTITY, grid).spawn(&mut app.world);
+    app.fixed_update();
+
+    let thruster_power = |app: &App| app.world.get::<Thrusters>(grid_entity).unwrap().0[0].power;
+    assert_eq!(thruster_power(&app), 1.0);
+
+    app.world.send_event(PlaceBlockRequest {
+        grid: grid_entity,
+        pos: pos(0, 0, 0),
+        block: Block::EMPTY,
+        record_history: false,
+    });
+    app.fixed_update();
+    app.fixed_update();
+
+    assert_eq!(thruster_power(&app), 0.0);
+}
+
+#[test]
+fn batteries_keep_their_charge_when_grids_split() {
+    let mut app = App::game_test();
+    let blocks = TestBlocks::from(register_blocks(
+        &mut app.world.resource_mut::<BlockRegistry>(),
+        block_definitions(),
+    ));
+
+    let grid = grid_with(&[
+        (IVec3::new(0, 0, 0), blocks.battery),
+        (IVec3::new(1, 0, 0), blocks.aluminum),
+        (IVec3::new(2, 0, 0), blocks.battery),
+    ]);
+    let grid_entity = SpawnGrid::new(Transform::IDENTITY, grid).spawn(&mut app.world);
+    app.fixed_update();
+
+    let mut power = app.world.get_mut::<GridPower>(grid_entity).unwrap();
+    power.set_charge(pos(0, 0, 0), 100.0);
+    power.set_charge(pos(2, 0, 0), 200.0);
+
+    app.world.send_event(PlaceBlockRequest {
+        grid: grid_entity,
+        pos: pos(1, 0, 0),
+        block: Block::EMPTY,
+        record_history: false,
+    });
+    app.fixed_update();
+    app.fixed_update();
+
+    let mut power_query = app.world.query::<&GridPower>();
+    let mut charges: Vec<f32> = power_query
+        .iter(&app.world)
+        .flat_map(|power| power.charges().map(|(_, charge)| charge))
+        .collect();
+    charges.sort_by(f32::total_cmp);
+
+    assert_eq!(power_query.iter(&app.world).count(), 2);
+    assert_eq!(charges, vec![100.0, 200.0]);
+}
@@ -0,0 +1,286 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+use space_game::building::events::PlaceBlockRequest;
+use space_game::grid::block::{Block, BLOCK_SIZE};
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::shape::{BlockShape, Orientation};
+use space_game::grid::{Grid, GridPos};
+use space_game::rooms::{RoomVented, Rooms};
+
+use crate::scaffolding::{register_blocks, test_registry, FixedUpdate, GameTest};
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Sets a block, adding an empty chunk for it if needed
+fn set_block(grid: &mut Grid, coords: IVec3, block: Block) {
+    let pos = GridPos::from_block_coords(coords);
+    if grid.get_chunk(pos.chunk_pos).is_none() {
+        grid.set_chunk(
+            pos.chunk_pos,
+            Some(Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY)),
+        );
+    }
+
+    grid.get_chunk_mut(pos.chunk_pos)
+        .unwrap()
+        .set_by_block_pos(pos.block_pos, block);
+}
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+const BLOCK_VOLUME: f32 = BLOCK_SIZE * BLOCK_SIZE * BLOCK_SIZE;
+
+// Returns the definitions of the aluminum and grating blocks
+fn block_definitions() -> [BlockDefinition; 2] {
+    [
+        BlockDefinition {
+            density: 2700.0,
+            ..BlockDefinition::new("aluminum", "Aluminum")
+        },
+        BlockDefinition {
+            collidable: false,
+            ..BlockDefinition::new("grating", "Grating")
+        },
+    ]
+}
+
+// Walls in the box between two corners
+fn hollow_box(grid: &mut Grid, min: IVec3, max: IVec3, wall: Block) {
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let coords = IVec3::new(x, y, z);
+                if coords.cmpeq(min).any() || coords.cmpeq(max).any() {
+                    set_block(grid, coords, wall);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn rooms_are_sealed_across_chunks() {
+    let (registry, [aluminum, grating]) = test_registry(block_definitions());
+    let mut grid = Grid::new();
+    hollow_box(&mut grid, IVec3::splat(14), IVec3::splat(18), aluminum);
+
+    let mut rooms = Rooms::default();
+    rooms.update(&grid, &registry);
+
+    assert_eq!(rooms.rooms().count(), 1);
+    let room = rooms.room_at(pos(15, 15, 15)).unwrap();
+    assert_eq!(room.volume(), 27.0 * BLOCK_VOLUME);
+    assert_eq!(room.pressure(), 1.0);
+    assert!(rooms.room_at(pos(19, 16, 16)).is_none());
+
+    // Air passes through blocks that don't collide
+    set_block(&mut grid, IVec3::new(14, 16, 16), grating);
+    let mut rooms = Rooms::default();
+    rooms.update(&grid, &registry);
+    assert_eq!(rooms.rooms().count(), 0);
+}
+
+#[test]
+fn removing_a_hull_block_vents_the_room() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let mut grid = Grid::new();
+    hollow_box(&mut grid, IVec3::splat(0), IVec3::splat(4), aluminum);
+
+    let mut rooms = Rooms::default();
+    rooms.update(&grid, &registry);
+
+    set_block(&mut grid, IVec3::new(0, 2, 2), Block::EMPTY);
+    rooms.block_changed(pos(0, 2, 2));
+    let vented = rooms.update(&grid, &registry);
+
+    assert_eq!(rooms.rooms().count(), 0);
+    assert_eq!(vented.len(), 1);
+    assert!((vented[0].1 - 27.0 * BLOCK_VOLUME).abs() < 1e-4);
+}
+
+#[test]
+fn half_block_in_a_wall_vents_the_room() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let mut grid = Grid::new();
+    hollow_box(&mut grid, IVec3::splat(0), IVec3::splat(4), aluminum);
+
+    let mut rooms = Rooms::default();
+    rooms.update(&grid, &registry);
+
+    // The half block only covers its bottom, so air escapes past it to either side
+    let half_block = aluminum.with_shape(BlockShape::HalfBlock, Orientation::default());
+    set_block(&mut grid, IVec3::new(0, 2, 2), half_block);
+    rooms.block_changed(pos(0, 2, 2));
+    let vented = rooms.update(&grid, &registry);
+
+    assert_eq!(rooms.rooms().count(), 0);
+    assert_eq!(vented.len(), 1);
+    assert!((vented[0].1 - 27.0 * BLOCK_VOLUME).abs() < 1e-4);
+}
+
+#[test]
+fn air_spreads_through_joined_rooms() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let mut grid = Grid::new();
+    hollow_box(
+        &mut grid,
+        IVec3::new(0, 0, 0),
+        IVec3::new(4, 4, 4),
+        aluminum,
+    );
+    for z in 0..=4 {
+        for y in 0..=4 {
+            set_block(&mut grid, IVec3::new(2, y, z), aluminum);
+        }
+    }
+
+    let mut rooms = Rooms::default();
+    rooms.update(&grid, &registry);
+    assert_eq!(rooms.rooms().count(), 2);
+
+    // Opening the left room and sealing it again leaves it empty
+    set_block(&mut grid, IVec3::new(0, 2, 2), Block::EMPTY);
+    rooms.block_changed(pos(0, 2, 2));
+    rooms.update(&grid, &registry);
+    set_block(&mut grid, IVec3::new(0, 2, 2), aluminum);
+    rooms.block_changed(pos(0, 2, 2));
+    rooms.update(&grid, &registry);
+    assert_eq!(rooms.room_at(pos(1, 2, 2)).unwrap().pressure(), 0.0);
+
+    // The air of the right room fills both rooms and the cell of the wall between them
+    set_block(&mut grid, IVec3::new(2, 2, 2), Block::EMPTY);
+    rooms.block_changed(pos(2, 2, 2));
+    let vented = rooms.update(&grid, &registry);
+
+    assert!(vented.is_empty());
+    assert_eq!(rooms.rooms().count(), 1);
+    let room = rooms.room_at(pos(1, 2, 2)).unwrap();
+    assert_eq!(room.volume(), 19.0 * BLOCK_VOLUME);
+    assert!((room.pressure() - 9.0 / 19.0).abs() < 1e-5);
+}
+
+#[test]
+fn breaching_a_ship_sends_an_event() {
+    let mut app = App::game_test();
+    let [aluminum, _] = register_blocks(
+        &mut app.world.resource_mut::<BlockRegistry>(),
+        block_definitions(),
+    );
+
+    let mut grid = Grid::new();
+    hollow_box(&mut grid, IVec3::splat(0), IVec3::splat(3), aluminum);
+    let grid_entity = spawn_grid(&mut app, SpawnGrid::new(Transform::IDENTITY, grid));
+    app.fixed_update();
+    assert_eq!(
+        app.world.get::<Rooms>(grid_entity).unwrap().rooms().count(),
+        1
+    );
+
+    let mut reader = app.world.resource::<Events<RoomVented>>().get_reader();
+    app.world.send_event(PlaceBlockRequest {
+        grid: grid_entity,
+        pos: pos(0, 1, 1),
+        block: Block::EMPTY,
+        record_history: false,
+    });
+
+    let mut vented = Vec::new();
+    for _ in 0..2 {
+        app.fixed_update();
+        let events = app.world.resource::<Events<RoomVented>>();
+        vented.extend(reader.read(events).map(|event| (event.grid, event.oxygen)));
+    }
+
+    assert_eq!(vented.len(), 1);
+    assert_eq!(vented[0].0, grid_entity);
+    assert!((vented[0].1 - 8.0 * BLOCK_VOLUME).abs() < 1e-4);
+    assert_eq!(
+        app.world.get::<Rooms>(grid_entity).unwrap().rooms().count(),
+        0
+    );
+}
+
+#[test]
+fn split_off_rooms_keep_their_air() {
+    let (registry, [aluminum, _]) = test_registry(block_definitions());
+    let mut grid = Grid::new();
+    hollow_box(&mut grid, IVec3::splat(0), IVec3::splat(4), aluminum);
+    hollow_box(&mut grid, IVec3::splat(10), IVec3::splat(14), aluminum);
+
+    let mut rooms = Rooms::default();
+    rooms.update(&grid, &registry);
+    let room = rooms.room_at_mut(pos(12, 12, 12)).unwrap();
+    room.oxygen = room.volume() / 2.0;
+
+    // The second box breaks off into a grid of its own
+    let mut new_grid = Grid::new();
+    hollow_box(&mut new_grid, IVec3::splat(10), IVec3::splat(14), aluminum);
+    let mut new_rooms = rooms.split_off(&new_grid);
+
+    assert!(new_rooms.update(&new_grid, &registry).is_empty());
+    assert_eq!(new_rooms.rooms().count(), 1);
+    let room = new_rooms.room_at(pos(12, 12, 12)).unwrap();
+    assert!((room.pressure() - 0.5).abs() < 1e-5);
+
+    assert_eq!(rooms.rooms().count(), 1);
+    assert!(rooms.room_at(pos(12, 12, 12)).is_none());
+}
+
+#[test]
+fn welded_rooms_keep_their_air() {
+    let mut app = App::game_test();
+    let [aluminum, _] = register_blocks(
+        &mut app.world.resource_mut::<BlockRegistry>(),
+        block_definitions(),
+    );
+
+    let mut grid = Grid::new();
+    hollow_box(&mut grid, IVec3::splat(0), IVec3::splat(4), aluminum);
+    let target = spawn_grid(&mut app, SpawnGrid::new(Transform::IDENTITY, grid));
+
+    let mut grid = Grid::new();
+    hollow_box(&mut grid, IVec3::splat(0), IVec3::splat(4), aluminum);
+    let source = spawn_grid(
+        &mut app,
+        SpawnGrid::new(Transform::from_xyz(6.0 * BLOCK_SIZE, 0.0, 0.0), grid),
+    );
+    app.fixed_update();
+
+    let mut rooms = app.world.get_mut::<Rooms>(source).unwrap();
+    let room = rooms.room_at_mut(pos(2, 2, 2)).unwrap();
+    room.oxygen = room.volume() / 2.0;
+
+    // A block between the two boxes welds the second box onto the first
+    app.world.send_event(PlaceBlockRequest {
+        grid: target,
+        pos: pos(5, 2, 2),
+        block: aluminum,
+        record_history: false,
+    });
+    app.fixed_update();
+    app.fixed_update();
+
+    assert!(app.world.get_entity(source).is_none());
+    let rooms = app.world.get::<Rooms>(target).unwrap();
+    assert_eq!(rooms.rooms().count(), 2);
+    assert!((rooms.room_at(pos(2, 2, 2)).unwrap().pressure() - 1.0).abs() < 1e-5);
+    assert!((rooms.room_at(pos(8, 2, 2)).unwrap().pressure() - 0.5).abs() < 1e-5);
+}
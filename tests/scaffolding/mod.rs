@@ -15,8 +15,28 @@ use bevy::scene::ScenePlugin;
 use big_space::{FloatingOrigin, FloatingOriginPlugin};
 use space_game::app_setup::{SetupGame, SetupMaterials};
 use space_game::fixed_update::{FixedUpdateSet, SetupFixedTimeStepSchedule, SetupRapier};
+use space_game::grid::block::Block;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
 use space_game::{UniverseGrid, UniverseGridPrecision, PHYSICS_TIMESTEP};
 
+// Registers the definitions and returns a block for each of them, in the same order
+pub fn register_blocks<const N: usize>(
+    registry: &mut BlockRegistry,
+    definitions: [BlockDefinition; N],
+) -> [Block; N] {
+    definitions.map(|definition| Block::new(registry.register(definition)))
+}
+
+// Returns a new registry with the definitions along with a block for each of them
+pub fn test_registry<const N: usize>(
+    definitions: [BlockDefinition; N],
+) -> (BlockRegistry, [Block; N]) {
+    let mut registry = BlockRegistry::new();
+    let blocks = register_blocks(&mut registry, definitions);
+
+    (registry, blocks)
+}
+
 pub trait SetupBevyPlugins {
     fn setup_bevy_plugins(&mut self) -> &mut Self;
 }
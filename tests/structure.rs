@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use space_game::grid::block::{Block, BLOCK_SIZE};
+use space_game::grid::chunk::Chunk;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::structure::find_weak_joints;
+use space_game::grid::{Grid, GridPos};
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Sets a block, adding an empty chunk for it if needed
+fn set_block(grid: &mut Grid, coords: IVec3, block: Block) {
+    let pos = GridPos::from_block_coords(coords);
+    if grid.get_chunk(pos.chunk_pos).is_none() {
+        grid.set_chunk(
+            pos.chunk_pos,
+            Some(Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY)),
+        );
+    }
+
+    grid.get_chunk_mut(pos.chunk_pos)
+        .unwrap()
+        .set_by_block_pos(pos.block_pos, block);
+}
+
+fn grid_with(blocks: &[(IVec3, Block)]) -> Grid {
+    let mut grid = Grid::new();
+    for &(coords, block) in blocks {
+        set_block(&mut grid, coords, block);
+    }
+
+    grid
+}
+
+const BLOCK_VOLUME: f32 = BLOCK_SIZE * BLOCK_SIZE * BLOCK_SIZE;
+
+// Returns the light and heavy blocks
+fn register_blocks(registry: &mut BlockRegistry) -> (Block, Block) {
+    let light = Block::new(registry.register(BlockDefinition {
+        density: 1000.0,
+        ..BlockDefinition::new("light", "Light")
+    }));
+    let heavy = Block::new(registry.register(BlockDefinition {
+        density: 3000.0,
+        ..BlockDefinition::new("heavy", "Heavy")
+    }));
+
+    (light, heavy)
+}
+
+fn assert_close(expected: f32, actual: f32) {
+    assert!(
+        (expected - actual).abs() <= expected.abs() * 1e-4,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn joints_carry_the_lighter_side() {
+    let mut registry = BlockRegistry::new();
+    let (light, heavy) = register_blocks(&mut registry);
+    // Crosses into the next chunk to check that joints are found across chunk boundaries
+    let grid = grid_with(&[
+        (IVec3::new(14, 0, 0), heavy),
+        (IVec3::new(15, 0, 0), light),
+        (IVec3::new(16, 0, 0), light),
+        (IVec3::new(17, 0, 0), light),
+    ]);
+
+    let mut joints = find_weak_joints(&grid, &registry);
+    joints.sort_by_key(|joint| joint.pos.to_block_coords().x);
+
+    // The blocks at the ends aren't holding anything
+    assert_eq!(2, joints.len());
+
+    assert_eq!(pos(15, 0, 0), joints[0].pos);
+    assert_close(2000.0 * BLOCK_VOLUME, joints[0].section_mass);
+
+    assert_eq!(pos(16, 0, 0), joints[1].pos);
+    assert_close(1000.0 * BLOCK_VOLUME, joints[1].section_mass);
+}
+
+#[test]
+fn solid_blocks_have_no_joints() {
+    let mut registry = BlockRegistry::new();
+    let (light, _) = register_blocks(&mut registry);
+    let mut blocks = Vec::new();
+    for z in 0..2 {
+        for y in 0..2 {
+            for x in 0..2 {
+                blocks.push((IVec3::new(x, y, z), light));
+            }
+        }
+    }
+
+    assert!(find_weak_joints(&grid_with(&blocks), &registry).is_empty());
+}
+
+#[test]
+fn block_joining_two_sections_is_a_joint() {
+    let mut registry = BlockRegistry::new();
+    let (light, heavy) = register_blocks(&mut registry);
+    // Two rings of blocks held together by a single block
+    let mut blocks = Vec::new();
+    for (x, y) in [(0, 0), (1, 0), (1, 1), (0, 1)] {
+        blocks.push((IVec3::new(x, y, 0), light));
+        blocks.push((IVec3::new(x + 3, y, 0), light));
+    }
+    blocks.push((IVec3::new(2, 0, 0), heavy));
+
+    let joints = find_weak_joints(&grid_with(&blocks), &registry);
+    let mut joint_positions: Vec<IVec3> = joints
+        .iter()
+        .map(|joint| joint.pos.to_block_coords())
+        .collect();
+    joint_positions.sort_by_key(|coords| coords.x);
+
+    // The rings only stay together through the heavy block and the blocks next to it
+    assert_eq!(
+        vec![
+            IVec3::new(1, 0, 0),
+            IVec3::new(2, 0, 0),
+            IVec3::new(3, 0, 0)
+        ],
+        joint_positions
+    );
+
+    let middle = joints
+        .iter()
+        .find(|joint| joint.pos == pos(2, 0, 0))
+        .unwrap();
+    assert_close(4000.0 * BLOCK_VOLUME, middle.section_mass);
+}
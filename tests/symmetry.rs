@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+
+use space_game::building::symmetry::{MirrorPlane, Symmetry};
+use space_game::grid::block::Block;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry};
+use space_game::grid::shape::{BlockShape, Orientation};
+use space_game::grid::GridPos;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+#[test]
+fn plane_through_block_maps_it_onto_itself() {
+    let plane = MirrorPlane::through_block(3);
+
+    assert_eq!(3, plane.mirror(3));
+    assert_eq!(1, plane.mirror(5));
+    assert_eq!(-4, plane.mirror(10));
+}
+
+#[test]
+fn plane_after_block_swaps_neighbors() {
+    let plane = MirrorPlane::after_block(3);
+
+    assert_eq!(4, plane.mirror(3));
+    assert_eq!(3, plane.mirror(4));
+    assert_eq!(-1, plane.mirror(8));
+}
+
+#[test]
+fn no_planes_only_keep_the_position() {
+    assert_eq!(
+        vec![pos(1, 2, 3)],
+        Symmetry::default().mirrored_positions(pos(1, 2, 3))
+    );
+}
+
+#[test]
+fn every_combination_of_planes_is_mirrored() {
+    let symmetry = Symmetry {
+        x: Some(MirrorPlane::after_block(-1)),
+        y: None,
+        z: Some(MirrorPlane::through_block(0)),
+    };
+
+    let positions = symmetry.mirrored_positions(pos(2, 5, 20));
+    assert_eq!(4, positions.len());
+    for expected in [
+        pos(2, 5, 20),
+        pos(-3, 5, 20),
+        pos(2, 5, -20),
+        pos(-3, 5, -20),
+    ] {
+        assert!(positions.contains(&expected));
+    }
+}
+
+#[test]
+fn positions_on_a_plane_are_not_duplicated() {
+    let symmetry = Symmetry {
+        x: Some(MirrorPlane::through_block(4)),
+        y: Some(MirrorPlane::through_block(0)),
+        z: None,
+    };
+
+    assert_eq!(
+        vec![pos(4, 0, 7)],
+        symmetry.mirrored_positions(pos(4, 0, 7))
+    );
+    assert_eq!(2, symmetry.mirrored_positions(pos(4, 1, 7)).len());
+}
+
+#[test]
+fn mirrored_slopes_face_the_other_way() {
+    let mut registry = BlockRegistry::new();
+    let aluminum = Block::new(registry.register(BlockDefinition::new("aluminum", "Aluminum")));
+    let slope = aluminum.with_shape(BlockShape::Slope, Orientation::default());
+
+    let symmetry = Symmetry {
+        z: Some(MirrorPlane::after_block(0)),
+        ..Default::default()
+    };
+    let blocks = symmetry.mirrored_blocks(pos(0, 0, 0), slope, &registry);
+    assert_eq!(2, blocks.len());
+    assert_eq!((pos(0, 0, 0), slope), blocks[0]);
+
+    // The full side of the slope faces away from the plane on both sides of it
+    let (mirrored_pos, mirrored) = blocks[1];
+    assert_eq!(pos(0, 0, 1), mirrored_pos);
+    assert_eq!(BlockShape::Slope, mirrored.shape);
+    assert_eq!(IVec3::NEG_Z, slope.orientation.rotate(IVec3::NEG_Z));
+    assert_eq!(IVec3::Z, mirrored.orientation.rotate(IVec3::NEG_Z));
+    assert_eq!(IVec3::Y, mirrored.orientation.rotate(IVec3::Y));
+
+    // Slopes are symmetric across planes along their incline
+    let symmetry = Symmetry {
+        x: Some(MirrorPlane::after_block(0)),
+        ..Default::default()
+    };
+    assert_eq!(
+        slope,
+        symmetry.mirrored_blocks(pos(0, 0, 0), slope, &registry)[1].1
+    );
+}
+
+#[test]
+fn areas_across_a_plane_are_not_duplicated() {
+    let registry = BlockRegistry::new();
+    let symmetry = Symmetry {
+        x: Some(MirrorPlane::after_block(-1)),
+        y: None,
+        z: Some(MirrorPlane::after_block(10)),
+    };
+
+    // The area is its own mirror image across the X plane
+    let areas = symmetry.mirrored_areas(pos(-3, 0, 0), pos(2, 1, 1), Block::EMPTY, &registry);
+    assert_eq!(2, areas.len());
+    assert_eq!((pos(-3, 0, 0), pos(2, 1, 1)), (areas[0].0, areas[0].1));
+    assert_eq!((pos(-3, 0, 21), pos(2, 1, 20)), (areas[1].0, areas[1].1));
+}
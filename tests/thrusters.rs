@@ -0,0 +1,127 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use space_game::grid::block::Block;
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{BlockDefinition, BlockRegistry, ThrusterDefinition};
+use space_game::grid::shape::{BlockShape, Orientation};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+use space_game::thruster::{find_thrusters, ThrusterControl};
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+fn pos(x: i32, y: i32, z: i32) -> GridPos {
+    GridPos::from_block_coords(IVec3::new(x, y, z))
+}
+
+// Spawns a grid and returns its entity
+fn spawn_grid(app: &mut App, spawn_grid: SpawnGrid) -> Entity {
+    let mut grid_query = app.world.query_filtered::<Entity, With<Grid>>();
+    let existing: Vec<Entity> = grid_query.iter(&app.world).collect();
+
+    spawn_grid.apply(&mut app.world);
+
+    grid_query
+        .iter(&app.world)
+        .find(|entity| !existing.contains(entity))
+        .unwrap()
+}
+
+// Returns the aluminum and thruster blocks
+fn register_blocks(registry: &mut BlockRegistry) -> (Block, Block) {
+    let aluminum = Block::new(registry.register(BlockDefinition {
+        density: 2700.0,
+        ..BlockDefinition::new("aluminum", "Aluminum")
+    }));
+    let thruster = Block::new(registry.register(BlockDefinition {
+        density: 2700.0,
+        thruster: Some(ThrusterDefinition {
+            max_thrust: 10000.0,
+        }),
+        ..BlockDefinition::new("thruster", "Thruster")
+    }));
+
+    (aluminum, thruster)
+}
+
+// A row of aluminum blocks along the X axis with a thruster at one end
+fn test_grid(aluminum: Block, thruster: Block) -> Grid {
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    for x in 0..4 {
+        chunk.set(x, 0, 0, aluminum);
+    }
+    chunk.set(4, 0, 0, thruster);
+
+    let mut grid = Grid::new();
+    grid.set_chunk(ChunkPos::new(0, 0, 0), Some(chunk));
+
+    grid
+}
+
+#[test]
+fn cube_thrusters_keep_their_orientation() {
+    let mut registry = BlockRegistry::new();
+    let (aluminum, thruster) = register_blocks(&mut registry);
+    let orientation = Orientation::default().rotated(0);
+
+    let oriented = registry.orient(thruster, BlockShape::Cube, orientation);
+    assert_eq!(oriented.orientation, orientation);
+
+    // Other cubes still look the same in every orientation
+    let oriented = registry.orient(aluminum, BlockShape::Cube, orientation);
+    assert_eq!(oriented, aluminum);
+}
+
+#[test]
+fn thrusters_push_towards_their_top() {
+    let mut registry = BlockRegistry::new();
+    let (aluminum, thruster) = register_blocks(&mut registry);
+
+    // A quarter turn around the X axis turns the top towards Z
+    let thruster = registry.orient(
+        thruster,
+        BlockShape::Cube,
+        Orientation::default().rotated(0),
+    );
+    let thrusters = find_thrusters(&test_grid(aluminum, thruster), &registry);
+
+    assert_eq!(thrusters.len(), 1);
+    assert_eq!(thrusters[0].pos, pos(4, 0, 0));
+    assert_eq!(thrusters[0].direction, IVec3::Z);
+    assert_eq!(thrusters[0].max_thrust, 10000.0);
+}
+
+#[test]
+fn thrusters_off_the_center_of_mass_turn_the_grid() {
+    let mut app = App::game_test();
+    let (aluminum, thruster) = register_blocks(&mut app.world.resource_mut::<BlockRegistry>());
+
+    let grid_entity = spawn_grid(
+        &mut app,
+        SpawnGrid::new(Transform::IDENTITY, test_grid(aluminum, thruster)),
+    );
+
+    // Thrusters only fire when they are told to
+    for _ in 0..4 {
+        app.fixed_update();
+    }
+    let velocity = app.world.get::<Velocity>(grid_entity).unwrap();
+    assert_eq!(velocity.linvel, Vec3::ZERO);
+
+    app.world
+        .get_mut::<ThrusterControl>(grid_entity)
+        .unwrap()
+        .linear = Vec3::Y;
+    for _ in 0..4 {
+        app.fixed_update();
+    }
+
+    // The thruster pushes the end of the row up, turning it around the Z axis
+    let velocity = app.world.get::<Velocity>(grid_entity).unwrap();
+    assert!(velocity.linvel.y > 0.0);
+    assert!(velocity.angvel.z > 0.0);
+}
@@ -0,0 +1,280 @@
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use big_space::FloatingOrigin;
+
+use space_game::camera::ActiveCamera;
+use space_game::cockpit::{EnterCockpit, Piloted, Seated};
+use space_game::grid::block::Block;
+use space_game::grid::chunk::Chunk;
+use space_game::grid::command::SpawnGrid;
+use space_game::grid::registry::{BatteryDefinition, BlockDefinition, BlockRegistry};
+use space_game::grid::{ChunkPos, Grid, GridPos};
+use space_game::player::{Player, SpawnPlayer};
+use space_game::player_camera::PlayerCamera;
+use space_game::player_controller::ActivelyControlled;
+use space_game::power::GridPower;
+use space_game::rooms::Rooms;
+use space_game::save::world::{read_world, write_world, WorldData};
+use space_game::settings::Settings;
+use space_game::UniverseGrid;
+
+use crate::scaffolding::{FixedUpdate, GameTest};
+
+mod scaffolding;
+
+fn populate_world(app: &mut App) {
+    let aluminum = Block::new(
+        app.world
+            .resource_mut::<BlockRegistry>()
+            .register(BlockDefinition::new("aluminum", "Aluminum")),
+    );
+
+    let mut grid = Grid::new();
+    grid.set_chunk(
+        ChunkPos::new(0, 0, 0),
+        Some(Chunk::filled(Entity::PLACEHOLDER, aluminum)),
+    );
+    grid.set_chunk(
+        ChunkPos::new(1, 0, 0),
+        Some(Chunk::filled(Entity::PLACEHOLDER, aluminum)),
+    );
+
+    SpawnGrid {
+        transform: Transform::from_xyz(1.0, 2.0, 3.0),
+        grid_cell: UniverseGrid::new(4, 5, 6),
+        velocity: Velocity {
+            linvel: Vec3::X,
+            angvel: Vec3::Y,
+        },
+        grid,
+    }
+    .apply(&mut app.world);
+
+    SpawnPlayer {
+        camera_transform: Transform::from_xyz(0.0, 0.95, 0.0)
+            .with_rotation(Quat::from_rotation_x(0.5)),
+        ..SpawnPlayer::new(
+            Transform::from_xyz(10.0, 0.0, 0.0),
+            UniverseGrid::new(-1, 0, 0),
+        )
+    }
+    .apply(&mut app.world);
+
+    SpawnPlayer {
+        take_control: false,
+        ..SpawnPlayer::new(
+            Transform::from_xyz(-10.0, 0.0, 0.0),
+            UniverseGrid::new(2, 0, 0),
+        )
+    }
+    .apply(&mut app.world);
+
+    app.world.resource_mut::<Settings>().camera_speed = 3.0;
+}
+
+fn save_and_load(app: &mut App) {
+    let world_data = WorldData::capture(&mut app.world).unwrap();
+
+    let mut bytes = Vec::new();
+    write_world(&mut bytes, &world_data).unwrap();
+
+    // Change the world so that loading has something to undo
+    app.world.resource_mut::<Settings>().camera_speed = 100.0;
+
+    read_world(bytes.as_slice())
+        .unwrap()
+        .restore(&mut app.world)
+        .unwrap();
+}
+
+#[test]
+fn grids_are_restored_with_meshes_and_colliders() {
+    let mut app = App::game_test();
+    populate_world(&mut app);
+
+    save_and_load(&mut app);
+
+    let mut grid_query = app
+        .world
+        .query::<(&Grid, &Transform, &UniverseGrid, &Velocity, &Children)>();
+    let grids: Vec<_> = grid_query.iter(&app.world).collect();
+    assert_eq!(1, grids.len());
+
+    let (grid, transform, cell, velocity, children) = grids[0];
+    assert_eq!(2, grid.chunks().count());
+    assert_eq!(Vec3::new(1.0, 2.0, 3.0), transform.translation);
+    assert_eq!(UniverseGrid::new(4, 5, 6), *cell);
+    assert_eq!(Vec3::X, velocity.linvel);
+    assert_eq!(Vec3::Y, velocity.angvel);
+
+    assert_eq!(2, children.len());
+    let chunk_entities: Vec<Entity> = grid.chunks().map(|(_, chunk)| chunk.entity).collect();
+    for &entity in chunk_entities.iter() {
+        assert!(children.contains(&entity));
+        // Chunks collide straight away, before their real collider has been generated
+        assert!(app.world.get::<Collider>(entity).is_some());
+    }
+
+    // Meshes are generated in the background
+    for _ in 0..100 {
+        if chunk_entities
+            .iter()
+            .all(|&entity| app.world.get::<Handle<Mesh>>(entity).is_some())
+        {
+            break;
+        }
+
+        app.fixed_update();
+    }
+
+    for entity in chunk_entities {
+        assert!(app.world.get::<Handle<Mesh>>(entity).is_some());
+        assert!(app.world.get::<Collider>(entity).is_some());
+    }
+}
+
+#[test]
+fn players_and_control_are_restored() {
+    let mut app = App::game_test();
+    populate_world(&mut app);
+
+    save_and_load(&mut app);
+
+    let mut player_query = app.world.query_filtered::<&UniverseGrid, With<Player>>();
+    assert_eq!(2, player_query.iter(&app.world).count());
+
+    let mut controlled_query = app
+        .world
+        .query_filtered::<(Entity, &UniverseGrid), With<ActivelyControlled>>();
+    let (controlled, cell) = controlled_query.single(&app.world);
+    assert_eq!(UniverseGrid::new(-1, 0, 0), *cell);
+
+    let mut origin_query = app.world.query_filtered::<Entity, With<FloatingOrigin>>();
+    assert_eq!(controlled, origin_query.single(&app.world));
+
+    let mut camera_query = app
+        .world
+        .query_filtered::<(Entity, &Parent, &Camera, &Transform), With<ActiveCamera>>();
+    let (camera_entity, parent, camera, camera_transform) = camera_query.single(&app.world);
+    assert!(app.world.get::<PlayerCamera>(camera_entity).is_some());
+    assert_eq!(controlled, parent.get());
+    assert!(camera.is_active);
+    assert_eq!(Quat::from_rotation_x(0.5), camera_transform.rotation);
+}
+
+#[test]
+fn settings_are_restored() {
+    let mut app = App::game_test();
+    populate_world(&mut app);
+
+    save_and_load(&mut app);
+
+    assert_eq!(3.0, app.world.resource::<Settings>().camera_speed);
+}
+
+#[test]
+fn game_runs_after_loading() {
+    let mut app = App::game_test();
+    populate_world(&mut app);
+    app.fixed_update();
+
+    save_and_load(&mut app);
+    app.fixed_update();
+    app.fixed_update();
+
+    let mut grid_query = app.world.query::<&Grid>();
+    assert_eq!(1, grid_query.iter(&app.world).count());
+}
+
+#[test]
+fn pilots_are_restored_to_their_cockpits() {
+    let mut app = App::game_test();
+    populate_world(&mut app);
+    let cockpit = Block::new(
+        app.world
+            .resource_mut::<BlockRegistry>()
+            .register(BlockDefinition {
+                cockpit: true,
+                ..BlockDefinition::new("cockpit", "Cockpit")
+            }),
+    );
+
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    chunk.set(0, 0, 0, cockpit);
+    let mut grid = Grid::new();
+    grid.set_chunk(ChunkPos::new(0, 0, 0), Some(chunk));
+    let ship = SpawnGrid::new(Transform::IDENTITY, grid).spawn(&mut app.world);
+
+    let pilot = app
+        .world
+        .query_filtered::<Entity, (With<Player>, With<ActivelyControlled>)>()
+        .single(&app.world);
+    EnterCockpit {
+        player: pilot,
+        grid: ship,
+        cockpit: GridPos::from_block_coords(IVec3::ZERO),
+    }
+    .apply(&mut app.world);
+
+    save_and_load(&mut app);
+
+    let mut piloted_query = app
+        .world
+        .query_filtered::<(Entity, &Piloted), With<ActivelyControlled>>();
+    let (ship, piloted) = piloted_query.single(&app.world);
+    assert_eq!(GridPos::from_block_coords(IVec3::ZERO), piloted.cockpit);
+    assert_eq!(ship, app.world.get::<Seated>(piloted.pilot).unwrap().grid);
+}
+
+#[test]
+fn battery_charge_and_air_are_restored() {
+    let mut app = App::game_test();
+    let (aluminum, battery) = {
+        let mut registry = app.world.resource_mut::<BlockRegistry>();
+        let aluminum = Block::new(registry.register(BlockDefinition::new("aluminum", "Aluminum")));
+        let battery = Block::new(registry.register(BlockDefinition {
+            battery: Some(BatteryDefinition {
+                capacity: 1000.0,
+                max_power: 50.0,
+            }),
+            ..BlockDefinition::new("battery", "Battery")
+        }));
+
+        (aluminum, battery)
+    };
+
+    // A hollow box with a battery in one of its walls
+    let mut chunk = Chunk::filled(Entity::PLACEHOLDER, Block::EMPTY);
+    for z in 0..5 {
+        for y in 0..5 {
+            for x in 0..5 {
+                if [x, y, z].iter().any(|&coord| coord == 0 || coord == 4) {
+                    chunk.set(x, y, z, aluminum);
+                }
+            }
+        }
+    }
+    chunk.set(0, 2, 2, battery);
+    let mut grid = Grid::new();
+    grid.set_chunk(ChunkPos::new(0, 0, 0), Some(chunk));
+    let ship = SpawnGrid::new(Transform::IDENTITY, grid).spawn(&mut app.world);
+    app.fixed_update();
+
+    let battery_pos = GridPos::from_block_coords(IVec3::new(0, 2, 2));
+    let room_pos = GridPos::from_block_coords(IVec3::splat(2));
+    app.world
+        .get_mut::<GridPower>(ship)
+        .unwrap()
+        .set_charge(battery_pos, 300.0);
+    let mut rooms = app.world.get_mut::<Rooms>(ship).unwrap();
+    let room = rooms.room_at_mut(room_pos).unwrap();
+    room.oxygen = room.volume() / 4.0;
+
+    save_and_load(&mut app);
+    app.fixed_update();
+
+    let (power, rooms) = app.world.query::<(&GridPower, &Rooms)>().single(&app.world);
+    assert_eq!(300.0, power.charge(battery_pos));
+    assert!((rooms.room_at(room_pos).unwrap().pressure() - 0.25).abs() < 1e-5);
+}